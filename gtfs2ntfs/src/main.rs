@@ -23,7 +23,10 @@ use tracing_subscriber::{
     layer::SubscriberExt as _,
     util::SubscriberInitExt as _,
 };
-use transit_model::{configuration, transfers::generates_transfers, PrefixConfiguration, Result};
+use transit_model::{
+    configuration, gtfs::StopTimesOrder, transfers::generates_transfers, PrefixConfiguration,
+    Result,
+};
 
 lazy_static::lazy_static! {
     pub static ref GIT_VERSION: String = transit_model::binary_full_version(env!("CARGO_PKG_VERSION"));
@@ -74,6 +77,12 @@ struct Opt {
     #[arg(long = "read-as-line")]
     read_as_line: bool,
 
+    /// What to do with the trips whose departure times go backwards along
+    /// the stop sequence: 'warn', 'repair' (clamp them to the previous
+    /// departure time) or 'strict' (fail).
+    #[arg(long = "stop-times-order", default_value = "warn")]
+    stop_times_order: StopTimesOrder,
+
     /// Current datetime.
     #[arg(
         short = 'x',
@@ -119,6 +128,8 @@ fn run(opt: Opt) -> Result<()> {
         on_demand_transport: opt.odt,
         on_demand_transport_comment: opt.odt_comment,
         read_as_line: opt.read_as_line,
+        stop_times_order: opt.stop_times_order,
+        calendar_configuration: Default::default(),
     };
 
     let model = transit_model::gtfs::Reader::new(configuration).parse(opt.input)?;
@@ -201,11 +201,14 @@ impl AddPrefix for Collections {
         self.ticket_use_restrictions.prefix(prefix_conf);
         self.pathways.prefix(prefix_conf);
         self.levels.prefix(prefix_conf);
+        self.attributions.prefix(prefix_conf);
         self.grid_calendars.prefix(prefix_conf);
         self.grid_exception_dates.prefix(prefix_conf);
         self.grid_periods.prefix(prefix_conf);
         self.grid_rel_calendar_line.prefix(prefix_conf);
         self.occupancies.prefix(prefix_conf);
+        self.timeframes.prefix(prefix_conf);
+        self.translations.prefix(prefix_conf);
         self.stop_time_headsigns =
             add_prefix_on_vehicle_journey_ids(&self.stop_time_headsigns, prefix_conf);
         self.stop_time_ids =
@@ -19,17 +19,29 @@
 
 use crate::file_handler::FileHandler;
 use crate::model::Collections;
-use crate::objects::{self, Date, ExceptionType};
-use crate::parser::read_objects;
+use crate::objects::{self, CalendarMetadata, Date, ExceptionType, ValidityPeriod};
+use crate::parser::{read_objects_with_options, ReadOptions};
 use crate::serde_utils::*;
-use crate::vptranslator::translate;
-use crate::Result;
+use crate::utils::FileWriter;
+use crate::vptranslator::{translate, ExceptionDate};
+use crate::{Model, Result};
 use anyhow::{anyhow, bail, Context};
-use chrono::{self, Datelike, Days, Weekday};
+use chrono::{
+    self,
+    format::{Item, StrftimeItems},
+    Datelike, Days, Weekday,
+};
+use derivative::Derivative;
+use relational_types::IdxSet;
 use serde::{Deserialize, Serialize};
 use skip_error::skip_error_and_warn;
-use std::{collections::BTreeSet, convert::TryFrom, path};
-use tracing::info;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    io, path,
+    sync::Arc,
+};
+use tracing::{info, warn};
 use typed_index_collection::*;
 
 /// Structure to serialize/deserialize the file calendar_dates.txt
@@ -48,31 +60,52 @@ pub struct CalendarDate {
 }
 
 /// Structure to serialize/deserialize the file calendar.txt
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Calendar {
     /// Identifiers of the Service
     #[serde(rename = "service_id")]
     id: String,
     /// True if the Service is active on Mondays
-    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    #[serde(
+        deserialize_with = "de_from_u8_or_float",
+        serialize_with = "ser_from_bool"
+    )]
     monday: bool,
     /// True if the Service is active on Tuesdays
-    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    #[serde(
+        deserialize_with = "de_from_u8_or_float",
+        serialize_with = "ser_from_bool"
+    )]
     tuesday: bool,
     /// True if the Service is active on Wednesdays
-    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    #[serde(
+        deserialize_with = "de_from_u8_or_float",
+        serialize_with = "ser_from_bool"
+    )]
     wednesday: bool,
     /// True if the Service is active on Thursdays
-    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    #[serde(
+        deserialize_with = "de_from_u8_or_float",
+        serialize_with = "ser_from_bool"
+    )]
     thursday: bool,
     /// True if the Service is active on Fridays
-    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    #[serde(
+        deserialize_with = "de_from_u8_or_float",
+        serialize_with = "ser_from_bool"
+    )]
     friday: bool,
     /// True if the Service is active on Saturdays
-    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    #[serde(
+        deserialize_with = "de_from_u8_or_float",
+        serialize_with = "ser_from_bool"
+    )]
     saturday: bool,
     /// True if the Service is active on Sundays
-    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    #[serde(
+        deserialize_with = "de_from_u8_or_float",
+        serialize_with = "ser_from_bool"
+    )]
     sunday: bool,
     /// The Service is active starting from this date
     #[serde(
@@ -86,6 +119,60 @@ pub struct Calendar {
         serialize_with = "ser_from_naive_date"
     )]
     end_date: Date,
+    /// Non-standard human-readable description of the Service
+    #[serde(
+        rename = "service_desc",
+        alias = "service_name",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    service_desc: Option<String>,
+}
+
+/// Row of calendar.txt read with
+/// [`ReadConfiguration::flexible_weekdays`], its weekday flags accepting
+/// boolean-ish values
+#[derive(Deserialize)]
+struct FlexibleCalendar {
+    service_id: String,
+    #[serde(deserialize_with = "de_from_bool_flexible")]
+    monday: bool,
+    #[serde(deserialize_with = "de_from_bool_flexible")]
+    tuesday: bool,
+    #[serde(deserialize_with = "de_from_bool_flexible")]
+    wednesday: bool,
+    #[serde(deserialize_with = "de_from_bool_flexible")]
+    thursday: bool,
+    #[serde(deserialize_with = "de_from_bool_flexible")]
+    friday: bool,
+    #[serde(deserialize_with = "de_from_bool_flexible")]
+    saturday: bool,
+    #[serde(deserialize_with = "de_from_bool_flexible")]
+    sunday: bool,
+    #[serde(deserialize_with = "de_from_date_string")]
+    start_date: Date,
+    #[serde(deserialize_with = "de_from_date_string")]
+    end_date: Date,
+    #[serde(rename = "service_desc", alias = "service_name", default)]
+    service_desc: Option<String>,
+}
+
+impl From<FlexibleCalendar> for Calendar {
+    fn from(calendar: FlexibleCalendar) -> Self {
+        Calendar {
+            id: calendar.service_id,
+            monday: calendar.monday,
+            tuesday: calendar.tuesday,
+            wednesday: calendar.wednesday,
+            thursday: calendar.thursday,
+            friday: calendar.friday,
+            saturday: calendar.saturday,
+            sunday: calendar.sunday,
+            start_date: calendar.start_date,
+            end_date: calendar.end_date,
+            service_desc: calendar.service_desc,
+        }
+    }
 }
 
 impl Calendar {
@@ -117,82 +204,566 @@ impl Calendar {
     }
 
     fn get_valid_dates(&self) -> BTreeSet<Date> {
-        let valid_days = self.get_valid_days();
-        let duration = self.end_date - self.start_date;
-        (0..=duration.num_days())
-            .filter_map(|i| {
-                u64::try_from(i)
-                    .ok()
-                    .map(|i| self.start_date + Days::new(i))
-            })
-            .filter(|d| valid_days.contains(&d.weekday()))
-            .collect()
+        weekly_dates(self.start_date, self.end_date, &self.get_valid_days())
+    }
+}
+
+/// Row of calendar_dates.txt as written, its date formatted with the
+/// `date_format` of the [`WriteConfiguration`]
+#[derive(Serialize)]
+struct CalendarDateRow<'a> {
+    service_id: &'a str,
+    date: String,
+    exception_type: &'a ExceptionType,
+}
+
+impl CalendarDate {
+    fn to_row(&self, date_format: &str) -> CalendarDateRow<'_> {
+        CalendarDateRow {
+            service_id: &self.service_id,
+            date: self.date.format(date_format).to_string(),
+            exception_type: &self.exception_type,
+        }
+    }
+}
+
+/// Row of calendar.txt as written, its dates formatted with the
+/// `date_format` of the [`WriteConfiguration`]
+#[derive(Serialize)]
+struct CalendarRow<'a> {
+    service_id: &'a str,
+    #[serde(serialize_with = "ser_from_bool")]
+    monday: bool,
+    #[serde(serialize_with = "ser_from_bool")]
+    tuesday: bool,
+    #[serde(serialize_with = "ser_from_bool")]
+    wednesday: bool,
+    #[serde(serialize_with = "ser_from_bool")]
+    thursday: bool,
+    #[serde(serialize_with = "ser_from_bool")]
+    friday: bool,
+    #[serde(serialize_with = "ser_from_bool")]
+    saturday: bool,
+    #[serde(serialize_with = "ser_from_bool")]
+    sunday: bool,
+    start_date: String,
+    end_date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_desc: Option<&'a str>,
+}
+
+impl Calendar {
+    // `with_description` writes an empty description for the services
+    // without one, so that all the rows have the same columns
+    fn to_row(&self, date_format: &str, with_description: bool) -> CalendarRow<'_> {
+        CalendarRow {
+            service_id: &self.id,
+            monday: self.monday,
+            tuesday: self.tuesday,
+            wednesday: self.wednesday,
+            thursday: self.thursday,
+            friday: self.friday,
+            saturday: self.saturday,
+            sunday: self.sunday,
+            start_date: self.start_date.format(date_format).to_string(),
+            end_date: self.end_date.format(date_format).to_string(),
+            service_desc: match self.service_desc.as_deref() {
+                None if with_description => Some(""),
+                service_desc => service_desc,
+            },
+        }
+    }
+}
+
+/// Dates between `start_date` and `end_date` (both included) falling on one
+/// of the `days`
+pub(crate) fn weekly_dates(start_date: Date, end_date: Date, days: &[Weekday]) -> BTreeSet<Date> {
+    // Offsets of the valid days from the weekday of the start date, sorted
+    // so that the dates of each week are generated in order
+    let start_weekday = start_date.weekday().num_days_from_monday();
+    let mut offsets: Vec<u64> = days
+        .iter()
+        .map(|day| u64::from((day.num_days_from_monday() + 7 - start_weekday) % 7))
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+    // Only the active dates are generated: collecting the (sorted) dates
+    // in a Vec lets the BTreeSet be bulk-built instead of inserting dates
+    // one at a time
+    let mut dates = vec![];
+    let mut week_start = start_date;
+    'weeks: while week_start <= end_date && !offsets.is_empty() {
+        for offset in &offsets {
+            let date = week_start + Days::new(*offset);
+            if date > end_date {
+                break 'weeks;
+            }
+            dates.push(date);
+        }
+        week_start = week_start + Days::new(7);
+    }
+    dates.into_iter().collect()
+}
+
+/// Row of calendar_dates.txt with its date kept as read
+#[derive(Deserialize)]
+struct RawCalendarDate {
+    service_id: String,
+    date: String,
+    exception_type: ExceptionType,
+}
+
+// YYYYMMDD dates have 8 digits: smaller integers are Excel serial dates
+const EXCEL_SERIAL_DATE_LIMIT: u32 = 10_000_000;
+
+/// Convert an Excel serial date (1900 date system) to a date. Excel wrongly
+/// considers 1900 as a leap year: 60 is the nonexistent 1900-02-29 and the
+/// serials before it are shifted by one day.
+fn excel_serial_to_date(serial: u32) -> Result<Date> {
+    let epoch = match serial {
+        0 => bail!("Excel serial date 0 is not a valid date"),
+        1..=59 => Date::from_ymd_opt(1899, 12, 31),
+        60 => bail!("Excel serial date 60 is the nonexistent 1900-02-29"),
+        _ => Date::from_ymd_opt(1899, 12, 30),
+    }
+    .ok_or_else(|| anyhow!("invalid Excel epoch"))?;
+    epoch
+        .checked_add_days(Days::new(u64::from(serial)))
+        .ok_or_else(|| anyhow!("Excel serial date {} is out of range", serial))
+}
+
+impl TryFrom<RawCalendarDate> for CalendarDate {
+    type Error = anyhow::Error;
+    fn try_from(raw_calendar_date: RawCalendarDate) -> Result<Self> {
+        let date = match raw_calendar_date.date.parse::<u32>() {
+            Ok(serial) if serial < EXCEL_SERIAL_DATE_LIMIT => excel_serial_to_date(serial)?,
+            _ => Date::parse_from_str(&raw_calendar_date.date, "%Y%m%d").with_context(|| {
+                format!(
+                    "invalid date {:?} for service {}",
+                    raw_calendar_date.date, raw_calendar_date.service_id
+                )
+            })?,
+        };
+        Ok(CalendarDate {
+            service_id: raw_calendar_date.service_id,
+            date,
+            exception_type: raw_calendar_date.exception_type,
+        })
+    }
+}
+
+/// Normalization applied to the service ids read from calendar.txt and
+/// calendar_dates.txt, and to the service ids referencing them (trips and
+/// time frames)
+pub type ServiceIdNormalizer = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Options used when reading calendar.txt and calendar_dates.txt
+#[derive(Derivative, Default, Clone)]
+#[derivative(Debug)]
+pub struct ReadConfiguration {
+    /// When set, each date of the calendars records this source tag
+    /// (see [`objects::Calendar::date_source`]). Disabled by default to avoid
+    /// the memory overhead.
+    pub provenance: Option<String>,
+    /// When a calendar.txt row has its end_date before its start_date, swap
+    /// both dates instead of failing
+    pub fix_swapped_dates: bool,
+    /// When a calendar.txt row has its end_date before its start_date, log a
+    /// warning and skip the row instead of failing (ignored when
+    /// `fix_swapped_dates` is set)
+    pub skip_inverted_dates: bool,
+    /// Applied to the service ids of both files before matching them, for
+    /// example to strip an agency prefix only present in one of them, and
+    /// to the service ids of the trips and time frames.
+    /// Service ids are kept as is when not set.
+    #[derivative(Debug = "ignore")]
+    pub service_id_normalizer: Option<ServiceIdNormalizer>,
+    /// Encoding of the files, transcoded to UTF-8 before parsing, for example
+    /// `encoding_rs::WINDOWS_1252` for Latin-1 feeds. UTF-8 when not set.
+    pub encoding: Option<&'static encoding_rs::Encoding>,
+    /// Delimiter of the fields of both files, for example `b';'`. Detected
+    /// from the header line when not set.
+    pub delimiter: Option<u8>,
+    /// Non-standard: when set, a calendar_dates.txt row whose service_id
+    /// contains this delimiter lists several services, the exception applying
+    /// to each of them. Listed services must be known (from calendar.txt or
+    /// from a previous single service row), the unknown ones are ignored.
+    pub service_id_list_delimiter: Option<char>,
+    /// Maximum number of calendar_dates.txt exceptions for a single service,
+    /// reading fails when a service exceeds it. Unlimited when not set.
+    pub max_exceptions_per_service: Option<usize>,
+    /// Non-standard: read the calendar_dates.txt dates given as an integer of
+    /// at most 7 digits (so not a `YYYYMMDD` date) as an Excel serial date
+    /// of the 1900 date system, as emitted by some spreadsheet exports
+    pub excel_serial_dates: bool,
+    /// When a calendar_dates.txt row removes a date from a service defined
+    /// neither in calendar.txt nor by a previous row adding a date, fail
+    /// instead of logging a warning and ignoring the row. Useful to validate
+    /// generated feeds.
+    pub strict_removals: bool,
+    /// Keep the days of the week of calendar.txt (see
+    /// [`objects::Calendar::declared_days`]), so that the services whose dates
+    /// are unchanged are written back as the same calendar.txt row instead
+    /// of being translated again
+    pub keep_declared_days: bool,
+    /// Non-standard: read the weekday flags of calendar.txt with
+    /// [`de_from_bool_flexible`] (accepting `true`/`false`, `yes`/`no` and
+    /// empty values) instead of only `0`/`1`
+    pub flexible_weekdays: bool,
+}
+
+impl ReadConfiguration {
+    fn read_objects<H, O>(
+        &self,
+        file_handler: &mut H,
+        file_name: &str,
+    ) -> Result<(Vec<O>, FileStatus)>
+    where
+        for<'a> &'a mut H: FileHandler,
+        O: for<'de> serde::Deserialize<'de>,
+    {
+        let file_exists = file_handler.get_file_if_exists(file_name)?.0.is_some();
+        let options = ReadOptions {
+            encoding: self.encoding,
+            delimiter: self.delimiter,
+        };
+        let objects: Vec<O> = read_objects_with_options(file_handler, file_name, false, &options)?;
+        let file_status = match (file_exists, objects.is_empty()) {
+            (false, _) => FileStatus::Absent,
+            (true, true) => {
+                info!(file_name = %file_name, "File is empty");
+                FileStatus::Empty
+            }
+            (true, false) => FileStatus::WithRows,
+        };
+        Ok((objects, file_status))
+    }
+
+    fn read_calendar_dates<H>(
+        &self,
+        file_handler: &mut H,
+    ) -> Result<(Vec<CalendarDate>, FileStatus)>
+    where
+        for<'a> &'a mut H: FileHandler,
+    {
+        if !self.excel_serial_dates {
+            return self.read_objects(file_handler, "calendar_dates.txt");
+        }
+        let (raw_calendar_dates, file_status): (Vec<RawCalendarDate>, _) =
+            self.read_objects(file_handler, "calendar_dates.txt")?;
+        let calendar_dates = raw_calendar_dates
+            .into_iter()
+            .map(CalendarDate::try_from)
+            .collect::<Result<Vec<CalendarDate>>>()
+            .context("Error reading \"calendar_dates.txt\"")?;
+        Ok((calendar_dates, file_status))
+    }
+
+    pub(crate) fn normalize_service_id(&self, service_id: String) -> String {
+        match &self.service_id_normalizer {
+            Some(normalizer) => normalizer(&service_id),
+            None => service_id,
+        }
+    }
+
+    /// Apply the service id normalizer to the service ids referenced by the
+    /// vehicle journeys and the time frames, so that they keep matching the
+    /// normalized calendars
+    pub(crate) fn normalize_referenced_service_ids(&self, collections: &mut Collections) {
+        if self.service_id_normalizer.is_none() {
+            return;
+        }
+        for vj_idx in collections.vehicle_journeys.indexes() {
+            let mut vj = collections.vehicle_journeys.index_mut(vj_idx);
+            vj.service_id = self.normalize_service_id(std::mem::take(&mut vj.service_id));
+        }
+        for timeframe in collections.timeframes.values_mut() {
+            timeframe.service_id =
+                self.normalize_service_id(std::mem::take(&mut timeframe.service_id));
+        }
     }
 }
 
+/// State of a calendar file when reading it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    /// The file does not exist
+    #[default]
+    Absent,
+    /// The file exists but has no row (header only or no content at all)
+    Empty,
+    /// The file exists and has rows
+    WithRows,
+}
+
+/// Metrics collected while reading calendar.txt and calendar_dates.txt
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CalendarParseStats {
+    /// State of calendar.txt
+    pub calendar_file: FileStatus,
+    /// State of calendar_dates.txt
+    pub calendar_dates_file: FileStatus,
+    /// Number of rows read in calendar.txt
+    pub calendar_rows: usize,
+    /// Number of rows read in calendar_dates.txt
+    pub calendar_date_rows: usize,
+    /// Number of services created from calendar.txt
+    pub services_created: usize,
+    /// Number of calendar.txt services dropped because they have no date
+    pub services_dropped_as_empty: usize,
+    /// Number of calendar.txt services dropped because their end_date is
+    /// before their start_date (see [`ReadConfiguration::skip_inverted_dates`])
+    pub services_dropped_as_inverted: usize,
+    /// Number of exceptions adding a date
+    pub exceptions_added: usize,
+    /// Number of exceptions removing a date of an existing service
+    pub exceptions_removed: usize,
+    /// Number of services only defined in calendar_dates.txt
+    pub services_from_dates_only: usize,
+    /// Number of dates added by calendar_dates.txt, the ones already active
+    /// excepted
+    pub dates_added: usize,
+    /// Number of dates removed by calendar_dates.txt, the ones already
+    /// inactive excepted
+    pub dates_removed: usize,
+}
+
 fn manage_calendar_dates<H>(
     calendars: &mut CollectionWithId<objects::Calendar>,
     file_handler: &mut H,
-    calendar_exists: bool,
+    calendar_service_ids: &BTreeSet<String>,
+    config: &ReadConfiguration,
+    stats: &mut CalendarParseStats,
 ) -> Result<()>
 where
     for<'a> &'a mut H: FileHandler,
 {
-    let calendar_dates =
-        read_objects::<_, CalendarDate>(file_handler, "calendar_dates.txt", false)?;
-    if calendar_dates.is_empty() && !calendar_exists {
+    let (calendar_dates, calendar_dates_file) = config.read_calendar_dates(file_handler)?;
+    stats.calendar_dates_file = calendar_dates_file;
+    if calendar_dates.is_empty() && calendar_service_ids.is_empty() {
         bail!("calendar_dates.txt or calendar.txt not found");
     }
+    stats.calendar_date_rows = calendar_dates.len();
+    let mut nb_exceptions_by_service: BTreeMap<String, usize> = BTreeMap::new();
     for calendar_date in calendar_dates {
-        let is_inserted = calendars
-            .get_mut(&calendar_date.service_id)
-            .map(|mut calendar| match calendar_date.exception_type {
-                ExceptionType::Add => {
-                    calendar.dates.insert(calendar_date.date);
+        let expanded_calendar_dates = match config.service_id_list_delimiter {
+            Some(delimiter) if calendar_date.service_id.contains(delimiter) => {
+                let mut listed_calendar_dates = vec![];
+                for service_id in calendar_date.service_id.split(delimiter) {
+                    let service_id = config.normalize_service_id(service_id.trim().to_string());
+                    if !calendars.contains_id(&service_id) {
+                        warn!(
+                            "service {} listed in calendar_dates.txt row {:?} is unknown, it is ignored",
+                            service_id, calendar_date.service_id
+                        );
+                        continue;
+                    }
+                    listed_calendar_dates.push(CalendarDate {
+                        service_id,
+                        date: calendar_date.date,
+                        exception_type: calendar_date.exception_type.clone(),
+                    });
                 }
-                ExceptionType::Remove => {
-                    calendar.dates.remove(&calendar_date.date);
+                listed_calendar_dates
+            }
+            _ => vec![CalendarDate {
+                service_id: config.normalize_service_id(calendar_date.service_id),
+                ..calendar_date
+            }],
+        };
+        for calendar_date in expanded_calendar_dates {
+            if let Some(max_exceptions) = config.max_exceptions_per_service {
+                let nb_exceptions = nb_exceptions_by_service
+                    .entry(calendar_date.service_id.clone())
+                    .or_default();
+                *nb_exceptions += 1;
+                if *nb_exceptions > max_exceptions {
+                    bail!(
+                        "service {} has more than {} exceptions in calendar_dates.txt",
+                        calendar_date.service_id,
+                        max_exceptions
+                    );
                 }
-            });
-        is_inserted.unwrap_or_else(|| {
-            if calendar_date.exception_type == ExceptionType::Add {
-                let mut dates = BTreeSet::new();
-                dates.insert(calendar_date.date);
-                calendars
-                    .push(objects::Calendar {
-                        id: calendar_date.service_id,
-                        dates,
-                    })
-                    .unwrap();
+            }
+            apply_calendar_date(
+                calendars,
+                calendar_date,
+                calendar_service_ids,
+                config,
+                stats,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn apply_calendar_date(
+    calendars: &mut CollectionWithId<objects::Calendar>,
+    calendar_date: CalendarDate,
+    calendar_service_ids: &BTreeSet<String>,
+    config: &ReadConfiguration,
+    stats: &mut CalendarParseStats,
+) -> Result<()> {
+    let source = config.provenance.as_deref().unwrap_or_default();
+    let is_inserted = calendars
+        .get_mut(&calendar_date.service_id)
+        .map(|mut calendar| match calendar_date.exception_type {
+            ExceptionType::Add => {
+                if calendar.insert_date_from(calendar_date.date, source) {
+                    stats.dates_added += 1;
+                } else {
+                    warn!(
+                        "calendar_dates.txt adds the date {} to service {} which is already active on that date",
+                        calendar_date.date, calendar_date.service_id
+                    );
+                }
+                stats.exceptions_added += 1;
+            }
+            ExceptionType::Remove => {
+                if calendar.remove_date(&calendar_date.date) {
+                    stats.dates_removed += 1;
+                } else {
+                    warn!(
+                        "calendar_dates.txt removes the date {} from service {} which is not active on that date",
+                        calendar_date.date, calendar_date.service_id
+                    );
+                }
+                stats.exceptions_removed += 1;
             }
         });
+    if is_inserted.is_some() {
+        return Ok(());
+    }
+    match calendar_date.exception_type {
+        ExceptionType::Add => {
+            let mut calendar = objects::Calendar::new(calendar_date.service_id);
+            if config.provenance.is_some() {
+                calendar.track_provenance(source);
+            }
+            calendar.insert_date_from(calendar_date.date, source);
+            calendars.push(calendar).unwrap();
+            stats.exceptions_added += 1;
+            stats.dates_added += 1;
+            stats.services_from_dates_only += 1;
+        }
+        // A service of calendar.txt without any date is not kept, but is known
+        ExceptionType::Remove if calendar_service_ids.contains(&calendar_date.service_id) => {}
+        ExceptionType::Remove => {
+            let error = anyhow!(
+                "calendar_dates.txt removes the date {} from service {} which is not defined",
+                calendar_date.date,
+                calendar_date.service_id
+            );
+            if config.strict_removals {
+                return Err(error);
+            }
+            warn!("{}, it is ignored", error);
+        }
     }
     Ok(())
 }
 
-pub(crate) fn _manage_calendars<H>(
+// Calendars of calendar.txt, with the ids of all its services (even the ones
+// not kept because they have no date)
+fn read_calendar_file<H>(
     file_handler: &mut H,
-    collections: &mut Collections,
-) -> Result<()>
+    config: &ReadConfiguration,
+    stats: &mut CalendarParseStats,
+) -> Result<(CollectionWithId<objects::Calendar>, BTreeSet<String>)>
 where
     for<'a> &'a mut H: FileHandler,
 {
     let mut calendars: CollectionWithId<objects::Calendar> = CollectionWithId::default();
-    let ntfs_calendars = read_objects::<_, Calendar>(file_handler, "calendar.txt", false)?;
-    let calendar_exists = !ntfs_calendars.is_empty();
-    for calendar in ntfs_calendars {
+    let (ntfs_calendars, calendar_file): (Vec<Calendar>, _) = if config.flexible_weekdays {
+        let (flexible_calendars, calendar_file): (Vec<FlexibleCalendar>, _) =
+            config.read_objects(file_handler, "calendar.txt")?;
+        (
+            flexible_calendars.into_iter().map(Calendar::from).collect(),
+            calendar_file,
+        )
+    } else {
+        config.read_objects(file_handler, "calendar.txt")?
+    };
+    stats.calendar_file = calendar_file;
+    let mut calendar_service_ids = BTreeSet::new();
+    stats.calendar_rows = ntfs_calendars.len();
+    for mut calendar in ntfs_calendars {
+        calendar.id = config.normalize_service_id(calendar.id);
+        calendar_service_ids.insert(calendar.id.clone());
+        if calendar.end_date < calendar.start_date {
+            if config.fix_swapped_dates {
+                warn!(
+                    "service {} has its end_date {} before its start_date {}, dates are swapped",
+                    calendar.id, calendar.end_date, calendar.start_date
+                );
+                std::mem::swap(&mut calendar.start_date, &mut calendar.end_date);
+            } else {
+                let error = anyhow!(
+                    "service {} has its end_date {} before its start_date {}",
+                    calendar.id,
+                    calendar.end_date,
+                    calendar.start_date
+                );
+                if !config.skip_inverted_dates {
+                    return Err(error);
+                }
+                warn!("{}, it is ignored", error);
+                stats.services_dropped_as_inverted += 1;
+                continue;
+            }
+        }
         let dates = calendar.get_valid_dates();
         if !dates.is_empty() {
-            skip_error_and_warn!(calendars.push(objects::Calendar {
+            let mut calendar = objects::Calendar {
                 id: calendar.id.clone(),
                 dates,
-            }));
+                metadata: Some(CalendarMetadata {
+                    declared_period: Some(ValidityPeriod {
+                        start_date: calendar.start_date,
+                        end_date: calendar.end_date,
+                    }),
+                    description: calendar.service_desc.clone(),
+                    declared_days: if config.keep_declared_days {
+                        Some(calendar.get_valid_days())
+                    } else {
+                        None
+                    },
+                    ..Default::default()
+                }),
+            };
+            if let Some(source) = &config.provenance {
+                calendar.track_provenance(source);
+            }
+            skip_error_and_warn!(calendars.push(calendar));
+            stats.services_created += 1;
+        } else {
+            stats.services_dropped_as_empty += 1;
         }
     }
+    Ok((calendars, calendar_service_ids))
+}
+
+pub(crate) fn _manage_calendars<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    config: &ReadConfiguration,
+) -> Result<CalendarParseStats>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let mut stats = CalendarParseStats::default();
+    let (calendars, calendar_service_ids) = read_calendar_file(file_handler, config, &mut stats)?;
     collections.calendars = calendars;
-    manage_calendar_dates(&mut collections.calendars, file_handler, calendar_exists)?;
+    manage_calendar_dates(
+        &mut collections.calendars,
+        file_handler,
+        &calendar_service_ids,
+        config,
+        &mut stats,
+    )?;
+    config.normalize_referenced_service_ids(collections);
 
-    Ok(())
+    Ok(stats)
 }
 
 #[cfg(not(feature = "parser"))]
@@ -200,7 +771,8 @@ pub(crate) fn manage_calendars<H>(file_handler: &mut H, collections: &mut Collec
 where
     for<'a> &'a mut H: FileHandler,
 {
-    _manage_calendars(file_handler, collections)
+    _manage_calendars(file_handler, collections, &ReadConfiguration::default())?;
+    Ok(())
 }
 #[cfg(feature = "parser")]
 /// Read calendar_dates.txt and calendar.txt files
@@ -208,75 +780,2539 @@ pub fn manage_calendars<H>(file_handler: &mut H, collections: &mut Collections)
 where
     for<'a> &'a mut H: FileHandler,
 {
-    _manage_calendars(file_handler, collections)
+    _manage_calendars(file_handler, collections, &ReadConfiguration::default())?;
+    Ok(())
 }
 
-/// Write the calendar_dates.txt file into a Path from a list of Calendar
-pub fn write_calendar_dates(
-    path: &path::Path,
+#[cfg(feature = "parser")]
+/// Read calendar_dates.txt and calendar.txt files with the given options
+pub fn manage_calendars_with_configuration<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    config: &ReadConfiguration,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    _manage_calendars(file_handler, collections, config)?;
+    Ok(())
+}
+
+#[cfg(feature = "parser")]
+/// Read calendar_dates.txt and calendar.txt files and return metrics about
+/// what has been read
+pub fn manage_calendars_with_stats<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+) -> Result<CalendarParseStats>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    _manage_calendars(file_handler, collections, &ReadConfiguration::default())
+}
+
+// Gives access to the content of a single file
+struct ReaderHandler<R> {
+    file_name: &'static str,
+    reader: R,
+}
+
+impl<'a, R: io::Read> FileHandler for &'a mut ReaderHandler<R> {
+    type Reader = &'a mut R;
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, path::PathBuf)> {
+        let reader = if name == self.file_name {
+            Some(&mut self.reader)
+        } else {
+            None
+        };
+        Ok((reader, path::PathBuf::from(name)))
+    }
+    fn source_name(&self) -> &str {
+        self.file_name
+    }
+}
+
+/// Read the services of a calendar.txt content, for example an in memory
+/// string. The services without any date are not kept.
+pub fn parse_calendar<R>(reader: R) -> Result<CollectionWithId<objects::Calendar>>
+where
+    R: io::Read,
+{
+    let mut file_handler = ReaderHandler {
+        file_name: "calendar.txt",
+        reader,
+    };
+    let (calendars, _) = read_calendar_file(
+        &mut file_handler,
+        &ReadConfiguration::default(),
+        &mut CalendarParseStats::default(),
+    )?;
+    Ok(calendars)
+}
+
+/// Apply the exceptions of a calendar_dates.txt content to the calendars, for
+/// example the ones from [`parse_calendar`]. The services only defined in
+/// calendar_dates.txt are added.
+pub fn parse_calendar_dates<R>(
+    reader: R,
+    calendars: &mut CollectionWithId<objects::Calendar>,
+) -> Result<()>
+where
+    R: io::Read,
+{
+    apply_calendar_dates_from_reader(reader, calendars)?;
+    Ok(())
+}
+
+pub(crate) fn apply_calendar_dates_from_reader<R>(
+    reader: R,
+    calendars: &mut CollectionWithId<objects::Calendar>,
+) -> Result<CalendarParseStats>
+where
+    R: io::Read,
+{
+    let mut file_handler = ReaderHandler {
+        file_name: "calendar_dates.txt",
+        reader,
+    };
+    let calendar_service_ids: BTreeSet<String> = calendars.values().map(|c| c.id.clone()).collect();
+    let mut stats = CalendarParseStats::default();
+    manage_calendar_dates(
+        calendars,
+        &mut file_handler,
+        &calendar_service_ids,
+        &ReadConfiguration::default(),
+        &mut stats,
+    )?;
+    Ok(stats)
+}
+
+#[cfg(feature = "parser")]
+/// Read calendar_dates.txt and calendar.txt files from a zip archive, without
+/// extracting it. The files are looked up by name, whatever their directory
+/// in the archive.
+pub fn manage_calendars_from_zip<R>(
+    archive: &mut zip::ZipArchive<R>,
+    collections: &mut Collections,
+) -> Result<()>
+where
+    R: std::io::Read + std::io::Seek,
+{
+    let mut file_handler = crate::file_handler::ZipArchiveHandler::new(archive, "");
+    manage_calendars(&mut file_handler, collections)
+}
+
+/// Returns the services whose dates, once calendar_dates.txt is applied, fall
+/// outside the window declared in calendar.txt
+pub fn check_declared_windows(calendars: &CollectionWithId<objects::Calendar>) -> Vec<String> {
+    let mut services = vec![];
+    for calendar in calendars.values() {
+        let declared_period = match calendar.declared_period() {
+            Some(declared_period) => declared_period,
+            None => continue,
+        };
+        let (first_date, last_date) = match (
+            calendar.dates.iter().next(),
+            calendar.dates.iter().next_back(),
+        ) {
+            (Some(first_date), Some(last_date)) => (first_date, last_date),
+            _ => continue,
+        };
+        if *first_date < declared_period.start_date || *last_date > declared_period.end_date {
+            warn!(
+                "service {} is active from {} to {} but calendar.txt declares {} to {}",
+                calendar.id,
+                first_date,
+                last_date,
+                declared_period.start_date,
+                declared_period.end_date
+            );
+            services.push(calendar.id.clone());
+        }
+    }
+    services
+}
+
+/// Removes the dates added by calendar_dates.txt outside the window declared
+/// in calendar.txt, usually stale exceptions. Services without a declared
+/// window (only defined in calendar_dates.txt) are left untouched.
+/// Returns the number of removed dates per service, for the services having
+/// some.
+pub fn drop_out_of_window_dates(
+    calendars: &mut CollectionWithId<objects::Calendar>,
+) -> BTreeMap<String, usize> {
+    let mut removed_dates = BTreeMap::new();
+    let out_of_window_services: Vec<String> = check_declared_windows(calendars);
+    for service_id in out_of_window_services {
+        if let Some(mut calendar) = calendars.get_mut(&service_id) {
+            let declared_period = match calendar.declared_period().cloned() {
+                Some(declared_period) => declared_period,
+                None => continue,
+            };
+            let out_of_window_dates: Vec<Date> = calendar
+                .dates
+                .iter()
+                .filter(|date| {
+                    **date < declared_period.start_date || **date > declared_period.end_date
+                })
+                .cloned()
+                .collect();
+            for date in &out_of_window_dates {
+                calendar.remove_date(date);
+            }
+            info!(
+                "service {}: {} dates outside its declared window are removed",
+                service_id,
+                out_of_window_dates.len()
+            );
+            removed_dates.insert(service_id, out_of_window_dates.len());
+        }
+    }
+    removed_dates
+}
+
+/// Levenshtein distance between both strings, in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = Vec::with_capacity(b.len() + 1);
+        row.push(i + 1);
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            let insertion = row[j] + 1;
+            let deletion = previous_row[j + 1] + 1;
+            row.push(substitution.min(insertion).min(deletion));
+        }
+        previous_row = row;
+    }
+    previous_row[b.len()]
+}
+
+/// Similarity of both strings between 0 (nothing in common) and 1 (equal),
+/// based on their edit distance
+fn string_similarity(a: &str, b: &str) -> f64 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(a, b) as f64 / max_len as f64
+}
+
+/// Reports the services only defined in calendar_dates.txt. This is valid but
+/// may come from a typo in a calendar.txt service id: each one is returned
+/// with the most similar calendar.txt service id when their similarity
+/// (1 minus the edit distance divided by the length of the longest id)
+/// reaches `similarity_threshold`.
+pub fn check_dates_only_services(
     calendars: &CollectionWithId<objects::Calendar>,
-) -> Result<()> {
-    let file = "calendar_dates.txt";
-    info!(file_name = %file, "Writing");
-    let calendar_dates_path = path.join(file);
-    let mut translations: Vec<Calendar> = vec![];
-    let mut exceptions: Vec<CalendarDate> = vec![];
-    for c in calendars.values() {
-        let translation = translate(&c.dates);
-        if !translation.operating_days.is_empty() {
-            let validity_period = skip_error_and_warn!(translation.validity_period.ok_or_else(
-                || anyhow!("Validity period not found for service id {}", c.id.clone())
-            ));
-            translations.push(Calendar {
-                id: c.id.clone(),
-                monday: translation.operating_days.contains(&Weekday::Mon),
-                tuesday: translation.operating_days.contains(&Weekday::Tue),
-                wednesday: translation.operating_days.contains(&Weekday::Wed),
-                thursday: translation.operating_days.contains(&Weekday::Thu),
-                friday: translation.operating_days.contains(&Weekday::Fri),
-                saturday: translation.operating_days.contains(&Weekday::Sat),
-                sunday: translation.operating_days.contains(&Weekday::Sun),
-                start_date: validity_period.start_date,
-                end_date: validity_period.end_date,
-            });
+    similarity_threshold: f64,
+) -> Vec<(String, Option<String>)> {
+    let (calendar_services, mut dates_only_services): (Vec<&objects::Calendar>, Vec<_>) = calendars
+        .values()
+        .partition(|calendar| calendar.declared_period().is_some());
+    dates_only_services.sort_unstable_by(|c1, c2| c1.id.cmp(&c2.id));
+    let mut services = vec![];
+    for dates_only_service in dates_only_services {
+        let similar_service = calendar_services
+            .iter()
+            .map(|calendar| {
+                (
+                    string_similarity(&dates_only_service.id, &calendar.id),
+                    &calendar.id,
+                )
+            })
+            .filter(|(similarity, _)| *similarity >= similarity_threshold)
+            // Most similar first, then smallest id on ties
+            .min_by(|(s1, id1), (s2, id2)| s2.total_cmp(s1).then_with(|| id1.cmp(id2)))
+            .map(|(_, service_id)| service_id.clone());
+        match &similar_service {
+            Some(similar_service) => warn!(
+                "service {} is only defined in calendar_dates.txt, is it a typo of {}?",
+                dates_only_service.id, similar_service
+            ),
+            None => info!(
+                "service {} is only defined in calendar_dates.txt",
+                dates_only_service.id
+            ),
+        }
+        services.push((dates_only_service.id.clone(), similar_service));
+    }
+    services
+}
+
+/// Checks that each of the given services is active on all the `required`
+/// dates, returning for each failing service the required dates it is not
+/// active on. An unknown service misses all the required dates.
+pub fn validate_required_dates(
+    collections: &Collections,
+    service_ids: &[&str],
+    required: &BTreeSet<Date>,
+) -> Vec<(String, Vec<Date>)> {
+    let mut missing_dates_by_service = vec![];
+    for service_id in service_ids {
+        let missing_dates: Vec<Date> = match collections.calendars.get(service_id) {
+            Some(calendar) => required.difference(&calendar.dates).cloned().collect(),
+            None => required.iter().cloned().collect(),
         };
-        for e in translation.exceptions {
-            exceptions.push(CalendarDate {
-                service_id: c.id.clone(),
-                date: e.date,
-                exception_type: e.exception_type,
-            });
+        if !missing_dates.is_empty() {
+            warn!(
+                "service {} is not active on {} required dates",
+                service_id,
+                missing_dates.len()
+            );
+            missing_dates_by_service.push((service_id.to_string(), missing_dates));
         }
     }
-    if !exceptions.is_empty() {
-        let mut wtr = csv::Writer::from_path(&calendar_dates_path)
-            .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
-        for e in exceptions {
-            wtr.serialize(&e)
-                .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+    missing_dates_by_service
+}
+
+/// Finds the pairs of services that are exact complements: they share no
+/// date and together are active on every day of the window going from the
+/// first to the last date of either. This is a common artifact of a service
+/// wrongly split in two. Pairs are returned sorted, as (smallest id, largest
+/// id).
+pub fn find_complementary_services(
+    calendars: &CollectionWithId<objects::Calendar>,
+) -> Vec<(String, String)> {
+    let mut sorted_calendars: Vec<(&objects::Calendar, Date, Date)> = calendars
+        .values()
+        .filter_map(|calendar| {
+            let first_date = calendar.dates.iter().next()?;
+            let last_date = calendar.dates.iter().next_back()?;
+            Some((calendar, *first_date, *last_date))
+        })
+        .collect();
+    sorted_calendars.sort_unstable_by(|(c1, _, _), (c2, _, _)| c1.id.cmp(&c2.id));
+    let mut pairs = vec![];
+    for (index, (calendar, first_date, last_date)) in sorted_calendars.iter().enumerate() {
+        for (other, other_first_date, other_last_date) in &sorted_calendars[index + 1..] {
+            let window_start = std::cmp::min(first_date, other_first_date);
+            let window_end = std::cmp::max(last_date, other_last_date);
+            let nb_days = (*window_end - *window_start).num_days() + 1;
+            // All the dates are in the window: covering it without overlap
+            // means having exactly as many dates as days in the window
+            let nb_dates = calendar.dates.len() + other.dates.len();
+            if usize::try_from(nb_days).map_or(false, |nb_days| nb_days == nb_dates)
+                && calendar.dates.is_disjoint(&other.dates)
+            {
+                warn!(
+                    "services {} and {} are complementary between {} and {}",
+                    calendar.id, other.id, window_start, window_end
+                );
+                pairs.push((calendar.id.clone(), other.id.clone()));
+            }
         }
-        wtr.flush()
-            .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
     }
-    write_calendar(path, &translations)
+    pairs
 }
 
-/// Write the calendar.txt file into a Path from a list of Calendar
-pub fn write_calendar(path: &path::Path, calendars: &[Calendar]) -> Result<()> {
-    let file = "calendar.txt";
-    info!(file_name = %file, "Writing");
-    if calendars.is_empty() {
-        return Ok(());
+/// Groups the services sharing the same weekly pattern and validity window
+/// (as computed by [`translate`]) and keeps one representative per group.
+/// Returns the representative calendars and, for each original service id,
+/// the id of its representative. The given calendars are left untouched.
+pub fn collapse_to_representatives(
+    calendars: &CollectionWithId<objects::Calendar>,
+) -> (
+    CollectionWithId<objects::Calendar>,
+    BTreeMap<String, String>,
+) {
+    let mut representatives: BTreeMap<(u8, Option<(Date, Date)>), &objects::Calendar> =
+        BTreeMap::new();
+    let mut mapping = BTreeMap::new();
+    let mut sorted_calendars: Vec<&objects::Calendar> = calendars.values().collect();
+    sorted_calendars.sort_unstable_by(|c1, c2| c1.id.cmp(&c2.id));
+    for calendar in sorted_calendars {
+        let translation = translate(&calendar.dates);
+        let week_pattern = translation.operating_days.iter().fold(0u8, |pattern, day| {
+            pattern | 1 << day.num_days_from_monday()
+        });
+        let window = translation
+            .validity_period
+            .map(|period| (period.start_date, period.end_date));
+        let representative = representatives
+            .entry((week_pattern, window))
+            .or_insert(calendar);
+        mapping.insert(calendar.id.clone(), representative.id.clone());
     }
+    let mut representatives: Vec<objects::Calendar> =
+        representatives.into_values().cloned().collect();
+    representatives.sort_unstable_by(|c1, c2| c1.id.cmp(&c2.id));
+    (CollectionWithId::new(representatives).unwrap(), mapping)
+}
 
-    let calendar_path = path.join(file);
-    let mut wtr = csv::Writer::from_path(&calendar_path)
-        .with_context(|| format!("Error reading {:?}", calendar_path))?;
-    for calendar in calendars {
-        wtr.serialize(calendar)
-            .with_context(|| format!("Error reading {:?}", calendar_path))?;
+/// Dump the calendars in a stable textual form, one line per service sorted
+/// by id, followed by its sorted dates. Useful for snapshot tests.
+///
+/// ```text
+/// service_1: 20200101,20200102
+/// service_2:
+/// ```
+pub fn dump_calendars_stable(calendars: &CollectionWithId<objects::Calendar>) -> String {
+    let mut sorted_calendars: Vec<&objects::Calendar> = calendars.values().collect();
+    sorted_calendars.sort_unstable_by(|c1, c2| c1.id.cmp(&c2.id));
+    let mut dump = String::new();
+    for calendar in sorted_calendars {
+        let dates: Vec<String> = calendar
+            .dates
+            .iter()
+            .map(|date| date.format("%Y%m%d").to_string())
+            .collect();
+        dump.push_str(&format!("{}: {}\n", calendar.id, dates.join(",")));
+    }
+    dump
+}
+
+/// Export the `(service_id, date)` pairs of all the services into the
+/// `service_dates` table of a SQLite database, indexed on `date`. Dates are
+/// stored as ISO 8601 text (`2020-01-31`). The table is replaced if it
+/// already exists in the database.
+#[cfg(feature = "rusqlite")]
+pub fn export_calendars_sqlite(collections: &Collections, path: &path::Path) -> Result<()> {
+    info!("Exporting service dates into {:?}", path);
+    let mut connection =
+        rusqlite::Connection::open(path).with_context(|| format!("Error opening {:?}", path))?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(
+        "DROP TABLE IF EXISTS service_dates;
+         CREATE TABLE service_dates (service_id TEXT NOT NULL, date TEXT NOT NULL);",
+    )?;
+    {
+        let mut statement =
+            transaction.prepare("INSERT INTO service_dates (service_id, date) VALUES (?1, ?2)")?;
+        for (service_id, date) in collections.iter_service_dates() {
+            statement.execute((service_id, date.format("%Y-%m-%d").to_string()))?;
+        }
+    }
+    transaction.execute_batch("CREATE INDEX service_dates_date ON service_dates (date);")?;
+    transaction
+        .commit()
+        .with_context(|| format!("Error writing {:?}", path))?;
+    Ok(())
+}
+
+/// Options used when writing calendar.txt and calendar_dates.txt
+#[derive(Debug, Default, Clone)]
+pub struct WriteConfiguration {
+    /// Only write the weekly pattern of calendar.txt and drop all the
+    /// exceptions of calendar_dates.txt. This is lossy: the number of lost
+    /// dates is logged.
+    pub weekday_pattern_only: bool,
+    /// Remove the newline ending the last row of the written files, for
+    /// consumers rejecting it
+    pub no_trailing_newline: bool,
+    /// Format of the written dates (see [`chrono::format::strftime`]), GTFS
+    /// `%Y%m%d` when not set
+    pub date_format: Option<String>,
+    /// Validity period of the whole feed: the start_date and end_date of
+    /// calendar.txt are clamped to it, and the exceptions outside of it are
+    /// dropped. The weekly pattern is still derived from all the dates of the
+    /// service. Services entirely outside of the period are skipped.
+    pub validity_period: Option<ValidityPeriod>,
+    /// Also write a calendar.txt row for the services without weekly
+    /// pattern, with all the weekdays inactive and the range of the dates of
+    /// the service (all written in calendar_dates.txt), for consumers
+    /// expecting every service in calendar.txt
+    pub always_write_calendar: bool,
+}
+
+impl WriteConfiguration {
+    // Format of the written dates, failing up front when it is not a valid
+    // strftime format (formatting a date with it would panic)
+    fn date_format(&self) -> Result<&str> {
+        let date_format = self.date_format.as_deref().unwrap_or("%Y%m%d");
+        if StrftimeItems::new(date_format).any(|item| matches!(item, Item::Error)) {
+            bail!("invalid date format {:?}", date_format);
+        }
+        Ok(date_format)
+    }
+}
+
+fn remove_trailing_newline(file_path: &path::Path) -> Result<()> {
+    let content =
+        std::fs::read(file_path).with_context(|| format!("Error reading {:?}", file_path))?;
+    if let Some(content) = content.strip_suffix(b"\n") {
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        std::fs::write(file_path, content)
+            .with_context(|| format!("Error writing {:?}", file_path))?;
     }
-    wtr.flush()
-        .with_context(|| format!("Error reading {:?}", calendar_path))?;
     Ok(())
 }
+
+fn to_calendar_dates(service_id: &str, exceptions: Vec<ExceptionDate>) -> Vec<CalendarDate> {
+    exceptions
+        .into_iter()
+        .map(|e| CalendarDate {
+            service_id: service_id.to_string(),
+            date: e.date,
+            exception_type: e.exception_type,
+        })
+        .collect()
+}
+
+/// Returns the calendar_dates.txt rows of a single service, as written by
+/// [`write_calendar_dates`], without writing anything
+pub fn exceptions_for_service(calendar: &objects::Calendar) -> Vec<CalendarDate> {
+    to_calendar_dates(&calendar.id, translate(&calendar.dates).exceptions)
+}
+
+/// Write the calendar_dates.txt file into a Path from a list of Calendar
+pub fn write_calendar_dates(
+    path: &path::Path,
+    calendars: &CollectionWithId<objects::Calendar>,
+) -> Result<()> {
+    write_calendar_dates_with_configuration(path, calendars, &WriteConfiguration::default())
+}
+
+/// Write the calendar_dates.txt file into a Path from a list of Calendar,
+/// with the given options
+pub fn write_calendar_dates_with_configuration(
+    path: &path::Path,
+    calendars: &CollectionWithId<objects::Calendar>,
+    config: &WriteConfiguration,
+) -> Result<()> {
+    write_calendar_dates_with_transformer(path, calendars, config, |_| true)
+}
+
+/// Write the calendar_dates.txt file into a Path from a list of Calendar,
+/// with the given options, running the transformer over each exception
+/// before it is written. The transformer may modify the exception (for
+/// example its service id) and returns false to drop it.
+///
+/// calendar.txt is not affected: remapping a service id only applies to
+/// calendar_dates.txt.
+pub fn write_calendar_dates_with_transformer<F>(
+    path: &path::Path,
+    calendars: &CollectionWithId<objects::Calendar>,
+    config: &WriteConfiguration,
+    transformer: F,
+) -> Result<()>
+where
+    F: FnMut(&mut CalendarDate) -> bool,
+{
+    write_calendar_files(path, calendars, config, transformer)
+}
+
+/// Write calendar_dates.txt then calendar.txt in the output, each file
+/// being complete before the next one is created.
+pub(crate) fn write_calendar_files<W, F>(
+    mut output: W,
+    calendars: &CollectionWithId<objects::Calendar>,
+    config: &WriteConfiguration,
+    transformer: F,
+) -> Result<()>
+where
+    W: FileWriter,
+    F: FnMut(&mut CalendarDate) -> bool,
+{
+    config.date_format()?;
+    let file = "calendar_dates.txt";
+    info!(file_name = %file, "Writing");
+    let (translations, exceptions) = translate_calendars(calendars, config, transformer);
+    if !exceptions.is_empty() {
+        write_calendar_date_rows(output.create_file(file)?, &exceptions, config)
+            .with_context(|| format!("Error reading {:?}", file))?;
+    }
+    write_calendar_file(output, &translations, config)
+}
+
+// The calendar.txt row the calendar has been read from, when kept and still
+// giving exactly the dates of the calendar
+fn declared_calendar(calendar: &objects::Calendar) -> Option<Calendar> {
+    let days = calendar.declared_days()?;
+    let declared_period = calendar.declared_period()?;
+    if weekly_dates(declared_period.start_date, declared_period.end_date, days) != calendar.dates {
+        return None;
+    }
+    Some(Calendar {
+        id: calendar.id.clone(),
+        monday: days.contains(&Weekday::Mon),
+        tuesday: days.contains(&Weekday::Tue),
+        wednesday: days.contains(&Weekday::Wed),
+        thursday: days.contains(&Weekday::Thu),
+        friday: days.contains(&Weekday::Fri),
+        saturday: days.contains(&Weekday::Sat),
+        sunday: days.contains(&Weekday::Sun),
+        start_date: declared_period.start_date,
+        end_date: declared_period.end_date,
+        service_desc: calendar.description().map(str::to_string),
+    })
+}
+
+// calendar.txt row (if any) and exceptions to write for a calendar, `None`
+// when it is skipped
+fn translate_calendar(
+    c: &objects::Calendar,
+    config: &WriteConfiguration,
+) -> Option<(Option<Calendar>, Vec<ExceptionDate>)> {
+    if let (Some(window), Some(first), Some(last)) = (
+        &config.validity_period,
+        c.dates.iter().next(),
+        c.dates.iter().next_back(),
+    ) {
+        if *last < window.start_date || *first > window.end_date {
+            warn!(
+                "service {} from {} to {} is outside of the validity period from {} to {}, skipping it",
+                c.id, first, last, window.start_date, window.end_date
+            );
+            return None;
+        }
+    }
+    // Clamping to the validity period needs the translation
+    if config.validity_period.is_none() {
+        if let Some(declared_calendar) = declared_calendar(c) {
+            return Some((Some(declared_calendar), vec![]));
+        }
+    }
+    let mut translation = translate(&c.dates);
+    if let Some(window) = &config.validity_period {
+        if let Some(validity_period) = translation.validity_period.as_mut() {
+            validity_period.start_date = validity_period.start_date.max(window.start_date);
+            validity_period.end_date = validity_period.end_date.min(window.end_date);
+            // The weekly pattern may only apply outside of the window
+            if validity_period.start_date > validity_period.end_date {
+                translation.operating_days.clear();
+            }
+        }
+        translation
+            .exceptions
+            .retain(|e| window.start_date <= e.date && e.date <= window.end_date);
+    }
+    let mut calendar = None;
+    if !translation.operating_days.is_empty() {
+        let validity_period = match translation.validity_period {
+            Some(validity_period) => validity_period,
+            None => {
+                warn!("Validity period not found for service id {}", c.id);
+                return None;
+            }
+        };
+        calendar = Some(Calendar {
+            id: c.id.clone(),
+            monday: translation.operating_days.contains(&Weekday::Mon),
+            tuesday: translation.operating_days.contains(&Weekday::Tue),
+            wednesday: translation.operating_days.contains(&Weekday::Wed),
+            thursday: translation.operating_days.contains(&Weekday::Thu),
+            friday: translation.operating_days.contains(&Weekday::Fri),
+            saturday: translation.operating_days.contains(&Weekday::Sat),
+            sunday: translation.operating_days.contains(&Weekday::Sun),
+            start_date: validity_period.start_date,
+            end_date: validity_period.end_date,
+            service_desc: c.description().map(str::to_string),
+        });
+    } else if config.always_write_calendar {
+        if let (Some(first), Some(last)) = (c.dates.iter().next(), c.dates.iter().next_back()) {
+            let (start_date, end_date) = match &config.validity_period {
+                Some(window) => (
+                    (*first).max(window.start_date),
+                    (*last).min(window.end_date),
+                ),
+                None => (*first, *last),
+            };
+            calendar = Some(Calendar {
+                id: c.id.clone(),
+                monday: false,
+                tuesday: false,
+                wednesday: false,
+                thursday: false,
+                friday: false,
+                saturday: false,
+                sunday: false,
+                start_date,
+                end_date,
+                service_desc: c.description().map(str::to_string),
+            });
+        }
+    }
+    Some((calendar, translation.exceptions))
+}
+
+// Weekly patterns and exceptions to write for the calendars
+fn translate_calendars<F>(
+    calendars: &CollectionWithId<objects::Calendar>,
+    config: &WriteConfiguration,
+    mut transformer: F,
+) -> (Vec<Calendar>, Vec<CalendarDate>)
+where
+    F: FnMut(&mut CalendarDate) -> bool,
+{
+    let mut translations: Vec<Calendar> = vec![];
+    let mut exceptions: Vec<CalendarDate> = vec![];
+    for c in calendars.values() {
+        if let Some((calendar, calendar_exceptions)) = translate_calendar(c, config) {
+            translations.extend(calendar);
+            exceptions.extend(to_calendar_dates(&c.id, calendar_exceptions));
+        }
+    }
+    if config.weekday_pattern_only && !exceptions.is_empty() {
+        let nb_added = exceptions
+            .iter()
+            .filter(|e| e.exception_type == ExceptionType::Add)
+            .count();
+        warn!(
+            "Weekday pattern only: {} active dates and {} inactive dates are lost",
+            nb_added,
+            exceptions.len() - nb_added
+        );
+        exceptions.clear();
+    }
+    let exceptions: Vec<CalendarDate> = exceptions
+        .into_iter()
+        .filter_map(|mut exception| {
+            if transformer(&mut exception) {
+                Some(exception)
+            } else {
+                None
+            }
+        })
+        .collect();
+    (translations, exceptions)
+}
+
+/// Serialize the rows of calendar_dates.txt, with their header, into the
+/// writer
+pub fn write_calendar_date_rows<W: io::Write>(
+    writer: W,
+    calendar_dates: &[CalendarDate],
+    config: &WriteConfiguration,
+) -> Result<()> {
+    let date_format = config.date_format()?;
+    serialize_rows(
+        writer,
+        calendar_dates
+            .iter()
+            .map(|calendar_date| calendar_date.to_row(date_format)),
+        config,
+    )
+}
+
+/// Serialize the rows of calendar.txt, with their header, into the writer
+pub fn write_calendar_rows<W: io::Write>(
+    writer: W,
+    calendars: &[Calendar],
+    config: &WriteConfiguration,
+) -> Result<()> {
+    let date_format = config.date_format()?;
+    // The description column is only written when some service has one
+    let with_description = calendars
+        .iter()
+        .any(|calendar| calendar.service_desc.is_some());
+    serialize_rows(
+        writer,
+        calendars
+            .iter()
+            .map(|calendar| calendar.to_row(date_format, with_description)),
+        config,
+    )
+}
+
+fn serialize_rows<W, R>(
+    mut writer: W,
+    rows: impl IntoIterator<Item = R>,
+    config: &WriteConfiguration,
+) -> Result<()>
+where
+    W: io::Write,
+    R: Serialize,
+{
+    if !config.no_trailing_newline {
+        let mut wtr = csv::Writer::from_writer(writer);
+        for row in rows {
+            wtr.serialize(row)?;
+        }
+        wtr.flush()?;
+        return Ok(());
+    }
+    // the rows are buffered to remove the trailing newline before writing them
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    for row in rows {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    let content = wtr.get_ref().as_slice();
+    let content = content
+        .strip_suffix(b"\n")
+        .map(|content| content.strip_suffix(b"\r").unwrap_or(content))
+        .unwrap_or(content);
+    writer.write_all(content)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write every date of the calendars as an added date in calendar_dates.txt,
+/// without any calendar.txt, for consumers not supporting calendar.txt.
+///
+/// Reading the files back with [`manage_calendars`] gives the same dates.
+/// Calendars without any date have no row, and no file is written if all the
+/// calendars are empty.
+pub fn write_calendar_dates_only(
+    path: &path::Path,
+    calendars: &CollectionWithId<objects::Calendar>,
+) -> Result<()> {
+    let file = "calendar_dates.txt";
+    info!(file_name = %file, "Writing");
+    if calendars.values().all(|calendar| calendar.dates.is_empty()) {
+        return Ok(());
+    }
+    let calendar_dates_path = path.join(file);
+    let mut wtr = csv::Writer::from_path(&calendar_dates_path)
+        .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+    for calendar in calendars.values() {
+        for date in &calendar.dates {
+            wtr.serialize(CalendarDate {
+                service_id: calendar.id.clone(),
+                date: *date,
+                exception_type: ExceptionType::Add,
+            })
+            .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+        }
+    }
+    wtr.flush()
+        .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+    Ok(())
+}
+
+/// Append calendar dates at the end of the calendar_dates.txt file in the
+/// Path, creating it (with its header) if needed or empty. A missing line
+/// break at the end of the existing file is added first.
+///
+/// The existing rows are neither read nor rewritten: the file may end up
+/// with duplicated or conflicting rows for the same service and date, and the
+/// rows are no longer ordered. Readers must reconcile them, the last row
+/// read winning when calendar_dates.txt is applied by [`manage_calendars`].
+pub fn append_calendar_dates(path: &path::Path, calendar_dates: &[CalendarDate]) -> Result<()> {
+    use io::{Read, Seek, SeekFrom, Write};
+    let file = "calendar_dates.txt";
+    info!(file_name = %file, "Appending");
+    let calendar_dates_path = path.join(file);
+    let mut output = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(&calendar_dates_path)
+        .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+    let file_length = output
+        .metadata()
+        .with_context(|| format!("Error reading {:?}", calendar_dates_path))?
+        .len();
+    if file_length > 0 {
+        let mut last_byte = [0u8];
+        output
+            .seek(SeekFrom::End(-1))
+            .and_then(|_| output.read_exact(&mut last_byte))
+            .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+        if last_byte[0] != b'\n' {
+            output
+                .write_all(b"\n")
+                .with_context(|| format!("Error writing {:?}", calendar_dates_path))?;
+        }
+    }
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(file_length == 0)
+        .from_writer(output);
+    for calendar_date in calendar_dates {
+        wtr.serialize(calendar_date)
+            .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+    }
+    wtr.flush()
+        .with_context(|| format!("Error reading {:?}", calendar_dates_path))?;
+    Ok(())
+}
+
+/// Write one calendar_dates.txt and calendar.txt pair per network, each one in
+/// a sub-directory named after the network id and containing only the
+/// services used by the vehicle journeys of this network.
+///
+/// Fails without writing anything when a network id is not a plain directory
+/// name (empty, `.`, `..`, or containing a path separator), which would
+/// write outside of `path`.
+pub fn write_calendar_dates_by_network(path: &path::Path, model: &Model) -> Result<()> {
+    for network in model.networks.values() {
+        let mut components = path::Path::new(&network.id).components();
+        let is_directory_name = matches!(
+            (components.next(), components.next()),
+            (Some(path::Component::Normal(_)), None)
+        );
+        if !is_directory_name || network.id.contains(['/', '\\']) {
+            bail!(
+                "network id {:?} can't be used as a directory name",
+                network.id
+            );
+        }
+    }
+    for (network_idx, network) in model.networks.iter() {
+        let network_path = path.join(&network.id);
+        std::fs::create_dir_all(&network_path)
+            .with_context(|| format!("Error creating {:?}", network_path))?;
+        let calendar_indexes: IdxSet<objects::Calendar> =
+            model.get_corresponding_from_idx(network_idx);
+        let mut calendars: Vec<objects::Calendar> = calendar_indexes
+            .into_iter()
+            .map(|idx| model.calendars[idx].clone())
+            .collect();
+        calendars.sort_unstable_by(|c1, c2| c1.id.cmp(&c2.id));
+        write_calendar_dates(&network_path, &CollectionWithId::new(calendars)?)?;
+    }
+    Ok(())
+}
+
+/// Row of booking_rule_service_windows.txt
+#[derive(Serialize)]
+struct BookingRuleServiceWindowRow<'a> {
+    booking_rule_id: &'a str,
+    service_id: &'a str,
+    start_date: String,
+    end_date: String,
+}
+
+/// Write, alongside calendar.txt, the booking_rule_service_windows.txt file
+/// linking each booking rule to the services of the vehicle journeys using
+/// it, with the service-date window of each service.
+///
+/// The window is the start_date and end_date of the calendar.txt row written
+/// for the service with the same configuration, so both files always agree.
+/// For a service without weekly pattern, it is the range of its active dates
+/// (as written with [`WriteConfiguration::always_write_calendar`]). Services
+/// without any active date or outside of the validity period are skipped.
+pub fn write_booking_rule_service_windows(
+    path: &path::Path,
+    model: &Model,
+    config: &WriteConfiguration,
+) -> Result<()> {
+    let date_format = config.date_format()?;
+    let file = "booking_rule_service_windows.txt";
+    info!(file_name = %file, "Writing");
+    let mut links: BTreeSet<(&str, &str)> = BTreeSet::new();
+    for vj in model.vehicle_journeys.values() {
+        for booking_rule_id in &vj.booking_rule_links {
+            if model.booking_rules.contains_id(booking_rule_id) {
+                links.insert((booking_rule_id.as_str(), vj.service_id.as_str()));
+            }
+        }
+    }
+    let window_config = WriteConfiguration {
+        always_write_calendar: true,
+        ..config.clone()
+    };
+    let mut windows: BTreeMap<&str, ValidityPeriod> = BTreeMap::new();
+    for &(_, service_id) in &links {
+        if windows.contains_key(service_id) {
+            continue;
+        }
+        let calendar = model
+            .calendars
+            .get(service_id)
+            .and_then(|calendar| translate_calendar(calendar, &window_config))
+            .and_then(|(calendar, _)| calendar);
+        if let Some(calendar) = calendar {
+            windows.insert(
+                service_id,
+                ValidityPeriod {
+                    start_date: calendar.start_date,
+                    end_date: calendar.end_date,
+                },
+            );
+        }
+    }
+    if windows.is_empty() {
+        return Ok(());
+    }
+
+    let windows_path = path.join(file);
+    let mut wtr = csv::Writer::from_path(&windows_path)
+        .with_context(|| format!("Error reading {:?}", windows_path))?;
+    for (booking_rule_id, service_id) in links {
+        let validity_period = match windows.get(service_id) {
+            Some(validity_period) => validity_period,
+            None => continue,
+        };
+        wtr.serialize(BookingRuleServiceWindowRow {
+            booking_rule_id,
+            service_id,
+            start_date: validity_period.start_date.format(date_format).to_string(),
+            end_date: validity_period.end_date.format(date_format).to_string(),
+        })
+        .with_context(|| format!("Error reading {:?}", windows_path))?;
+    }
+    wtr.flush()
+        .with_context(|| format!("Error reading {:?}", windows_path))?;
+    if config.no_trailing_newline {
+        remove_trailing_newline(&windows_path)?;
+    }
+    Ok(())
+}
+
+/// Write the calendar.txt file into a Path from a list of Calendar
+pub fn write_calendar(path: &path::Path, calendars: &[Calendar]) -> Result<()> {
+    write_calendar_with_configuration(path, calendars, &WriteConfiguration::default())
+}
+
+/// Write the calendar.txt file into a Path from a list of Calendar, with the
+/// given options
+pub fn write_calendar_with_configuration(
+    path: &path::Path,
+    calendars: &[Calendar],
+    config: &WriteConfiguration,
+) -> Result<()> {
+    write_calendar_file(path, calendars, config)
+}
+
+fn write_calendar_file<W: FileWriter>(
+    mut output: W,
+    calendars: &[Calendar],
+    config: &WriteConfiguration,
+) -> Result<()> {
+    config.date_format()?;
+    let file = "calendar.txt";
+    info!(file_name = %file, "Writing");
+    if calendars.is_empty() {
+        return Ok(());
+    }
+    write_calendar_rows(output.create_file(file)?, calendars, config)
+        .with_context(|| format!("Error reading {:?}", file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{file_handler::PathFileHandler, test_utils::*, ModelBuilder};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn read_calendars_with_provenance() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180501,20180506";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180507,1\n\
+                                      1,20180505,2\n\
+                                      2,20180508,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            let config = ReadConfiguration {
+                provenance: Some("feed_a".to_string()),
+                ..Default::default()
+            };
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+
+            let calendar = collections.calendars.get("1").unwrap();
+            let date = |d| Date::from_ymd_opt(2018, 5, d).unwrap();
+            assert_eq!(Some("feed_a"), calendar.date_source(&date(6)));
+            assert_eq!(Some("feed_a"), calendar.date_source(&date(7)));
+            assert_eq!(None, calendar.date_source(&date(5)));
+            let calendar = collections.calendars.get("2").unwrap();
+            assert_eq!(Some("feed_a"), calendar.date_source(&date(8)));
+
+            let mut merged = objects::Calendar::new("1".to_string());
+            merged.track_provenance("feed_b");
+            merged.insert_date_from(date(6), "feed_b");
+            merged.union_with(collections.calendars.get("1").unwrap());
+            assert_eq!(Some("feed_b"), merged.date_source(&date(6)));
+            assert_eq!(
+                vec![&date(7)],
+                merged.dates_from_source("feed_a").collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn read_calendars_without_provenance() {
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180507,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+
+            assert!(collections.calendars.get("1").unwrap().metadata.is_none());
+        });
+    }
+
+    #[test]
+    fn write_calendars_sharded_by_network() {
+        let model = ModelBuilder::default()
+            .calendar("c1", &["2020-01-01", "2020-01-02"])
+            .calendar("c2", &["2020-01-03"])
+            .vj("vj1", |vj| {
+                vj.route("r1")
+                    .line("l1")
+                    .network("n1")
+                    .calendar("c1")
+                    .st("A", "10:00:00")
+                    .st("B", "11:00:00");
+            })
+            .vj("vj2", |vj| {
+                vj.route("r2")
+                    .line("l2")
+                    .network("n2")
+                    .calendar("c2")
+                    .st("A", "10:00:00")
+                    .st("B", "11:00:00");
+            })
+            .build();
+
+        test_in_tmp_dir(|path| {
+            write_calendar_dates_by_network(path, &model).unwrap();
+            let shard_content = |network_id: &str| {
+                ["calendar.txt", "calendar_dates.txt"]
+                    .iter()
+                    .map(|file| path.join(network_id).join(file))
+                    .filter(|file| file.exists())
+                    .flat_map(get_file_content)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let n1 = shard_content("n1");
+            assert!(n1.contains("c1"));
+            assert!(!n1.contains("c2"));
+            let n2 = shard_content("n2");
+            assert!(n2.contains("c2"));
+            assert!(!n2.contains("c1"));
+        });
+    }
+
+    #[test]
+    fn write_calendars_sharded_by_unsafe_network_id() {
+        for network_id in ["../n1", "n1/n2", "/n1", "..", ""] {
+            let model = ModelBuilder::default()
+                .calendar("c1", &["2020-01-01"])
+                .vj("vj1", |vj| {
+                    vj.route("r1")
+                        .line("l1")
+                        .network(network_id)
+                        .calendar("c1")
+                        .st("A", "10:00:00")
+                        .st("B", "11:00:00");
+                })
+                .build();
+
+            test_in_tmp_dir(|path| {
+                let shard_path = path.join("shards");
+                assert!(write_calendar_dates_by_network(&shard_path, &model).is_err());
+                assert!(!shard_path.exists());
+                assert!(!path.join("n1").exists());
+            });
+        }
+    }
+
+    #[test]
+    fn write_weekday_pattern_only() {
+        let mut calendar = objects::Calendar::new("c1".to_string());
+        for day in [1, 8, 10, 22, 29] {
+            calendar
+                .dates
+                .insert(Date::from_ymd_opt(2018, 1, day).unwrap());
+        }
+        let calendars = CollectionWithId::from(calendar);
+
+        test_in_tmp_dir(|path| {
+            let config = WriteConfiguration {
+                weekday_pattern_only: true,
+                ..Default::default()
+            };
+            write_calendar_dates_with_configuration(path, &calendars, &config).unwrap();
+            assert!(!path.join("calendar_dates.txt").exists());
+            assert_eq!(
+                vec![
+                    "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+                    "c1,1,0,0,0,0,0,0,20180101,20180129",
+                ],
+                get_file_content(path.join("calendar.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn check_declared_windows_after_calendar_dates() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,1,1,1,1,1,1,1,20180501,20180506\n\
+                                 2,1,1,1,1,1,1,1,20180501,20180506";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180507,1\n\
+                                      2,20180503,2";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+
+            assert_eq!(
+                vec!["1".to_string()],
+                check_declared_windows(&collections.calendars)
+            );
+        });
+    }
+
+    #[test]
+    fn collapse_calendars_to_representatives() {
+        let calendar = |id: &str, days: &[u32]| {
+            let mut calendar = objects::Calendar::new(id.to_string());
+            for day in days {
+                calendar
+                    .dates
+                    .insert(Date::from_ymd_opt(2018, 1, *day).unwrap());
+            }
+            calendar
+        };
+        let calendars = CollectionWithId::new(vec![
+            calendar("c2", &[1, 8, 15]),
+            calendar("c1", &[1, 8, 10, 15]),
+            calendar("c3", &[2, 9, 16]),
+        ])
+        .unwrap();
+
+        let (representatives, mapping) = collapse_to_representatives(&calendars);
+
+        assert_eq!(
+            vec!["c1", "c3"],
+            representatives
+                .values()
+                .map(|c| c.id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("c1", mapping["c1"]);
+        assert_eq!("c1", mapping["c2"]);
+        assert_eq!("c3", mapping["c3"]);
+        assert_eq!(3, calendars.len());
+    }
+
+    #[test]
+    fn read_calendar_with_swapped_dates() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180508,20180501";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+
+            let mut collections = Collections::default();
+            let error = _manage_calendars(
+                &mut handler,
+                &mut collections,
+                &ReadConfiguration::default(),
+            )
+            .unwrap_err();
+            assert_eq!(
+                "service 1 has its end_date 2018-05-01 before its start_date 2018-05-08",
+                error.to_string()
+            );
+
+            let config = ReadConfiguration {
+                skip_inverted_dates: true,
+                ..Default::default()
+            };
+            let stats = _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+            assert_eq!(0, collections.calendars.len());
+            assert_eq!(1, stats.services_dropped_as_inverted);
+            assert_eq!(0, stats.services_dropped_as_empty);
+
+            let config = ReadConfiguration {
+                fix_swapped_dates: true,
+                ..Default::default()
+            };
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+            let calendar = collections.calendars.get("1").unwrap();
+            assert_eq!(
+                vec![
+                    Date::from_ymd_opt(2018, 5, 5).unwrap(),
+                    Date::from_ymd_opt(2018, 5, 6).unwrap()
+                ],
+                calendar.dates.iter().cloned().collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn read_calendars_with_service_id_normalizer() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 AG:1,0,0,0,0,0,1,1,20180501,20180506";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180505,2";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            // as in the NTFS, the trips are read before the calendars
+            collections.vehicle_journeys = CollectionWithId::from(objects::VehicleJourney {
+                id: "vj1".to_string(),
+                service_id: "AG:1".to_string(),
+                ..Default::default()
+            });
+            let config = ReadConfiguration {
+                service_id_normalizer: Some(Arc::new(|id: &str| {
+                    id.trim_start_matches("AG:").to_string()
+                })),
+                ..Default::default()
+            };
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+
+            let calendar = collections.calendars.get("1").unwrap();
+            assert_eq!(
+                vec![Date::from_ymd_opt(2018, 5, 6).unwrap()],
+                calendar.dates.iter().cloned().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                "1",
+                collections.vehicle_journeys.get("vj1").unwrap().service_id
+            );
+        });
+    }
+
+    #[test]
+    fn read_calendars_stats() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180501,20180508\n\
+                                 2,1,0,0,0,0,0,0,20180502,20180506";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180507,1\n\
+                                      1,20180505,2\n\
+                                      3,20180508,1\n\
+                                      4,20180508,2";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            let stats = _manage_calendars(
+                &mut handler,
+                &mut collections,
+                &ReadConfiguration::default(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                CalendarParseStats {
+                    calendar_file: FileStatus::WithRows,
+                    calendar_dates_file: FileStatus::WithRows,
+                    calendar_rows: 2,
+                    calendar_date_rows: 4,
+                    services_created: 1,
+                    services_dropped_as_empty: 1,
+                    services_dropped_as_inverted: 0,
+                    exceptions_added: 2,
+                    exceptions_removed: 1,
+                    services_from_dates_only: 1,
+                    dates_added: 2,
+                    dates_removed: 1,
+                },
+                stats
+            );
+        });
+    }
+
+    #[test]
+    fn read_latin1_calendars() {
+        // "é" encoded as ISO-8859-1
+        let mut calendar_dates_content = b"service_id,date,exception_type\nservice_".to_vec();
+        calendar_dates_content.push(0xE9);
+        calendar_dates_content.extend_from_slice(b",20180507,1");
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            std::fs::write(path.join("calendar_dates.txt"), &calendar_dates_content).unwrap();
+
+            let mut collections = Collections::default();
+            let config = ReadConfiguration {
+                encoding: Some(encoding_rs::WINDOWS_1252),
+                ..Default::default()
+            };
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+
+            assert!(collections.calendars.contains_id("service_é"));
+        });
+    }
+
+    #[test]
+    fn dump_calendars_in_stable_order() {
+        let mut calendar = objects::Calendar::new("b".to_string());
+        calendar
+            .dates
+            .insert(Date::from_ymd_opt(2020, 1, 2).unwrap());
+        calendar
+            .dates
+            .insert(Date::from_ymd_opt(2020, 1, 1).unwrap());
+        let calendars =
+            CollectionWithId::new(vec![calendar, objects::Calendar::new("a".to_string())]).unwrap();
+
+        assert_eq!(
+            "a: \nb: 20200101,20200102\n",
+            dump_calendars_stable(&calendars)
+        );
+    }
+
+    #[test]
+    fn write_calendars_trailing_newline() {
+        let mut calendar = objects::Calendar::new("c1".to_string());
+        for day in [1, 8, 15, 17] {
+            calendar
+                .dates
+                .insert(Date::from_ymd_opt(2018, 1, day).unwrap());
+        }
+        let calendars = CollectionWithId::from(calendar);
+
+        test_in_tmp_dir(|path| {
+            write_calendar_dates(path, &calendars).unwrap();
+            for file in ["calendar.txt", "calendar_dates.txt"] {
+                let content = std::fs::read(path.join(file)).unwrap();
+                assert_eq!(Some(&b'\n'), content.last());
+            }
+
+            let config = WriteConfiguration {
+                no_trailing_newline: true,
+                ..Default::default()
+            };
+            write_calendar_dates_with_configuration(path, &calendars, &config).unwrap();
+            let content = std::fs::read(path.join("calendar.txt")).unwrap();
+            assert_eq!(Some(&b'7'), content.last());
+            let content = std::fs::read(path.join("calendar_dates.txt")).unwrap();
+            assert_eq!(Some(&b'1'), content.last());
+        });
+    }
+
+    #[test]
+    fn read_calendars_stats_file_status() {
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180507,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date");
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            let stats = _manage_calendars(
+                &mut handler,
+                &mut collections,
+                &ReadConfiguration::default(),
+            )
+            .unwrap();
+            assert_eq!(FileStatus::Empty, stats.calendar_file);
+            assert_eq!(FileStatus::WithRows, stats.calendar_dates_file);
+
+            std::fs::remove_file(path.join("calendar.txt")).unwrap();
+            let stats = _manage_calendars(
+                &mut handler,
+                &mut collections,
+                &ReadConfiguration::default(),
+            )
+            .unwrap();
+            assert_eq!(FileStatus::Absent, stats.calendar_file);
+        });
+    }
+
+    #[test]
+    fn write_calendars_with_date_format() {
+        let mut calendar = objects::Calendar::new("c1".to_string());
+        for day in [1, 8, 15, 17] {
+            calendar
+                .dates
+                .insert(Date::from_ymd_opt(2018, 1, day).unwrap());
+        }
+        let calendars = CollectionWithId::from(calendar);
+
+        test_in_tmp_dir(|path| {
+            let config = WriteConfiguration {
+                date_format: Some("%Y-%m-%d".to_string()),
+                ..Default::default()
+            };
+            write_calendar_dates_with_configuration(path, &calendars, &config).unwrap();
+            assert_eq!(
+                vec![
+                    "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+                    "c1,1,0,0,0,0,0,0,2018-01-01,2018-01-17",
+                ],
+                get_file_content(path.join("calendar.txt"))
+            );
+            assert_eq!(
+                vec!["service_id,date,exception_type", "c1,2018-01-17,1"],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn write_calendars_with_invalid_date_format() {
+        let mut calendar = objects::Calendar::new("c1".to_string());
+        calendar
+            .dates
+            .insert(Date::from_ymd_opt(2018, 1, 1).unwrap());
+        let calendars = CollectionWithId::from(calendar);
+
+        test_in_tmp_dir(|path| {
+            let config = WriteConfiguration {
+                date_format: Some("%Y-%Q".to_string()),
+                ..Default::default()
+            };
+            let error =
+                write_calendar_dates_with_configuration(path, &calendars, &config).unwrap_err();
+            assert_eq!("invalid date format \"%Y-%Q\"", error.to_string());
+            assert!(!path.join("calendar_dates.txt").exists());
+            assert!(!path.join("calendar.txt").exists());
+        });
+    }
+
+    #[test]
+    fn append_to_calendar_dates() {
+        let calendar_date = |day, exception_type| CalendarDate {
+            service_id: "c1".to_string(),
+            date: Date::from_ymd_opt(2018, 1, day).unwrap(),
+            exception_type,
+        };
+
+        test_in_tmp_dir(|path| {
+            append_calendar_dates(path, &[calendar_date(1, ExceptionType::Add)]).unwrap();
+            append_calendar_dates(
+                path,
+                &[
+                    calendar_date(2, ExceptionType::Add),
+                    calendar_date(1, ExceptionType::Remove),
+                ],
+            )
+            .unwrap();
+            assert_eq!(
+                vec![
+                    "service_id,date,exception_type",
+                    "c1,20180101,1",
+                    "c1,20180102,1",
+                    "c1,20180101,2",
+                ],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn append_to_calendar_dates_without_trailing_line_break() {
+        test_in_tmp_dir(|path| {
+            create_file_with_content(
+                path,
+                "calendar_dates.txt",
+                "service_id,date,exception_type\nc1,20180101,1",
+            );
+            append_calendar_dates(
+                path,
+                &[CalendarDate {
+                    service_id: "c1".to_string(),
+                    date: Date::from_ymd_opt(2018, 1, 2).unwrap(),
+                    exception_type: ExceptionType::Add,
+                }],
+            )
+            .unwrap();
+            assert_eq!(
+                vec![
+                    "service_id,date,exception_type",
+                    "c1,20180101,1",
+                    "c1,20180102,1",
+                ],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn append_to_empty_calendar_dates() {
+        test_in_tmp_dir(|path| {
+            create_file_with_content(path, "calendar_dates.txt", "");
+            append_calendar_dates(
+                path,
+                &[CalendarDate {
+                    service_id: "c1".to_string(),
+                    date: Date::from_ymd_opt(2018, 1, 1).unwrap(),
+                    exception_type: ExceptionType::Add,
+                }],
+            )
+            .unwrap();
+            assert_eq!(
+                vec!["service_id,date,exception_type", "c1,20180101,1"],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn validate_services_required_dates() {
+        let date = |day| Date::from_ymd_opt(2018, 1, day).unwrap();
+        let mut collections = Collections::default();
+        let mut calendar = objects::Calendar::new("c1".to_string());
+        calendar.dates.extend([date(1), date(2)]);
+        collections.calendars.push(calendar).unwrap();
+        let mut calendar = objects::Calendar::new("c2".to_string());
+        calendar.dates.extend([date(1), date(3)]);
+        collections.calendars.push(calendar).unwrap();
+
+        let required: BTreeSet<Date> = vec![date(1), date(2)].into_iter().collect();
+        assert_eq!(
+            vec![
+                ("c2".to_string(), vec![date(2)]),
+                ("unknown".to_string(), vec![date(1), date(2)]),
+            ],
+            validate_required_dates(&collections, &["c1", "c2", "unknown"], &required)
+        );
+    }
+
+    #[test]
+    fn read_calendar_with_float_weekdays() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0.0,0.0,0.0,0.0,0.0,1.0,1,20180501,20180506";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+
+            assert_eq!(2, collections.calendars.get("1").unwrap().dates.len());
+        });
+    }
+
+    #[test]
+    fn read_calendar_with_flexible_weekdays() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,false,no,,FALSE,0,Yes,true,20180501,20180506";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+
+            let mut collections = Collections::default();
+            assert!(manage_calendars(&mut handler, &mut collections).is_err());
+
+            let config = ReadConfiguration {
+                flexible_weekdays: true,
+                ..Default::default()
+            };
+            let mut collections = Collections::default();
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+            assert_eq!(2, collections.calendars.get("1").unwrap().dates.len());
+        });
+    }
+
+    #[test]
+    fn write_booking_rule_service_windows_of_linked_services() {
+        let model = ModelBuilder::default()
+            .calendar("c1", &["2020-01-01", "2020-01-02", "2020-01-05"])
+            .calendar("c2", &["2020-01-03"])
+            .vj("vj1", |vj| {
+                vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+            })
+            .vj("vj2", |vj| {
+                vj.calendar("c2").st("A", "10:00:00").st("B", "11:00:00");
+            })
+            .build();
+        let mut collections = model.into_collections();
+        collections
+            .booking_rules
+            .push(objects::BookingRule {
+                id: "br1".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        let vj_idx = collections.vehicle_journeys.get_idx("vj1").unwrap();
+        collections
+            .vehicle_journeys
+            .index_mut(vj_idx)
+            .booking_rule_links
+            .insert("br1".to_string());
+        let model = Model::new(collections).unwrap();
+
+        test_in_tmp_dir(|path| {
+            write_booking_rule_service_windows(path, &model, &WriteConfiguration::default())
+                .unwrap();
+            assert_eq!(
+                vec![
+                    "booking_rule_id,service_id,start_date,end_date",
+                    "br1,c1,20200101,20200105",
+                ],
+                get_file_content(path.join("booking_rule_service_windows.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn write_booking_rule_service_windows_as_calendar() {
+        // 10 Mondays from 2012-07-02 to 2012-09-03, and a last Monday 10
+        // weeks later written as an exception: the window is the one of the
+        // weekly pattern, not the range of the active dates
+        let mut dates: Vec<Date> = (0..10)
+            .map(|week| Date::from_ymd_opt(2012, 7, 2).unwrap() + Days::new(7 * week))
+            .collect();
+        dates.push(Date::from_ymd_opt(2012, 11, 12).unwrap());
+        let model = ModelBuilder::default()
+            .calendar("c1", &dates)
+            .vj("vj1", |vj| {
+                vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+            })
+            .build();
+        let mut collections = model.into_collections();
+        collections
+            .booking_rules
+            .push(objects::BookingRule {
+                id: "br1".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        let vj_idx = collections.vehicle_journeys.get_idx("vj1").unwrap();
+        collections
+            .vehicle_journeys
+            .index_mut(vj_idx)
+            .booking_rule_links
+            .insert("br1".to_string());
+        let model = Model::new(collections).unwrap();
+
+        test_in_tmp_dir(|path| {
+            let config = WriteConfiguration::default();
+            write_booking_rule_service_windows(path, &model, &config).unwrap();
+            let calendars = CollectionWithId::from(model.calendars.get("c1").unwrap().clone());
+            write_calendar_dates_with_configuration(path, &calendars, &config).unwrap();
+            assert_eq!(
+                vec![
+                    "booking_rule_id,service_id,start_date,end_date",
+                    "br1,c1,20120702,20120903",
+                ],
+                get_file_content(path.join("booking_rule_service_windows.txt"))
+            );
+            assert_eq!(
+                vec![
+                    "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+                    "c1,1,0,0,0,0,0,0,20120702,20120903",
+                ],
+                get_file_content(path.join("calendar.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn write_calendar_dates_with_transformed_exceptions() {
+        let date = |day| Date::from_ymd_opt(2018, 1, day).unwrap();
+        let mut calendar = objects::Calendar::new("c1".to_string());
+        // Weekly on Mondays, with 2 additional dates
+        calendar
+            .dates
+            .extend([date(1), date(2), date(8), date(15), date(18), date(22)]);
+        let calendars = CollectionWithId::new(vec![calendar]).unwrap();
+
+        test_in_tmp_dir(|path| {
+            write_calendar_dates_with_transformer(
+                path,
+                &calendars,
+                &WriteConfiguration::default(),
+                |calendar_date| {
+                    calendar_date.service_id = format!("remapped:{}", calendar_date.service_id);
+                    calendar_date.date != date(18)
+                },
+            )
+            .unwrap();
+            assert_eq!(
+                vec!["service_id,date,exception_type", "remapped:c1,20180102,1"],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn find_complementary_services_pairs() {
+        let date = |day| Date::from_ymd_opt(2018, 1, day).unwrap();
+        let calendar = |id: &str, days: &[u32]| {
+            let mut calendar = objects::Calendar::new(id.to_string());
+            calendar.dates.extend(days.iter().map(|day| date(*day)));
+            calendar
+        };
+        let calendars = CollectionWithId::new(vec![
+            calendar("weekdays", &[1, 2, 3, 4, 5, 8, 9]),
+            calendar("weekend", &[6, 7]),
+            // Overlaps weekdays
+            calendar("overlap", &[5, 6, 7]),
+            // Leaves a gap with weekdays
+            calendar("gap", &[6, 7, 11]),
+            calendar("empty", &[]),
+        ])
+        .unwrap();
+        assert_eq!(
+            vec![("weekdays".to_string(), "weekend".to_string())],
+            find_complementary_services(&calendars)
+        );
+    }
+
+    #[test]
+    fn read_calendar_dates_with_service_id_list() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180501,20180508\n\
+                                 2,1,1,1,1,1,0,0,20180501,20180508";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1|2,20180502,2\n\
+                                      1 | unknown,20180503,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            let config = ReadConfiguration {
+                service_id_list_delimiter: Some('|'),
+                ..Default::default()
+            };
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+
+            let date = |day| Date::from_ymd_opt(2018, 5, day).unwrap();
+            assert_eq!(2, collections.calendars.len());
+            let dates: Vec<Date> = collections
+                .calendars
+                .get("1")
+                .unwrap()
+                .dates
+                .iter()
+                .cloned()
+                .collect();
+            assert_eq!(vec![date(3), date(5), date(6)], dates);
+            let dates: Vec<Date> = collections
+                .calendars
+                .get("2")
+                .unwrap()
+                .dates
+                .iter()
+                .cloned()
+                .collect();
+            assert_eq!(vec![date(1), date(3), date(4), date(7), date(8)], dates);
+        });
+    }
+
+    #[test]
+    fn drop_out_of_window_exceptions() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180501,20180531\n\
+                                 2,1,1,1,1,1,0,0,20180501,20180531";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180402,1\n\
+                                      1,20180602,1\n\
+                                      1,20180502,1\n\
+                                      3,20170101,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+            let nb_dates = collections.calendars.get("1").unwrap().dates.len();
+
+            let removed_dates = drop_out_of_window_dates(&mut collections.calendars);
+            let expected: BTreeMap<String, usize> =
+                vec![("1".to_string(), 2)].into_iter().collect();
+            assert_eq!(expected, removed_dates);
+            assert_eq!(
+                nb_dates - 2,
+                collections.calendars.get("1").unwrap().dates.len()
+            );
+            assert!(collections
+                .calendars
+                .get("1")
+                .unwrap()
+                .dates
+                .contains(&Date::from_ymd_opt(2018, 5, 2).unwrap()));
+            assert_eq!(1, collections.calendars.get("3").unwrap().dates.len());
+        });
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn export_service_dates_into_sqlite() {
+        let collections = ModelBuilder::default()
+            .calendar("c1", &["2020-01-01", "2020-01-02"])
+            .calendar("c2", &["2020-01-02"])
+            .vj("vj1", |vj| {
+                vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+            })
+            .vj("vj2", |vj| {
+                vj.calendar("c2").st("A", "10:00:00").st("B", "11:00:00");
+            })
+            .build()
+            .into_collections();
+
+        test_in_tmp_dir(|path| {
+            let database_path = path.join("calendars.sqlite");
+            export_calendars_sqlite(&collections, &database_path).unwrap();
+            // Exporting twice replaces the table
+            export_calendars_sqlite(&collections, &database_path).unwrap();
+
+            let connection = rusqlite::Connection::open(&database_path).unwrap();
+            let mut statement = connection
+                .prepare("SELECT service_id FROM service_dates WHERE date = ?1 ORDER BY service_id")
+                .unwrap();
+            let service_ids: Vec<String> = statement
+                .query_map(["2020-01-02"], |row| row.get(0))
+                .unwrap()
+                .map(|service_id| service_id.unwrap())
+                .collect();
+            assert_eq!(vec!["c1", "c2"], service_ids);
+        });
+    }
+
+    #[test]
+    fn read_calendar_dates_with_max_exceptions_per_service() {
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180501,1\n\
+                                      2,20180501,1\n\
+                                      1,20180502,1\n\
+                                      1,20180503,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let config = ReadConfiguration {
+                max_exceptions_per_service: Some(3),
+                ..Default::default()
+            };
+            let mut collections = Collections::default();
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+            assert_eq!(3, collections.calendars.get("1").unwrap().dates.len());
+
+            let config = ReadConfiguration {
+                max_exceptions_per_service: Some(2),
+                ..Default::default()
+            };
+            let mut collections = Collections::default();
+            let error = _manage_calendars(&mut handler, &mut collections, &config).unwrap_err();
+            assert_eq!(
+                "service 1 has more than 2 exceptions in calendar_dates.txt",
+                error.to_string()
+            );
+        });
+    }
+
+    #[test]
+    fn read_calendar_dates_removing_from_unknown_services() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,1,1,1,1,1,1,1,20180501,20180506\n\
+                                 2,0,0,0,0,0,0,0,20180501,20180506";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180502,2\n\
+                                      2,20180502,2\n\
+                                      3,20180502,2";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            _manage_calendars(
+                &mut handler,
+                &mut collections,
+                &ReadConfiguration::default(),
+            )
+            .unwrap();
+            assert_eq!(
+                vec!["1"],
+                collections
+                    .calendars
+                    .values()
+                    .map(|c| c.id.as_str())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(5, collections.calendars.get("1").unwrap().dates.len());
+
+            let config = ReadConfiguration {
+                strict_removals: true,
+                ..Default::default()
+            };
+            let mut collections = Collections::default();
+            let error = _manage_calendars(&mut handler, &mut collections, &config).unwrap_err();
+            assert_eq!(
+                "calendar_dates.txt removes the date 2018-05-02 from service 3 which is not defined",
+                error.to_string()
+            );
+        });
+    }
+
+    #[test]
+    fn write_back_declared_calendar_rows() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,1,1,1,1,1,1,1,20180501,20180506\n\
+                                 2,1,1,1,1,1,1,1,20180501,20180506";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            let config = ReadConfiguration {
+                keep_declared_days: true,
+                ..Default::default()
+            };
+            let mut collections = Collections::default();
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+            collections
+                .calendars
+                .get_mut("2")
+                .unwrap()
+                .remove_date(&Date::from_ymd_opt(2018, 5, 3).unwrap());
+
+            let output_path = path.join("output");
+            std::fs::create_dir(&output_path).unwrap();
+            write_calendar_dates(&output_path, &collections.calendars).unwrap();
+            // 2018-05-01 is a Tuesday, so translating the dates of the first
+            // service would not give Mondays
+            assert_eq!(
+                vec![
+                    "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+                    "1,1,1,1,1,1,1,1,20180501,20180506",
+                    "2,0,1,1,0,1,1,1,20180501,20180506",
+                ],
+                get_file_content(output_path.join("calendar.txt"))
+            );
+            assert!(!output_path.join("calendar_dates.txt").exists());
+        });
+    }
+
+    #[test]
+    fn exceptions_for_single_service() {
+        let date = |day| Date::from_ymd_opt(2018, 1, day).unwrap();
+        let mut calendar = objects::Calendar::new("c1".to_string());
+        // Weekly on Mondays, with 2 additional dates
+        calendar
+            .dates
+            .extend([date(1), date(2), date(8), date(15), date(18), date(22)]);
+
+        let exceptions: Vec<(String, Date, ExceptionType)> = exceptions_for_service(&calendar)
+            .into_iter()
+            .map(|e| (e.service_id, e.date, e.exception_type))
+            .collect();
+        assert_eq!(
+            vec![
+                ("c1".to_string(), date(2), ExceptionType::Add),
+                ("c1".to_string(), date(18), ExceptionType::Add),
+            ],
+            exceptions
+        );
+    }
+
+    #[test]
+    fn excel_serial_dates_around_1900_leap_year_bug() {
+        let date = |year, month, day| Date::from_ymd_opt(year, month, day).unwrap();
+        assert_eq!(date(1900, 1, 1), excel_serial_to_date(1).unwrap());
+        assert_eq!(date(1900, 2, 28), excel_serial_to_date(59).unwrap());
+        assert!(excel_serial_to_date(60).is_err());
+        assert_eq!(date(1900, 3, 1), excel_serial_to_date(61).unwrap());
+        assert_eq!(date(2023, 3, 15), excel_serial_to_date(45000).unwrap());
+        assert!(excel_serial_to_date(0).is_err());
+    }
+
+    #[test]
+    fn read_calendar_dates_with_excel_serial_dates() {
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,45000,1\n\
+                                      1,20230316,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let config = ReadConfiguration {
+                excel_serial_dates: true,
+                ..Default::default()
+            };
+            let mut collections = Collections::default();
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+            let dates: Vec<Date> = collections
+                .calendars
+                .get("1")
+                .unwrap()
+                .dates
+                .iter()
+                .cloned()
+                .collect();
+            assert_eq!(
+                vec![
+                    Date::from_ymd_opt(2023, 3, 15).unwrap(),
+                    Date::from_ymd_opt(2023, 3, 16).unwrap()
+                ],
+                dates
+            );
+
+            // Excel serial dates are rejected by default
+            let mut collections = Collections::default();
+            assert!(manage_calendars(&mut handler, &mut collections).is_err());
+        });
+    }
+
+    #[test]
+    fn check_dates_only_services_typos() {
+        assert_eq!(0, edit_distance("service", "service"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+        assert_eq!(4, edit_distance("", "abcd"));
+
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 weekday_1,1,1,1,1,1,0,0,20180501,20180531\n\
+                                 weekend_1,0,0,0,0,0,1,1,20180501,20180531";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      weekdya_1,20180601,1\n\
+                                      holiday,20180815,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+
+            assert_eq!(
+                vec![
+                    ("holiday".to_string(), None),
+                    ("weekdya_1".to_string(), Some("weekday_1".to_string())),
+                ],
+                check_dates_only_services(&collections.calendars, 0.7)
+            );
+        });
+    }
+
+    #[test]
+    fn read_and_write_service_description() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date,service_name\n\
+                                 1,1,1,1,1,1,0,0,20180507,20180511,Weekdays\n\
+                                 2,0,0,0,0,0,1,1,20180505,20180506,";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+            assert_eq!(
+                Some("Weekdays"),
+                collections.calendars.get("1").unwrap().description()
+            );
+            assert_eq!(None, collections.calendars.get("2").unwrap().description());
+
+            write_calendar_dates(path, &collections.calendars).unwrap();
+            assert_eq!(
+                vec![
+                    "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date,service_desc",
+                    "1,1,1,1,1,1,0,0,20180507,20180511,Weekdays",
+                    "2,0,0,0,0,0,1,1,20180505,20180506,",
+                ],
+                get_file_content(path.join("calendar.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn write_calendar_without_service_description() {
+        let mut calendar = objects::Calendar::new("1".to_string());
+        calendar
+            .dates
+            .insert(Date::from_ymd_opt(2018, 5, 5).unwrap());
+        let calendars = CollectionWithId::new(vec![calendar]).unwrap();
+
+        test_in_tmp_dir(|path| {
+            write_calendar_dates(path, &calendars).unwrap();
+            assert_eq!(
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+                get_file_content(path.join("calendar.txt"))[0]
+            );
+        });
+    }
+
+    #[test]
+    fn write_calendar_dates_only_round_trip() {
+        let date = |day| Date::from_ymd_opt(2018, 1, day).unwrap();
+        let mut weekly = objects::Calendar::new("weekly".to_string());
+        weekly.dates.extend([date(1), date(8), date(15)]);
+        let mut single = objects::Calendar::new("single".to_string());
+        single.dates.insert(date(3));
+        let calendars = CollectionWithId::new(vec![weekly, single]).unwrap();
+
+        test_in_tmp_dir(|path| {
+            write_calendar_dates_only(path, &calendars).unwrap();
+            assert!(!path.join("calendar.txt").exists());
+            assert_eq!(
+                vec![
+                    "service_id,date,exception_type",
+                    "weekly,20180101,1",
+                    "weekly,20180108,1",
+                    "weekly,20180115,1",
+                    "single,20180103,1",
+                ],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+            assert_eq!(
+                calendars.values().cloned().collect::<Vec<_>>(),
+                collections.calendars.into_vec()
+            );
+        });
+    }
+
+    #[test]
+    fn write_calendar_dates_only_without_dates() {
+        let calendars =
+            CollectionWithId::new(vec![objects::Calendar::new("empty".to_string())]).unwrap();
+
+        test_in_tmp_dir(|path| {
+            write_calendar_dates_only(path, &calendars).unwrap();
+            assert!(!path.join("calendar_dates.txt").exists());
+        });
+    }
+
+    #[test]
+    fn parse_calendars_from_readers() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180501,20180508\n\
+                                 2,1,0,0,0,0,0,0,20180501,20180506";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180505,2\n\
+                                      3,20180502,1";
+
+        let mut calendars = parse_calendar(calendars_content.as_bytes()).unwrap();
+        assert_eq!(
+            vec!["1"],
+            calendars
+                .values()
+                .map(|c| c.id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, calendars.get("1").unwrap().dates.len());
+
+        parse_calendar_dates(calendar_dates_content.as_bytes(), &mut calendars).unwrap();
+        assert_eq!(1, calendars.get("1").unwrap().dates.len());
+        assert_eq!(1, calendars.get("3").unwrap().dates.len());
+    }
+
+    #[cfg(feature = "parser")]
+    #[test]
+    fn read_calendars_from_zip() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180501,20180508";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180505,2\n\
+                                      2,20180502,1";
+
+        test_in_tmp_dir(|path| {
+            let open_archive = |files: &[(&str, &str)]| {
+                let files_path = path.join("files");
+                let _ = std::fs::remove_dir_all(&files_path);
+                std::fs::create_dir(&files_path).unwrap();
+                for (name, content) in files {
+                    create_file_with_content(&files_path, name, content);
+                }
+                let zip_path = path.join("calendars.zip");
+                crate::utils::zip_to(&files_path, &zip_path).unwrap();
+                zip::ZipArchive::new(std::fs::File::open(zip_path).unwrap()).unwrap()
+            };
+
+            let mut archive = open_archive(&[
+                ("calendar.txt", calendars_content),
+                ("calendar_dates.txt", calendar_dates_content),
+            ]);
+            let mut collections = Collections::default();
+            manage_calendars_from_zip(&mut archive, &mut collections).unwrap();
+            assert_eq!(1, collections.calendars.get("1").unwrap().dates.len());
+            assert_eq!(1, collections.calendars.get("2").unwrap().dates.len());
+
+            let mut archive = open_archive(&[("calendar_dates.txt", calendar_dates_content)]);
+            let mut collections = Collections::default();
+            manage_calendars_from_zip(&mut archive, &mut collections).unwrap();
+            assert_eq!(1, collections.calendars.len());
+
+            let mut archive = open_archive(&[("other.txt", "")]);
+            let mut collections = Collections::default();
+            let error = manage_calendars_from_zip(&mut archive, &mut collections).unwrap_err();
+            assert_eq!(
+                "calendar_dates.txt or calendar.txt not found",
+                error.to_string()
+            );
+        });
+    }
+
+    #[test]
+    fn read_malformed_calendar_row_reports_its_line() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,0,0,0,0,0,1,1,20180501,20180508\n\
+                                 2,0,0,0,0,0,2,1,20180501,20180508";
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180505,2\n\
+                                      1,20180506,2\n\
+                                      3,2018-05-07,1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+
+            let mut collections = Collections::default();
+            let error = manage_calendars(&mut handler, &mut collections).unwrap_err();
+            let message = error.to_string();
+            assert!(
+                message.ends_with("calendar.txt\" at line 3 (service_id \"2\")"),
+                "{}",
+                message
+            );
+
+            create_file_with_content(path, "calendar.txt", "");
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+            let error = manage_calendars(&mut handler, &mut collections).unwrap_err();
+            let message = error.to_string();
+            assert!(
+                message.ends_with("calendar_dates.txt\" at line 4 (service_id \"3\")"),
+                "{}",
+                message
+            );
+        });
+    }
+
+    #[test]
+    fn read_calendars_with_bom_and_semicolons() {
+        let calendars_content = "\u{feff}service_id;monday;tuesday;wednesday;thursday;friday;saturday;sunday;start_date;end_date\n\
+                                 1;0;0;0;0;0;1;1;20180501;20180508";
+        let calendar_dates_content = "\u{feff}service_id,date,exception_type\n\
+                                      1,20180505,2";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+            assert_eq!(
+                vec![Date::from_ymd_opt(2018, 5, 6).unwrap()],
+                collections
+                    .calendars
+                    .get("1")
+                    .unwrap()
+                    .dates
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn read_calendars_with_given_delimiter() {
+        let calendars_content = "service_id|monday|tuesday|wednesday|thursday|friday|saturday|sunday|start_date|end_date\n\
+                                 1|0|0|0|0|0|1|1|20180501|20180508";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+
+            let config = ReadConfiguration {
+                delimiter: Some(b'|'),
+                ..Default::default()
+            };
+            let mut collections = Collections::default();
+            _manage_calendars(&mut handler, &mut collections, &config).unwrap();
+            assert_eq!(2, collections.calendars.get("1").unwrap().dates.len());
+        });
+    }
+
+    #[test]
+    fn read_calendars_with_long_header() {
+        // the first semicolon is beyond the capacity of the read buffer
+        let calendars_content = format!(
+            "{};service_id;monday;tuesday;wednesday;thursday;friday;saturday;sunday;start_date;end_date\n\
+             ;1;0;0;0;0;0;1;1;20180501;20180508",
+            "x".repeat(10_000)
+        );
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", &calendars_content);
+
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+            assert_eq!(2, collections.calendars.get("1").unwrap().dates.len());
+        });
+    }
+
+    #[test]
+    fn write_calendars_clamped_to_validity_period() {
+        let mut c1 = objects::Calendar::new("c1".to_string());
+        for day in [1, 15, 17, 22, 29] {
+            c1.dates.insert(Date::from_ymd_opt(2018, 1, day).unwrap());
+        }
+        let mut c2 = objects::Calendar::new("c2".to_string());
+        c2.dates.insert(Date::from_ymd_opt(2018, 3, 5).unwrap());
+        let calendars = CollectionWithId::new(vec![c1, c2]).unwrap();
+
+        test_in_tmp_dir(|path| {
+            let config = WriteConfiguration {
+                validity_period: Some(ValidityPeriod {
+                    start_date: Date::from_ymd_opt(2018, 1, 10).unwrap(),
+                    end_date: Date::from_ymd_opt(2018, 1, 31).unwrap(),
+                }),
+                ..Default::default()
+            };
+            write_calendar_dates_with_configuration(path, &calendars, &config).unwrap();
+            assert_eq!(
+                vec![
+                    "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+                    "c1,1,0,0,0,0,0,0,20180110,20180129",
+                ],
+                get_file_content(path.join("calendar.txt"))
+            );
+            assert_eq!(
+                vec!["service_id,date,exception_type", "c1,20180117,1"],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+        });
+    }
+
+    #[test]
+    fn always_write_calendar_rows() {
+        let mut c1 = objects::Calendar::new("c1".to_string());
+        for day in [1, 8, 15] {
+            c1.dates.insert(Date::from_ymd_opt(2018, 1, day).unwrap());
+        }
+        let mut c2 = objects::Calendar::new("c2".to_string());
+        c2.dates.insert(Date::from_ymd_opt(2018, 3, 5).unwrap());
+        let calendars = CollectionWithId::new(vec![c1, c2]).unwrap();
+
+        test_in_tmp_dir(|path| {
+            let config = WriteConfiguration {
+                always_write_calendar: true,
+                ..Default::default()
+            };
+            write_calendar_dates_with_configuration(path, &calendars, &config).unwrap();
+            assert_eq!(
+                vec![
+                    "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+                    "c1,1,0,0,0,0,0,0,20180101,20180115",
+                    "c2,0,0,0,0,0,0,0,20180305,20180305",
+                ],
+                get_file_content(path.join("calendar.txt"))
+            );
+            assert_eq!(
+                vec!["service_id,date,exception_type", "c2,20180305,1"],
+                get_file_content(path.join("calendar_dates.txt"))
+            );
+
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+            for calendar in calendars.values() {
+                assert_eq!(
+                    calendar.dates,
+                    collections.calendars.get(&calendar.id).unwrap().dates
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn valid_dates_of_weekly_pattern() {
+        let date = |day| Date::from_ymd_opt(2020, 1, day).unwrap();
+        // 2020-01-01 is a Wednesday
+        let calendar = Calendar {
+            id: "1".to_string(),
+            monday: true,
+            tuesday: false,
+            wednesday: false,
+            thursday: false,
+            friday: false,
+            saturday: true,
+            sunday: false,
+            start_date: date(1),
+            end_date: date(20),
+            service_desc: None,
+        };
+        assert_eq!(
+            vec![date(4), date(6), date(11), date(13), date(18), date(20)],
+            calendar.get_valid_dates().into_iter().collect::<Vec<_>>()
+        );
+
+        let calendar = Calendar {
+            wednesday: true,
+            end_date: date(1),
+            ..calendar
+        };
+        assert_eq!(
+            vec![date(1)],
+            calendar.get_valid_dates().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn calendar_from_weekly_same_as_read() {
+        let calendars_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                 1,1,0,0,0,1,0,1,20200101,20200131";
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar.txt", calendars_content);
+            let mut collections = Collections::default();
+            manage_calendars(&mut handler, &mut collections).unwrap();
+
+            let date = |day| Date::from_ymd_opt(2020, 1, day).unwrap();
+            let calendar = objects::Calendar::from_weekly(
+                "1".to_string(),
+                date(1),
+                date(31),
+                &[Weekday::Sun, Weekday::Mon, Weekday::Fri],
+            );
+            let read_calendar = collections.calendars.get("1").unwrap();
+            assert_eq!(read_calendar.dates, calendar.dates);
+            assert_eq!(read_calendar.declared_period(), calendar.declared_period());
+            // 2020-01-31 is a Friday, the end date is included
+            assert!(calendar.dates.contains(&date(31)));
+        });
+    }
+
+    #[test]
+    fn write_calendar_date_rows_into_writer() {
+        let date = |day| Date::from_ymd_opt(2018, 1, day).unwrap();
+        let calendar_dates = vec![
+            CalendarDate {
+                service_id: "1".to_string(),
+                date: date(1),
+                exception_type: ExceptionType::Add,
+            },
+            CalendarDate {
+                service_id: "1".to_string(),
+                date: date(2),
+                exception_type: ExceptionType::Remove,
+            },
+        ];
+        let mut content = vec![];
+        write_calendar_date_rows(
+            &mut content,
+            &calendar_dates,
+            &WriteConfiguration::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "service_id,date,exception_type\n1,20180101,1\n1,20180102,2\n",
+            String::from_utf8(content).unwrap()
+        );
+
+        let config = WriteConfiguration {
+            no_trailing_newline: true,
+            ..Default::default()
+        };
+        let mut content = vec![];
+        write_calendar_date_rows(&mut content, &calendar_dates, &config).unwrap();
+        assert_eq!(
+            "service_id,date,exception_type\n1,20180101,1\n1,20180102,2",
+            String::from_utf8(content).unwrap()
+        );
+    }
+}
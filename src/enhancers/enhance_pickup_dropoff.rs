@@ -328,6 +328,7 @@ mod tests {
         collections.calendars = CollectionWithId::new(vec![Calendar {
             id: "default_service".to_owned(),
             dates,
+            ..Default::default()
         }])
         .unwrap();
         enhance_pickup_dropoff(&mut collections);
@@ -369,6 +370,7 @@ mod tests {
         collections.calendars = CollectionWithId::new(vec![Calendar {
             id: "default_service".to_owned(),
             dates,
+            ..Default::default()
         }])
         .unwrap();
         enhance_pickup_dropoff(&mut collections);
@@ -410,6 +412,7 @@ mod tests {
         collections.calendars = CollectionWithId::new(vec![Calendar {
             id: "default_service".to_owned(),
             dates,
+            ..Default::default()
         }])
         .unwrap();
         enhance_pickup_dropoff(&mut collections);
@@ -476,6 +479,7 @@ mod tests {
         collections.calendars = CollectionWithId::new(vec![Calendar {
             id: "default_service".to_owned(),
             dates,
+            ..Default::default()
         }])
         .unwrap();
         enhance_pickup_dropoff(&mut collections);
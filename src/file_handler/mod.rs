@@ -126,6 +126,50 @@ where
     }
 }
 
+/// ZipArchiveHandler is used to read files from an already opened archive,
+/// borrowed for the lifetime of the handler
+#[cfg(feature = "parser")]
+pub struct ZipArchiveHandler<'z, R: Seek + Read> {
+    archive: &'z mut zip::ZipArchive<R>,
+    archive_path: PathBuf,
+    index_by_name: BTreeMap<String, usize>,
+}
+
+#[cfg(feature = "parser")]
+impl<'z, R> ZipArchiveHandler<'z, R>
+where
+    R: Seek + Read,
+{
+    /// Constructs a new ZipArchiveHandler, `path` only being used in messages
+    pub fn new<P: AsRef<Path>>(archive: &'z mut zip::ZipArchive<R>, path: P) -> Self {
+        ZipArchiveHandler {
+            index_by_name: ZipHandler::files_by_name(archive),
+            archive,
+            archive_path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[cfg(feature = "parser")]
+impl<'a, 'z, R> FileHandler for &'a mut ZipArchiveHandler<'z, R>
+where
+    R: Seek + Read,
+{
+    type Reader = zip::read::ZipFile<'a, R>;
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+        let p = self.archive_path.join(name);
+        match self.index_by_name.get(name) {
+            None => Ok((None, p)),
+            Some(i) => Ok((Some(self.archive.by_index(*i)?), p)),
+        }
+    }
+    fn source_name(&self) -> &str {
+        self.archive_path
+            .to_str()
+            .unwrap_or_else(|| panic!("the path '{:?}' should be valid UTF-8", self.archive_path))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
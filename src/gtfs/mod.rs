@@ -18,16 +18,16 @@ mod read;
 mod write;
 
 use crate::{
-    calendars::{manage_calendars, write_calendar_dates},
+    calendars::{self, write_calendar_dates},
     file_handler::{FileHandler, PathFileHandler, ZipHandler},
     model::{Collections, Model},
     objects::{self, Availability, Contributor, Dataset, Network, StopType, Time},
-    parser::read_opt_collection,
+    parser::read_objects,
     serde_utils::*,
     utils::*,
-    validity_period, AddPrefix, PrefixConfiguration, Result,
+    validity_period, AddPrefix, Error, PrefixConfiguration, Result,
 };
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use chrono_tz::Tz;
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
@@ -36,15 +36,17 @@ use std::{
     fmt,
     hash::{Hash, Hasher},
     path::Path,
+    str::FromStr,
 };
 
-use tracing::info;
+use tracing::{info, warn};
 use typed_index_collection::CollectionWithId;
 
 #[cfg(all(feature = "gtfs", feature = "parser"))]
 pub use read::{
-    apply_attribution_rules, manage_frequencies, manage_pathways, manage_shapes, manage_stop_times,
-    read_agency, read_attributions, read_routes, read_stops, read_transfers, EquipmentList,
+    apply_attribution_rules, check_stop_times_order, manage_frequencies, manage_pathways,
+    manage_shapes, manage_stop_times, read_agency, read_attributions, read_routes, read_stops,
+    read_transfers, EquipmentList,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -289,11 +291,20 @@ struct Transfer {
 
 impl From<&objects::Transfer> for Transfer {
     fn from(obj: &objects::Transfer) -> Transfer {
-        Transfer {
-            from_stop_id: obj.from_stop_id.clone(),
-            to_stop_id: obj.to_stop_id.clone(),
-            transfer_type: TransferType::WithTransferTime,
-            min_transfer_time: obj.min_transfer_time,
+        if obj.is_possible() {
+            Transfer {
+                from_stop_id: obj.from_stop_id.clone(),
+                to_stop_id: obj.to_stop_id.clone(),
+                transfer_type: TransferType::WithTransferTime,
+                min_transfer_time: obj.min_transfer_time,
+            }
+        } else {
+            Transfer {
+                from_stop_id: obj.from_stop_id.clone(),
+                to_stop_id: obj.to_stop_id.clone(),
+                transfer_type: TransferType::NotPossible,
+                min_transfer_time: None,
+            }
         }
     }
 }
@@ -321,6 +332,36 @@ struct TicketingDeepLink {
 
 type TicketingDeepLinks = HashMap<String, TicketingDeepLink>;
 
+/// What to do when the departure times of the stop times of a trip go
+/// backwards along the `stop_sequence`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StopTimesOrder {
+    /// Log a warning and keep the stop times as they are
+    #[default]
+    Warn,
+    /// Clamp the backward stop times to the previous departure time, logging
+    /// a warning
+    Repair,
+    /// Fail the reading with the trip id and the offending sequences
+    Strict,
+}
+
+impl FromStr for StopTimesOrder {
+    type Err = Error;
+
+    fn from_str(stop_times_order: &str) -> Result<Self> {
+        match stop_times_order {
+            "warn" => Ok(StopTimesOrder::Warn),
+            "repair" => Ok(StopTimesOrder::Repair),
+            "strict" => Ok(StopTimesOrder::Strict),
+            _ => bail!(
+                "invalid stop times order {:?}, expected 'warn', 'repair' or 'strict'",
+                stop_times_order
+            ),
+        }
+    }
+}
+
 ///parameters consolidation
 #[derive(Default)]
 pub struct Configuration {
@@ -340,18 +381,29 @@ pub struct Configuration {
     /// Else we group the routes by `agency_id` and `route_short_name`
     /// (or `route_long_name` if the short name is empty) and create a `Line` for each group.
     pub read_as_line: bool,
+    /// What to do with the trips whose departure times go backwards along
+    /// the stop sequence
+    pub stop_times_order: StopTimesOrder,
+    /// Options used to read calendar.txt and calendar_dates.txt
+    pub calendar_configuration: calendars::ReadConfiguration,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 struct Attribution {
     attribution_id: Option<String>,
+    #[serde(skip_serializing)]
+    agency_id: Option<String>,
     route_id: Option<String>,
     trip_id: Option<String>,
+    #[serde(default, deserialize_with = "de_opt_bool_from_str", skip_serializing)]
+    is_producer: Option<bool>,
     #[serde(
         deserialize_with = "de_opt_bool_from_str",
         serialize_with = "ser_from_opt_bool"
     )]
     is_operator: Option<bool>,
+    #[serde(default, deserialize_with = "de_opt_bool_from_str", skip_serializing)]
+    is_authority: Option<bool>,
     organization_name: String,
     attribution_url: Option<String>,
     attribution_email: Option<String>,
@@ -393,14 +445,17 @@ where
         on_demand_transport,
         on_demand_transport_comment,
         read_as_line,
+        stop_times_order,
+        calendar_configuration,
     } = configuration;
 
-    manage_calendars(file_handler, &mut collections)?;
+    calendars::_manage_calendars(file_handler, &mut collections, &calendar_configuration)?;
     validity_period::compute_dataset_validity_period(&mut dataset, &collections.calendars)?;
 
     collections.contributors = CollectionWithId::from(contributor);
     collections.datasets = CollectionWithId::from(dataset);
     collections.feed_infos = feed_infos;
+    read::manage_feed_info(&mut collections, file_handler);
 
     let (networks, companies) = read::read_agency(file_handler)?;
     collections.networks = networks;
@@ -415,6 +470,9 @@ where
     read::manage_shapes(&mut collections, file_handler)?;
 
     read::read_routes(file_handler, &mut collections, read_as_line)?;
+    calendar_configuration.normalize_referenced_service_ids(&mut collections);
+    read::set_default_line_colors(&mut collections.lines);
+    read::resolve_trip_shapes(&mut collections)?;
     collections.equipments = CollectionWithId::new(equipments.into_equipments())?;
     read::manage_stop_times(
         &mut collections,
@@ -422,10 +480,16 @@ where
         on_demand_transport,
         on_demand_transport_comment,
     )?;
+    read::check_stop_times_order(&mut collections, stop_times_order)?;
+    let gtfs_attributions = read_objects(file_handler, "attributions.txt", false)?;
+    read::manage_attributions(&mut collections, &gtfs_attributions);
+    read::manage_translations(&mut collections, file_handler)?;
     read::manage_frequencies(&mut collections, file_handler)?;
     read::manage_pathways(&mut collections, file_handler)?;
-    collections.levels = read_opt_collection(file_handler, "levels.txt")?;
-    let attribution_rules = read::read_attributions(file_handler, "attributions.txt")?;
+    read::manage_timeframes(&mut collections, file_handler, &calendar_configuration)?;
+    read::manage_fares(&mut collections, file_handler)?;
+    read::manage_levels(&mut collections, file_handler)?;
+    let attribution_rules = read::attribution_rules(&gtfs_attributions);
     read::apply_attribution_rules(&mut collections, &attribution_rules)?;
 
     //add prefixes
@@ -630,22 +694,45 @@ struct Route {
     route_type: RouteType,
     #[serde(rename = "route_url")]
     url: Option<String>,
-    #[serde(
-        rename = "route_color",
-        default,
-        deserialize_with = "de_with_empty_or_invalid_default"
-    )]
+    #[serde(rename = "route_color", default, deserialize_with = "de_route_color")]
     color: Option<objects::Rgb>,
     #[serde(
         rename = "route_text_color",
         default,
-        deserialize_with = "de_with_empty_or_invalid_default"
+        deserialize_with = "de_route_color"
     )]
     text_color: Option<objects::Rgb>,
     #[serde(rename = "route_sort_order")]
     sort_order: Option<u32>,
 }
 
+// Deserialize a route_color or route_text_color, also accepting a leading `#`
+// and the 3 digits short form (`F0A` for `FF00AA`). Invalid colors are
+// ignored with a warning.
+fn de_route_color<'de, D>(deserializer: D) -> std::result::Result<Option<objects::Rgb>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let color = String::deserialize(deserializer)?;
+    let trimmed_color = color.trim();
+    let trimmed_color = trimmed_color.strip_prefix('#').unwrap_or(trimmed_color);
+    if trimmed_color.is_empty() {
+        return Ok(None);
+    }
+    let color_hex: String = if trimmed_color.chars().count() == 3 {
+        trimmed_color.chars().flat_map(|c| [c, c]).collect()
+    } else {
+        trimmed_color.to_string()
+    };
+    match color_hex.parse() {
+        Ok(rgb) => Ok(Some(rgb)),
+        Err(error) => {
+            warn!("invalid color {:?} ({}), it is ignored", color, error);
+            Ok(None)
+        }
+    }
+}
+
 /// Use to serialize extended route type
 /// For more information, see \
 /// https://developers.google.com/transit/gtfs/reference/extended-route-types"
@@ -744,6 +831,12 @@ where
 /// in the given directory.
 /// see [NTFS to GTFS conversion](https://github.com/hove-io/transit_model/blob/master/src/documentation/ntfs2gtfs.md)
 pub fn write<P: AsRef<Path>>(model: Model, path: P, extend_route_type: bool) -> Result<()> {
+    if let Some(epsg) = model.stops_epsg {
+        bail!(
+            "Cannot write GTFS with stops reprojected to EPSG:{}, longitudes and latitudes are expected",
+            epsg
+        );
+    }
     let path = path.as_ref();
     std::fs::create_dir_all(path)?;
     info!("Writing GTFS to {:?}", path);
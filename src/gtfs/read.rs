@@ -14,9 +14,10 @@
 
 use super::{
     Agency, Attribution, DirectionType, Route, RouteType, Shape, Stop, StopLocationType, StopTime,
-    Transfer, TransferType, Trip,
+    StopTimesOrder, Transfer, TransferType, Trip,
 };
 use crate::{
+    calendars,
     file_handler::FileHandler,
     model::Collections,
     objects::{
@@ -24,22 +25,25 @@ use crate::{
         Pathway, PropertiesMap, StopLocation, StopPoint, StopTimePrecision, StopType, Time,
         TransportType, VehicleJourney,
     },
-    parser::{read_collection, read_objects, read_objects_loose},
-    serde_utils::de_with_empty_default,
+    parser::{read_collection, read_objects, read_objects_loose, read_opt_collection},
+    serde_utils::{
+        de_currency_code, de_option_without_slashes, de_positive_decimal, de_with_empty_default,
+    },
     Result,
 };
 use anyhow::{anyhow, bail, Error};
 use derivative::Derivative;
 use geo::{LineString, Point};
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use skip_error::{skip_error_and_warn, SkipError};
 use std::convert::TryFrom;
 use std::{
     cmp,
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     hash::{DefaultHasher, Hash, Hasher},
 };
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 use typed_index_collection::{impl_id, Collection, CollectionWithId, Idx};
 
 fn default_agency_id() -> String {
@@ -385,6 +389,24 @@ where
     Ok(())
 }
 
+/// Unlink the trips whose `shape_id` is not found in shapes.txt
+pub(crate) fn resolve_trip_shapes(collections: &mut Collections) -> Result<()> {
+    let mut vehicle_journeys = collections.vehicle_journeys.take();
+    for vj in &mut vehicle_journeys {
+        if let Some(shape_id) = &vj.geometry_id {
+            if !collections.geometries.contains_id(shape_id) {
+                warn!(
+                    "Problem reading {:?}: shape_id={:?} of trip_id={:?} not found. Trip kept without geometry",
+                    "trips.txt", shape_id, vj.id
+                );
+                vj.geometry_id = None;
+            }
+        }
+    }
+    collections.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    Ok(())
+}
+
 /// Reading times that a vehicle arrives at and departs from stops for each trip
 pub fn manage_stop_times<H>(
     collections: &mut Collections,
@@ -505,6 +527,59 @@ where
     Ok(())
 }
 
+/// Check that the departure times of the stop times of each vehicle journey
+/// never go backwards along the stop sequence, handling the ones that do
+/// according to `stop_times_order`.
+///
+/// The times are offsets from the start of the service day, so the stop times
+/// of an overnight trip (e.g. `24:30:00` after `23:50:00`) are in order.
+pub fn check_stop_times_order(
+    collections: &mut Collections,
+    stop_times_order: StopTimesOrder,
+) -> Result<()> {
+    for vj_idx in collections.vehicle_journeys.indexes() {
+        let mut vj = collections.vehicle_journeys.index_mut(vj_idx);
+        let vj = &mut *vj;
+        // sequence and departure time of the latest stop time in order
+        let mut previous: Option<(u32, Time)> = None;
+        for stop_time in vj.stop_times.iter_mut() {
+            let departure_time = match stop_time.departure_time {
+                Some(departure_time) => departure_time,
+                None => continue,
+            };
+            match previous {
+                Some((sequence, previous_departure_time))
+                    if departure_time < previous_departure_time =>
+                {
+                    match stop_times_order {
+                        StopTimesOrder::Strict => bail!(
+                            "the departure time of the stop_sequence {} of the trip '{}' is before the one of the stop_sequence {}",
+                            stop_time.sequence,
+                            vj.id,
+                            sequence
+                        ),
+                        StopTimesOrder::Warn => warn!(
+                            "the departure time of the stop_sequence {} of the trip '{}' is before the one of the stop_sequence {}",
+                            stop_time.sequence, vj.id, sequence
+                        ),
+                        StopTimesOrder::Repair => {
+                            warn!(
+                                "the departure time of the stop_sequence {} of the trip '{}' is before the one of the stop_sequence {}, it is set to {}",
+                                stop_time.sequence, vj.id, sequence, previous_departure_time
+                            );
+                            stop_time.arrival_time = Some(previous_departure_time);
+                            stop_time.departure_time = Some(previous_departure_time);
+                            stop_time.precision = Some(StopTimePrecision::Approximate);
+                        }
+                    }
+                }
+                _ => previous = Some((stop_time.sequence, departure_time)),
+            }
+        }
+    }
+    Ok(())
+}
+
 fn ventilate_stop_times(
     undefined_stop_times: &[&StopTime],
     before: &StopTimesValues,
@@ -823,6 +898,9 @@ where
 }
 
 /// Reading pathways linking together locations within stations.
+/// A bidirectional pathway is kept once: the reversed rows of an already read
+/// bidirectional pathway, with the same mode and traversal attributes, are
+/// ignored.
 pub fn manage_pathways<H>(collections: &mut Collections, file_handler: &mut H) -> Result<()>
 where
     for<'a> &'a mut H: FileHandler,
@@ -831,6 +909,7 @@ where
 
     let gtfs_pathways = read_objects_loose::<_, Pathway>(file_handler, file, false)?;
     let mut pathways = vec![];
+    let mut bidirectional_pathways: HashMap<(String, String), Vec<Pathway>> = HashMap::new();
     for mut pathway in gtfs_pathways {
         pathway.from_stop_type = skip_error_and_warn!(collections
             .stop_points
@@ -863,12 +942,82 @@ where
                     pathway.to_stop_id
                 )
             }));
+        let reverse_key = (pathway.to_stop_id.clone(), pathway.from_stop_id.clone());
+        if let Some(reversed_pathway) =
+            bidirectional_pathways
+                .get(&reverse_key)
+                .and_then(|candidates| {
+                    candidates
+                        .iter()
+                        .find(|candidate| is_reverse_pathway(&pathway, candidate))
+                })
+        {
+            warn!(
+                "Problem reading {:?}: pathway_id={:?} is the reverse of the bidirectional pathway_id={:?}, ignored",
+                file, pathway.id, reversed_pathway.id
+            );
+            continue;
+        }
+        if pathway.is_bidirectional {
+            bidirectional_pathways
+                .entry((pathway.from_stop_id.clone(), pathway.to_stop_id.clone()))
+                .or_insert_with(Vec::new)
+                .push(pathway.clone());
+        }
         pathways.push(pathway);
     }
     collections.pathways = CollectionWithId::new(pathways)?;
     Ok(())
 }
 
+// Whether `pathway` describes the bidirectional pathway `other` read in the
+// opposite direction: same mode and traversal attributes, the stair count and
+// the slope being compared regardless of their sign
+fn is_reverse_pathway(pathway: &Pathway, other: &Pathway) -> bool {
+    pathway.is_bidirectional
+        && other.is_bidirectional
+        && pathway.from_stop_id == other.to_stop_id
+        && pathway.to_stop_id == other.from_stop_id
+        && pathway.pathway_mode == other.pathway_mode
+        && pathway.length == other.length
+        && pathway.traversal_time == other.traversal_time
+        && pathway.stair_count.map(i16::abs) == other.stair_count.map(i16::abs)
+        && pathway.max_slope.map(f32::abs) == other.max_slope.map(f32::abs)
+        && pathway.min_width == other.min_width
+}
+
+/// Reading the levels of levels.txt, the levels of the stops not found in
+/// levels.txt being removed.
+pub fn manage_levels<H>(collections: &mut Collections, file_handler: &mut H) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let levels: CollectionWithId<objects::Level> = read_opt_collection(file_handler, "levels.txt")?;
+    macro_rules! remove_unknown_levels {
+        ($($field:ident),*) => {
+            $(
+                for idx in collections.$field.indexes() {
+                    let mut stop = collections.$field.index_mut(idx);
+                    let is_level_unknown = stop
+                        .level_id
+                        .as_ref()
+                        .map_or(false, |level_id| !levels.contains_id(level_id));
+                    if is_level_unknown {
+                        warn!(
+                            "level_id={:?} of stop_id={:?} not found in levels.txt, the stop has no level",
+                            stop.level_id, stop.id
+                        );
+                        stop.level_id = None;
+                    }
+                }
+            )*
+        };
+    }
+    remove_unknown_levels!(stop_points, stop_areas, stop_locations);
+    collections.levels = levels;
+    Ok(())
+}
+
 /// Reading rules for making connections at transfer points between routes.
 pub fn read_transfers<H>(
     file_handler: &mut H,
@@ -924,9 +1073,24 @@ where
                             from_stop_point.id, to_stop_point.id
                         );
                         }
-                        (transfer.min_transfer_time, transfer.min_transfer_time)
+                        // a possible transfer must not be mistaken for a not possible one
+                        let min_transfer_time = transfer.min_transfer_time.map(|time| {
+                            if time >= objects::Transfer::NOT_POSSIBLE_TRANSFER_TIME {
+                                warn!(
+                                    "The min_transfer_time {} between from_stop_id {} and to_stop_id {} is capped",
+                                    time, from_stop_point.id, to_stop_point.id
+                                );
+                                objects::Transfer::NOT_POSSIBLE_TRANSFER_TIME - 1
+                            } else {
+                                time
+                            }
+                        });
+                        (min_transfer_time, min_transfer_time)
                     }
-                    TransferType::NotPossible => (Some(86400), Some(86400)),
+                    TransferType::NotPossible => (
+                        Some(objects::Transfer::NOT_POSSIBLE_TRANSFER_TIME),
+                        Some(objects::Transfer::NOT_POSSIBLE_TRANSFER_TIME),
+                    ),
                 };
 
                 transfers.push(objects::Transfer {
@@ -1208,6 +1372,28 @@ where
     Ok(())
 }
 
+/// Set the colors of the lines without one to the defaults of the GTFS
+/// specification: white for the color and black for the text color
+pub(crate) fn set_default_line_colors(lines: &mut CollectionWithId<objects::Line>) {
+    for idx in lines.indexes() {
+        let mut line = lines.index_mut(idx);
+        if line.color.is_none() {
+            line.color = Some(objects::Rgb {
+                red: 255,
+                green: 255,
+                blue: 255,
+            });
+        }
+        if line.text_color.is_none() {
+            line.text_color = Some(objects::Rgb {
+                red: 0,
+                green: 0,
+                blue: 0,
+            });
+        }
+    }
+}
+
 #[derive(Derivative, Deserialize, Debug, Clone, PartialEq)]
 #[derivative(Default)]
 enum FrequencyPrecision {
@@ -1235,12 +1421,461 @@ where
 {
     let file = "frequencies.txt";
     let frequencies = read_objects::<_, Frequency>(file_handler, file, false)?
-        .iter()
-        .map(|gtfs_frequency| objects::Frequency::try_from(gtfs_frequency.clone()))
+        .into_iter()
+        .map(|gtfs_frequency| {
+            let exact_times = gtfs_frequency.exact_times == FrequencyPrecision::Exact;
+            objects::Frequency::try_from(gtfs_frequency).map(|frequency| (frequency, exact_times))
+        })
         .skip_error_and_warn()
         .collect();
 
-    collections.convert_frequencies_to_stoptimes(frequencies)
+    collections.expand_frequencies(frequencies)
+}
+
+#[derive(Deserialize, Debug)]
+struct Translation {
+    table_name: String,
+    field_name: String,
+    language: String,
+    translation: String,
+    #[serde(default, deserialize_with = "de_option_without_slashes")]
+    record_id: Option<String>,
+    field_value: Option<String>,
+}
+
+// Ids of the objects of the type by the value of the translated field, used
+// to find the objects targeted by a `field_value`
+fn field_value_index(
+    collections: &Collections,
+    object_type: &ObjectType,
+    field_name: &str,
+) -> HashMap<String, Vec<String>> {
+    let values_and_ids: Vec<(&str, &str)> = match (object_type, field_name) {
+        (ObjectType::StopPoint, _) => collections
+            .stop_points
+            .values()
+            .map(|sp| (sp.name.as_str(), sp.id.as_str()))
+            .collect(),
+        (ObjectType::StopArea, _) => collections
+            .stop_areas
+            .values()
+            .map(|sa| (sa.name.as_str(), sa.id.as_str()))
+            .collect(),
+        (ObjectType::Line, "code") => collections
+            .lines
+            .values()
+            .filter_map(|l| Some((l.code.as_deref()?, l.id.as_str())))
+            .collect(),
+        (ObjectType::Line, _) => collections
+            .lines
+            .values()
+            .map(|l| (l.name.as_str(), l.id.as_str()))
+            .collect(),
+        (ObjectType::Network, _) => collections
+            .networks
+            .values()
+            .map(|n| (n.name.as_str(), n.id.as_str()))
+            .collect(),
+        (ObjectType::VehicleJourney, _) => collections
+            .vehicle_journeys
+            .values()
+            .filter_map(|vj| Some((vj.headsign.as_deref()?, vj.id.as_str())))
+            .collect(),
+        _ => vec![],
+    };
+    let mut index: HashMap<String, Vec<String>> = HashMap::new();
+    for (value, id) in values_and_ids {
+        index
+            .entry(value.to_string())
+            .or_default()
+            .push(id.to_string());
+    }
+    index
+}
+
+/// Reading the translations of the names of the stops, the routes and the
+/// agencies, and of the headsigns of the trips. The translated objects are
+/// targeted either by their `record_id` or by the untranslated `field_value`.
+/// Rows of the other tables and fields are ignored.
+pub(crate) fn manage_translations<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "translations.txt";
+    let gtfs_translations = read_objects::<_, Translation>(file_handler, file, false)?;
+    let mut translations = vec![];
+    // built on first use, once per object type and field
+    let mut field_value_indexes: HashMap<(ObjectType, &str), HashMap<String, Vec<String>>> =
+        HashMap::new();
+    for gtfs_translation in gtfs_translations {
+        let (object_types, field_name) = match (
+            gtfs_translation.table_name.as_str(),
+            gtfs_translation.field_name.as_str(),
+        ) {
+            ("stops", "stop_name") => (vec![ObjectType::StopPoint, ObjectType::StopArea], "name"),
+            ("routes", "route_long_name") => (vec![ObjectType::Line], "name"),
+            ("routes", "route_short_name") => (vec![ObjectType::Line], "code"),
+            ("agency", "agency_name") => (vec![ObjectType::Network], "name"),
+            ("trips", "trip_headsign") => (vec![ObjectType::VehicleJourney], "headsign"),
+            (table_name, field_name) => {
+                debug!(
+                    "Problem reading {:?}: field {}.{} is not supported, translation ignored",
+                    file, table_name, field_name
+                );
+                continue;
+            }
+        };
+        let targets: Vec<(ObjectType, String)> =
+            match (&gtfs_translation.record_id, &gtfs_translation.field_value) {
+                (Some(record_id), _) => object_types
+                    .into_iter()
+                    .filter(|object_type| collections.contains_object(object_type, record_id))
+                    .map(|object_type| (object_type, record_id.clone()))
+                    .collect(),
+                (None, Some(field_value)) => {
+                    let mut targets = vec![];
+                    for object_type in object_types {
+                        let index = field_value_indexes
+                            .entry((object_type.clone(), field_name))
+                            .or_insert_with(|| {
+                                field_value_index(collections, &object_type, field_name)
+                            });
+                        for object_id in index.get(field_value).into_iter().flatten() {
+                            targets.push((object_type.clone(), object_id.clone()));
+                        }
+                    }
+                    targets
+                }
+                (None, None) => vec![],
+            };
+        if targets.is_empty() {
+            debug!(
+                "Problem reading {:?}: no object found for the translation of {}.{} (record_id={:?}, field_value={:?})",
+                file,
+                gtfs_translation.table_name,
+                gtfs_translation.field_name,
+                gtfs_translation.record_id,
+                gtfs_translation.field_value
+            );
+        }
+        for (object_type, object_id) in targets {
+            translations.push(objects::Translation {
+                object_type,
+                object_id,
+                field_name: field_name.to_string(),
+                language: gtfs_translation.language.clone(),
+                translation: gtfs_translation.translation.clone(),
+            });
+        }
+    }
+    collections.translations = Collection::new(translations);
+    Ok(())
+}
+
+/// Reading the metadata of the feed into the feed infos, only the first row
+/// of feed_info.txt is used. The feed infos already set (from the
+/// configuration) are kept, and an invalid feed_info.txt is ignored with a
+/// warning.
+pub(crate) fn manage_feed_info<H>(collections: &mut Collections, file_handler: &mut H)
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "feed_info.txt";
+    let feed_infos = match read_objects::<_, objects::FeedInfo>(file_handler, file, false) {
+        Ok(feed_infos) => feed_infos,
+        Err(error) => {
+            warn!(
+                "Problem reading {:?}, the file is ignored: {:#}",
+                file, error
+            );
+            return;
+        }
+    };
+    if feed_infos.len() > 1 {
+        warn!(
+            "Problem reading {:?}: {} rows found, only the first one is used",
+            file,
+            feed_infos.len()
+        );
+    }
+    if let Some(feed_info) = feed_infos.into_iter().next() {
+        let format_date = |date: objects::Date| date.format("%Y%m%d").to_string();
+        for (param, value) in [
+            ("feed_publisher_name", Some(feed_info.publisher_name)),
+            ("feed_publisher_url", Some(feed_info.publisher_url)),
+            ("feed_lang", Some(feed_info.lang)),
+            ("feed_start_date", feed_info.start_date.map(format_date)),
+            ("feed_end_date", feed_info.end_date.map(format_date)),
+            ("feed_version", feed_info.version),
+        ] {
+            if let Some(value) = value {
+                collections
+                    .feed_infos
+                    .entry(param.to_string())
+                    .or_insert(value);
+            }
+        }
+    }
+}
+
+/// Reading the time windows of the fare rules (GTFS fares v2), linked to the
+/// calendars by their service_id (normalized as the calendars are)
+pub fn manage_timeframes<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    calendar_configuration: &calendars::ReadConfiguration,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "timeframes.txt";
+    let gtfs_timeframes = read_objects::<_, objects::Timeframe>(file_handler, file, false)?;
+    let mut timeframes = vec![];
+    for mut timeframe in gtfs_timeframes {
+        timeframe.service_id = calendar_configuration.normalize_service_id(timeframe.service_id);
+        if !collections.calendars.contains_id(&timeframe.service_id) {
+            warn!(
+                "Problem reading {:?}: service_id={:?} not found",
+                file, timeframe.service_id
+            );
+            continue;
+        }
+        timeframes.push(timeframe);
+    }
+    collections.timeframes = Collection::new(timeframes);
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+struct FareAttribute {
+    fare_id: String,
+    #[serde(deserialize_with = "de_positive_decimal")]
+    price: Decimal,
+    #[serde(deserialize_with = "de_currency_code")]
+    currency_type: String,
+    // empty when the number of transfers is unlimited
+    #[serde(default)]
+    transfers: Option<u32>,
+    #[serde(default)]
+    transfer_duration: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FareRule {
+    fare_id: String,
+    #[serde(default)]
+    route_id: Option<String>,
+    #[serde(default)]
+    origin_id: Option<String>,
+    #[serde(default)]
+    destination_id: Option<String>,
+    #[serde(default)]
+    contains_id: Option<String>,
+}
+
+type FareCombination = (Option<String>, Option<(String, String)>);
+
+/// Reading the fares of fare_attributes.txt and fare_rules.txt as NTFS
+/// tickets, a ticket being created for each fare.
+///
+/// The routes of the rules are resolved to their lines, a fare without any
+/// route rule applying to all the networks. The rules with both an origin and
+/// a destination zone restrict the fare to these zones. The rules with only
+/// one of them or with containing zones can't be expressed in NTFS and are
+/// ignored, as is the payment method. The prices are valid over the validity
+/// period of the datasets.
+///
+/// NTFS applies all the restrictions of a ticket use on all its perimeters,
+/// so a ticket use is created for each distinct route and zones combination
+/// of the rules of a fare. Its id is the fare id, suffixed by `:1`, `:2`...
+/// when the fare has several combinations.
+pub fn manage_fares<H>(collections: &mut Collections, file_handler: &mut H) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "fare_attributes.txt";
+    let fare_attributes = read_objects::<_, FareAttribute>(file_handler, file, false)?;
+    if fare_attributes.is_empty() {
+        return Ok(());
+    }
+    let rules_file = "fare_rules.txt";
+    let fare_rules = read_objects::<_, FareRule>(file_handler, rules_file, false)?;
+    let (start_date, end_date) = collections.calculate_validity_period()?;
+    let fare_zones: HashSet<&str> = collections
+        .stop_points
+        .values()
+        .filter_map(|stop_point| stop_point.fare_zone_id.as_deref())
+        .collect();
+
+    let mut tickets = CollectionWithId::default();
+    let mut ticket_prices = vec![];
+    let mut fares = vec![];
+    for fare in fare_attributes {
+        if tickets.contains_id(&fare.fare_id) {
+            warn!(
+                "Problem reading {:?}: fare_id={:?} already found",
+                file, fare.fare_id
+            );
+            continue;
+        }
+        tickets.push(objects::Ticket {
+            id: fare.fare_id.clone(),
+            name: fare.fare_id.clone(),
+            comment: None,
+        })?;
+        ticket_prices.push(objects::TicketPrice {
+            ticket_id: fare.fare_id.clone(),
+            price: fare.price,
+            currency: fare.currency_type.clone(),
+            ticket_validity_start: start_date,
+            ticket_validity_end: end_date,
+        });
+        fares.push(fare);
+    }
+
+    // line and (origin, destination) zones of each rule, by fare
+    let mut combinations: HashMap<String, Vec<FareCombination>> = HashMap::new();
+    let mut fares_with_route_rules = HashSet::new();
+    for rule in fare_rules {
+        if !tickets.contains_id(&rule.fare_id) {
+            warn!(
+                "Problem reading {:?}: fare_id={:?} not found",
+                rules_file, rule.fare_id
+            );
+            continue;
+        }
+        let line_id = match &rule.route_id {
+            Some(route_id) => {
+                fares_with_route_rules.insert(rule.fare_id.clone());
+                let route = collections
+                    .routes
+                    .get(route_id)
+                    .or_else(|| collections.routes.get(&format!("{}_R", route_id)));
+                match route {
+                    Some(route) => Some(route.line_id.clone()),
+                    None => {
+                        warn!(
+                            "Problem reading {:?}: route_id={:?} not found",
+                            rules_file, route_id
+                        );
+                        continue;
+                    }
+                }
+            }
+            None => None,
+        };
+        if let Some(zone_id) = [&rule.origin_id, &rule.destination_id, &rule.contains_id]
+            .iter()
+            .filter_map(|zone_id| zone_id.as_deref())
+            .find(|zone_id| !fare_zones.contains(zone_id))
+        {
+            warn!(
+                "Problem reading {:?}: zone_id={:?} not found",
+                rules_file, zone_id
+            );
+            continue;
+        }
+        let zones = match (&rule.origin_id, &rule.destination_id, &rule.contains_id) {
+            (None, None, None) => None,
+            (Some(origin_id), Some(destination_id), None) => {
+                Some((origin_id.clone(), destination_id.clone()))
+            }
+            _ => {
+                warn!(
+                    "Problem reading {:?}: zones of fare_id={:?} not supported, rule ignored",
+                    rules_file, rule.fare_id
+                );
+                continue;
+            }
+        };
+        let fare_combinations = combinations.entry(rule.fare_id).or_default();
+        if !fare_combinations.contains(&(line_id.clone(), zones.clone())) {
+            fare_combinations.push((line_id, zones));
+        }
+    }
+
+    let mut ticket_uses = CollectionWithId::default();
+    let mut line_perimeters = BTreeSet::new();
+    let mut network_perimeters = BTreeSet::new();
+    let mut restrictions = BTreeSet::new();
+    for fare in fares {
+        let fare_combinations = combinations.remove(&fare.fare_id).unwrap_or_default();
+        let ticket_use_ids: Vec<String> = match fare_combinations.len() {
+            0 | 1 => vec![fare.fare_id.clone()],
+            nb_combinations => (1..=nb_combinations)
+                .map(|index| format!("{}:{}", fare.fare_id, index))
+                .collect(),
+        };
+        for ticket_use_id in &ticket_use_ids {
+            ticket_uses.push(objects::TicketUse {
+                id: ticket_use_id.clone(),
+                ticket_id: fare.fare_id.clone(),
+                max_transfers: fare.transfers,
+                boarding_time_limit: fare.transfer_duration,
+                alighting_time_limit: None,
+            })?;
+        }
+        if fare_combinations.is_empty() && !fares_with_route_rules.contains(&fare.fare_id) {
+            for network in collections.networks.values() {
+                network_perimeters.insert((fare.fare_id.clone(), network.id.clone()));
+            }
+        }
+        for (ticket_use_id, (line_id, zones)) in ticket_use_ids.into_iter().zip(fare_combinations) {
+            match line_id {
+                Some(line_id) => {
+                    line_perimeters.insert((ticket_use_id.clone(), line_id));
+                }
+                None => {
+                    for network in collections.networks.values() {
+                        network_perimeters.insert((ticket_use_id.clone(), network.id.clone()));
+                    }
+                }
+            }
+            if let Some((origin_id, destination_id)) = zones {
+                restrictions.insert((ticket_use_id, origin_id, destination_id));
+            }
+        }
+    }
+
+    collections.tickets = tickets;
+    collections.ticket_prices = Collection::new(ticket_prices);
+    collections.ticket_uses = ticket_uses;
+    let make_perimeter = |object_type: ObjectType| {
+        move |(ticket_use_id, object_id): (String, String)| objects::TicketUsePerimeter {
+            ticket_use_id,
+            object_type: object_type.clone(),
+            object_id,
+            perimeter_action: objects::PerimeterAction::Included,
+        }
+    };
+    collections.ticket_use_perimeters = Collection::new(
+        line_perimeters
+            .into_iter()
+            .map(make_perimeter(ObjectType::Line))
+            .chain(
+                network_perimeters
+                    .into_iter()
+                    .map(make_perimeter(ObjectType::Network)),
+            )
+            .collect(),
+    );
+    collections.ticket_use_restrictions = Collection::new(
+        restrictions
+            .into_iter()
+            .map(
+                |(ticket_use_id, use_origin, use_destination)| objects::TicketUseRestriction {
+                    ticket_use_id,
+                    restriction_type: objects::RestrictionType::Zone,
+                    use_origin,
+                    use_destination,
+                },
+            )
+            .collect(),
+    );
+    Ok(())
 }
 
 /// attributions applied to the dataset.
@@ -1332,33 +1967,104 @@ impl AttributionRule {
     }
 }
 
+/// Reading the attributions of attributions.txt, carried as is to the NTFS.
+///
+/// The agency of an attribution is resolved to its network, its route to the
+/// line of the route and its trip to the vehicle journey; an attribution with
+/// a reference not found is skipped. An attribution without identifier is
+/// identified by its position in the file.
+pub(crate) fn manage_attributions(
+    collections: &mut Collections,
+    gtfs_attributions: &[Attribution],
+) {
+    let file = "attributions.txt";
+    let mut attributions = CollectionWithId::default();
+    for (position, attribution) in gtfs_attributions.iter().enumerate() {
+        let id = attribution
+            .attribution_id
+            .clone()
+            .unwrap_or_else(|| format!("attribution:{}", position + 1));
+        let is_producer = attribution.is_producer.unwrap_or(false);
+        let is_operator = attribution.is_operator.unwrap_or(false);
+        let is_authority = attribution.is_authority.unwrap_or(false);
+        if !is_producer && !is_operator && !is_authority {
+            warn!(
+                "Problem reading {:?}: attribution_id={:?} has no role among producer, operator and authority",
+                file, id
+            );
+        }
+        if let Some(agency_id) = &attribution.agency_id {
+            if !collections.networks.contains_id(agency_id) {
+                warn!(
+                    "Problem reading {:?}: agency_id={:?} not found, attribution_id={:?} skipped",
+                    file, agency_id, id
+                );
+                continue;
+            }
+        }
+        let line_id = match &attribution.route_id {
+            Some(route_id) => {
+                let route = collections
+                    .routes
+                    .get(route_id)
+                    .or_else(|| collections.routes.get(&format!("{}_R", route_id)));
+                match route {
+                    Some(route) => Some(route.line_id.clone()),
+                    None => {
+                        warn!(
+                            "Problem reading {:?}: route_id={:?} not found, attribution_id={:?} skipped",
+                            file, route_id, id
+                        );
+                        continue;
+                    }
+                }
+            }
+            None => None,
+        };
+        if let Some(trip_id) = &attribution.trip_id {
+            if !collections.vehicle_journeys.contains_id(trip_id) {
+                warn!(
+                    "Problem reading {:?}: trip_id={:?} not found, attribution_id={:?} skipped",
+                    file, trip_id, id
+                );
+                continue;
+            }
+        }
+        skip_error_and_warn!(attributions.push(objects::Attribution {
+            id,
+            organization_name: attribution.organization_name.clone(),
+            is_producer,
+            is_operator,
+            is_authority,
+            network_id: attribution.agency_id.clone(),
+            line_id,
+            vehicle_journey_id: attribution.trip_id.clone(),
+            url: attribution.attribution_url.clone(),
+            email: attribution.attribution_email.clone(),
+            phone: attribution.attribution_phone.clone(),
+        }));
+    }
+    collections.attributions = attributions;
+}
+
+/// Attributions rules applied to the trip, from the operator attributions.
+pub(crate) fn attribution_rules(gtfs_attributions: &[Attribution]) -> Vec<AttributionRule> {
+    gtfs_attributions
+        .iter()
+        .filter(|attribution| attribution.is_operator == Some(true))
+        .map(AttributionRule::try_from)
+        .skip_error_and_warn()
+        .collect()
+}
+
 /// Read attributions rules applied to the trip.
+#[cfg(all(feature = "gtfs", feature = "parser"))]
 pub fn read_attributions<H>(file_handler: &mut H, file_name: &str) -> Result<Vec<AttributionRule>>
 where
     for<'a> &'a mut H: FileHandler,
 {
-    let (reader, path) = file_handler.get_file_if_exists(file_name)?;
-    let file_name = path.file_name();
-    let basename = file_name.map_or(path.to_string_lossy(), |b| b.to_string_lossy());
-    let mut attribution_rules = Vec::new();
-    if let Some(reader) = reader {
-        info!(file_name = %basename, "Reading");
-        let mut rdr = csv::ReaderBuilder::new()
-            .flexible(true)
-            .trim(csv::Trim::All)
-            .from_reader(reader);
-
-        for result in rdr.deserialize() {
-            let attribution: Attribution = result?;
-            if let Some(true) = attribution.is_operator {
-                let attribution_rule =
-                    skip_error_and_warn!(AttributionRule::try_from(&attribution));
-                attribution_rules.push(attribution_rule);
-            }
-        }
-    };
-
-    Ok(attribution_rules)
+    let gtfs_attributions = read_objects::<_, Attribution>(file_handler, file_name, false)?;
+    Ok(attribution_rules(&gtfs_attributions))
 }
 /// Apply attributions rules on trips.
 pub fn apply_attribution_rules(
@@ -1851,6 +2557,63 @@ mod tests {
         });
     }
 
+    #[test]
+    fn gtfs_routes_with_colors_to_normalize() {
+        let agency_content = "agency_id,agency_name,agency_url,agency_timezone\n\
+                              id_agency1,My agency 1,http://my-agency_url1.com,Europe/London";
+        let routes_content =
+            "route_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
+             route_1,1,My line 1,3,#7bc142,f0a\n\
+             route_2,2,My line 2,3,GGGGGG,\n\
+             route_3,3,My line 3,3,,#000";
+
+        let trips_content = "trip_id,route_id,service_id\n\
+                             1,route_1,service_1\n\
+                             2,route_2,service_1\n\
+                             3,route_3,service_1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "agency.txt", agency_content);
+            create_file_with_content(path, "routes.txt", routes_content);
+            create_file_with_content(path, "trips.txt", trips_content);
+
+            let mut collections = Collections::default();
+            let (networks, _) = super::read_agency(&mut handler).unwrap();
+            collections.networks = networks;
+            let (contributor, dataset, _) = read_config(None::<&str>).unwrap();
+            collections.contributors = CollectionWithId::new(vec![contributor]).unwrap();
+            collections.datasets = CollectionWithId::new(vec![dataset]).unwrap();
+            super::read_routes(&mut handler, &mut collections, true).unwrap();
+            let colors = |collections: &Collections, line_id: &str| {
+                let line = collections.lines.get(line_id).unwrap();
+                (
+                    line.color.as_ref().map(Rgb::to_string),
+                    line.text_color.as_ref().map(Rgb::to_string),
+                )
+            };
+            assert_eq!(
+                (Some("7BC142".to_string()), Some("FF00AA".to_string())),
+                colors(&collections, "route_1")
+            );
+            assert_eq!((None, None), colors(&collections, "route_2"));
+            assert_eq!(
+                (None, Some("000000".to_string())),
+                colors(&collections, "route_3")
+            );
+
+            super::set_default_line_colors(&mut collections.lines);
+            assert_eq!(
+                (Some("7BC142".to_string()), Some("FF00AA".to_string())),
+                colors(&collections, "route_1")
+            );
+            assert_eq!(
+                (Some("FFFFFF".to_string()), Some("000000".to_string())),
+                colors(&collections, "route_2")
+            );
+        });
+    }
+
     #[test]
     #[should_panic(expected = "Impossible to get agency id, several networks found")]
     fn gtfs_routes_without_agency_id_as_line_and_2_agencies() {
@@ -2646,12 +3409,98 @@ mod tests {
         });
     }
 
-    #[test]
-    fn gtfs_stop_times_deduplicated() {
-        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
-                              route_1,agency_1,1,My line 1,3,8F7A32,FFFFFF";
-
-        let stops_content =
+    fn stop_times_with_backward_departure() -> Collections {
+        crate::ModelBuilder::default()
+            .vj("vj1", |vj| {
+                vj.st("A", "23:50:00")
+                    .st("B", "24:30:00")
+                    .st("C", "24:20:00")
+                    .st("D", "25:10:00");
+            })
+            .vj("vj2", |vj| {
+                vj.st("A", "23:50:00")
+                    .st("B", "24:30:00")
+                    .st("C", "25:10:00");
+            })
+            .build()
+            .into_collections()
+    }
+
+    fn departure_times(collections: &Collections, vj_id: &str) -> Vec<Time> {
+        collections
+            .vehicle_journeys
+            .get(vj_id)
+            .unwrap()
+            .stop_times
+            .iter()
+            .map(|st| st.departure_time.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn gtfs_stop_times_order_warn() {
+        let mut collections = stop_times_with_backward_departure();
+        super::check_stop_times_order(&mut collections, StopTimesOrder::Warn).unwrap();
+        assert_eq!(
+            vec![
+                Time::new(23, 50, 0),
+                Time::new(24, 30, 0),
+                Time::new(24, 20, 0),
+                Time::new(25, 10, 0)
+            ],
+            departure_times(&collections, "vj1")
+        );
+    }
+
+    #[test]
+    fn gtfs_stop_times_order_repair() {
+        let mut collections = stop_times_with_backward_departure();
+        super::check_stop_times_order(&mut collections, StopTimesOrder::Repair).unwrap();
+        assert_eq!(
+            vec![
+                Time::new(23, 50, 0),
+                Time::new(24, 30, 0),
+                Time::new(24, 30, 0),
+                Time::new(25, 10, 0)
+            ],
+            departure_times(&collections, "vj1")
+        );
+        let stop_time = &collections.vehicle_journeys.get("vj1").unwrap().stop_times[2];
+        assert_eq!(Some(Time::new(24, 30, 0)), stop_time.arrival_time);
+        assert_eq!(Some(StopTimePrecision::Approximate), stop_time.precision);
+        // The overnight trip is in order
+        assert_eq!(
+            vec![
+                Time::new(23, 50, 0),
+                Time::new(24, 30, 0),
+                Time::new(25, 10, 0)
+            ],
+            departure_times(&collections, "vj2")
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "the departure time of the stop_sequence 2 of the trip 'vj1' is before the one of the stop_sequence 1"
+    )]
+    fn gtfs_stop_times_order_strict() {
+        let mut collections = stop_times_with_backward_departure();
+        super::check_stop_times_order(&mut collections, StopTimesOrder::Strict).unwrap();
+    }
+
+    #[test]
+    fn gtfs_stop_times_order_strict_overnight() {
+        let mut collections = stop_times_with_backward_departure();
+        collections.vehicle_journeys.retain(|vj| vj.id == "vj2");
+        super::check_stop_times_order(&mut collections, StopTimesOrder::Strict).unwrap();
+    }
+
+    #[test]
+    fn gtfs_stop_times_deduplicated() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
+                              route_1,agency_1,1,My line 1,3,8F7A32,FFFFFF";
+
+        let stops_content =
             "stop_id,stop_name,stop_desc,stop_lat,stop_lon,location_type,parent_station\n\
              sp:01,my stop point name 1,,0.1,1.1,0,\n\
              sp:02,my stop point name 2,,0.2,1.2,0,\n\
@@ -2922,6 +3771,45 @@ mod tests {
         });
     }
 
+    #[test]
+    fn read_not_possible_transfers() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,wheelchair_boarding\n\
+                             sp:01,my stop point name 1,48.857332,2.346331,0,,1\n\
+                             sp:02,my stop point name 2,48.858195,2.347448,0,,1";
+
+        let transfers_content = "from_stop_id,to_stop_id,transfer_type,min_transfer_time\n\
+                                 sp:01,sp:02,2,90000\n\
+                                 sp:02,sp:01,3,\n\
+                                 sp:02,unknown,3,";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "transfers.txt", transfers_content);
+
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let (stop_areas, stop_points, _) =
+                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+
+            let transfers = super::read_transfers(&mut handler, &stop_points, &stop_areas).unwrap();
+            let transfers: Vec<_> = transfers
+                .values()
+                .map(|t| {
+                    (
+                        t.from_stop_id.as_str(),
+                        t.min_transfer_time,
+                        t.is_possible(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                vec![("sp:01", Some(86399), true), ("sp:02", Some(86400), false)],
+                transfers
+            );
+        });
+    }
+
     #[test]
     fn gtfs_with_calendars_and_no_calendar_dates() {
         let content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
@@ -2942,6 +3830,7 @@ mod tests {
                 vec![Calendar {
                     id: "1".to_string(),
                     dates,
+                    ..Default::default()
                 },],
                 collections.calendars.into_vec()
             );
@@ -2968,6 +3857,7 @@ mod tests {
                 vec![Calendar {
                     id: "1".to_string(),
                     dates,
+                    ..Default::default()
                 }],
                 collections.calendars.into_vec()
             );
@@ -3002,10 +3892,12 @@ mod tests {
                     Calendar {
                         id: "1".to_string(),
                         dates,
+                        ..Default::default()
                     },
                     Calendar {
                         id: "2".to_string(),
                         dates: BTreeSet::new(),
+                        ..Default::default()
                     },
                 ],
                 collections.calendars.into_vec()
@@ -3023,6 +3915,49 @@ mod tests {
         });
     }
 
+    #[test]
+    fn read_timeframes() {
+        let calendar_dates_content = "service_id,date,exception_type\n\
+                                      1,20180212,1";
+        let timeframes_content = "timeframe_group_id,start_time,end_time,service_id\n\
+                                  peak,07:00:00,09:00:00,1\n\
+                                  all_day,,,1\n\
+                                  peak,17:00:00,19:00:00,unknown";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "calendar_dates.txt", calendar_dates_content);
+            create_file_with_content(path, "timeframes.txt", timeframes_content);
+
+            let mut collections = Collections::default();
+            calendars::manage_calendars(&mut handler, &mut collections).unwrap();
+            super::manage_timeframes(
+                &mut collections,
+                &mut handler,
+                &calendars::ReadConfiguration::default(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                vec![
+                    Timeframe {
+                        timeframe_group_id: "peak".to_string(),
+                        start_time: Some(Time::new(7, 0, 0)),
+                        end_time: Some(Time::new(9, 0, 0)),
+                        service_id: "1".to_string(),
+                    },
+                    Timeframe {
+                        timeframe_group_id: "all_day".to_string(),
+                        start_time: None,
+                        end_time: None,
+                        service_id: "1".to_string(),
+                    },
+                ],
+                collections.timeframes.into_vec()
+            );
+        });
+    }
+
     #[test]
     fn read_shapes() {
         let shapes_content = "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
@@ -3061,6 +3996,338 @@ mod tests {
         });
     }
 
+    #[test]
+    fn resolve_trip_with_unknown_shape() {
+        let shapes_content = "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
+                              1,4.4,3.3,20\n\
+                              1,2.2,1.1,5";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "shapes.txt", shapes_content);
+
+            let mut collections = Collections::default();
+            super::manage_shapes(&mut collections, &mut handler).unwrap();
+            collections.vehicle_journeys = CollectionWithId::new(vec![
+                VehicleJourney {
+                    id: "trip:1".to_string(),
+                    geometry_id: Some("1".to_string()),
+                    ..Default::default()
+                },
+                VehicleJourney {
+                    id: "trip:2".to_string(),
+                    geometry_id: Some("unknown".to_string()),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+            super::resolve_trip_shapes(&mut collections).unwrap();
+
+            assert_eq!(
+                vec![("trip:1", Some("1")), ("trip:2", None)],
+                extract(
+                    |vj| (vj.id.as_str(), vj.geometry_id.as_deref()),
+                    &collections.vehicle_journeys,
+                )
+            );
+            assert_eq!(
+                Geometry {
+                    id: "1".to_string(),
+                    geometry: line_string![(x: 1.1, y: 2.2), (x: 3.3, y: 4.4)].into(),
+                },
+                collections.geometries.into_vec()[0]
+            );
+        });
+    }
+
+    #[test]
+    fn read_feed_info() {
+        let feed_info_content =
+            "feed_publisher_name,feed_publisher_url,feed_lang,feed_start_date,feed_end_date,feed_version\n\
+             Hove,https://www.hove.com,fr,20180101,,v1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            let mut collections = Collections::default();
+            super::manage_feed_info(&mut collections, &mut handler);
+            assert!(collections.feed_infos.is_empty());
+
+            create_file_with_content(path, "feed_info.txt", feed_info_content);
+            collections
+                .feed_infos
+                .insert("feed_lang".to_string(), "en".to_string());
+            super::manage_feed_info(&mut collections, &mut handler);
+            assert_eq!(
+                vec![
+                    ("feed_lang", "en"),
+                    ("feed_publisher_name", "Hove"),
+                    ("feed_publisher_url", "https://www.hove.com"),
+                    ("feed_start_date", "20180101"),
+                    ("feed_version", "v1"),
+                ],
+                collections
+                    .feed_infos
+                    .iter()
+                    .map(|(param, value)| (param.as_str(), value.as_str()))
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn read_invalid_feed_info() {
+        let feed_info_content =
+            "feed_publisher_name,feed_publisher_url,feed_lang,feed_start_date\n\
+             Hove,https://www.hove.com,fr,2018-01-01";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "feed_info.txt", feed_info_content);
+            let mut collections = Collections::default();
+            super::manage_feed_info(&mut collections, &mut handler);
+            assert!(collections.feed_infos.is_empty());
+        });
+    }
+
+    #[test]
+    fn read_translations() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sp:01,Gare,0.1,1.2,0,sa:01\n\
+                             sa:01,Gare,0.1,1.2,1,";
+        let translations_content =
+            "table_name,field_name,language,translation,record_id,field_value\n\
+             stops,stop_name,en,Station,sp:01,\n\
+             stops,stop_name,de,Bahnhof,,Gare\n\
+             stops,stop_code,en,Code,sp:01,\n\
+             feed_info,feed_publisher_name,en,Publisher,,";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "translations.txt", translations_content);
+            let mut collections = Collections::default();
+            let mut equipments = EquipmentList::default();
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let (stop_areas, stop_points, _) =
+                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            collections.stop_areas = stop_areas;
+            collections.stop_points = stop_points;
+            super::manage_translations(&mut collections, &mut handler).unwrap();
+
+            assert_eq!(
+                vec![
+                    (ObjectType::StopPoint, "sp:01", "en", "Station"),
+                    (ObjectType::StopPoint, "sp:01", "de", "Bahnhof"),
+                    (ObjectType::StopArea, "sa:01", "de", "Bahnhof"),
+                ],
+                collections
+                    .translations
+                    .values()
+                    .map(|t| (
+                        t.object_type.clone(),
+                        t.object_id.as_str(),
+                        t.language.as_str(),
+                        t.translation.as_str()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+            assert!(collections
+                .translations
+                .values()
+                .all(|t| t.field_name == "name"));
+        });
+    }
+
+    #[test]
+    fn read_fares() {
+        let fare_attributes_content =
+            "fare_id,price,currency_type,payment_method,transfers,transfer_duration\n\
+             unlimited,1.5,EUR,0,,3600\n\
+             single,2,EUR,1,0,\n\
+             zoned,3,EUR,0,1,";
+        let fare_rules_content = "fare_id,route_id,origin_id,destination_id,contains_id\n\
+                                  single,route_1,,,\n\
+                                  single,unknown_route,,,\n\
+                                  zoned,,zone_1,zone_2,\n\
+                                  zoned,,zone_1,,\n\
+                                  zoned,,zone_1,unknown_zone,";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "fare_attributes.txt", fare_attributes_content);
+            create_file_with_content(path, "fare_rules.txt", fare_rules_content);
+            let mut collections = Collections {
+                datasets: CollectionWithId::from(Dataset {
+                    id: "dataset_1".to_string(),
+                    contributor_id: "contributor_1".to_string(),
+                    start_date: chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                    end_date: chrono::NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+                    dataset_type: None,
+                    extrapolation: false,
+                    desc: None,
+                    system: None,
+                }),
+                networks: CollectionWithId::from(Network {
+                    id: "network_1".to_string(),
+                    ..Default::default()
+                }),
+                routes: CollectionWithId::from(objects::Route {
+                    id: "route_1_R".to_string(),
+                    line_id: "line_1".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            for (stop_point_id, fare_zone_id) in [("sp_1", "zone_1"), ("sp_2", "zone_2")] {
+                collections
+                    .stop_points
+                    .push(StopPoint {
+                        id: stop_point_id.to_string(),
+                        fare_zone_id: Some(fare_zone_id.to_string()),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            super::manage_fares(&mut collections, &mut handler).unwrap();
+
+            assert_eq!(
+                vec!["unlimited", "single", "zoned"],
+                extract_ids(&collections.tickets)
+            );
+            assert_eq!(
+                vec![(None, Some(3600)), (Some(0), None), (Some(1), None)],
+                collections
+                    .ticket_uses
+                    .values()
+                    .map(|ticket_use| (ticket_use.max_transfers, ticket_use.boarding_time_limit))
+                    .collect::<Vec<_>>()
+            );
+            let price = collections.ticket_prices.values().next().unwrap();
+            assert_eq!(Decimal::new(15, 1), price.price);
+            assert_eq!("EUR", price.currency);
+            assert_eq!(
+                chrono::NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+                price.ticket_validity_end
+            );
+            assert_eq!(
+                vec![
+                    ("single", ObjectType::Line, "line_1"),
+                    ("unlimited", ObjectType::Network, "network_1"),
+                    ("zoned", ObjectType::Network, "network_1"),
+                ],
+                collections
+                    .ticket_use_perimeters
+                    .values()
+                    .map(|perimeter| (
+                        perimeter.ticket_use_id.as_str(),
+                        perimeter.object_type.clone(),
+                        perimeter.object_id.as_str()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                vec![("zoned", "zone_1", "zone_2")],
+                collections
+                    .ticket_use_restrictions
+                    .values()
+                    .map(|restriction| (
+                        restriction.ticket_use_id.as_str(),
+                        restriction.use_origin.as_str(),
+                        restriction.use_destination.as_str()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    fn read_fares_with_mixed_route_and_zone_rules() {
+        let fare_attributes_content = "fare_id,price,currency_type,payment_method,transfers\n\
+                                       mixed,2,EUR,0,0";
+        let fare_rules_content = "fare_id,route_id,origin_id,destination_id,contains_id\n\
+                                  mixed,route_a,,,\n\
+                                  mixed,route_b,zone_1,zone_2,\n\
+                                  mixed,route_b,zone_1,zone_2,";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "fare_attributes.txt", fare_attributes_content);
+            create_file_with_content(path, "fare_rules.txt", fare_rules_content);
+            let mut collections = Collections {
+                datasets: CollectionWithId::from(Dataset {
+                    id: "dataset_1".to_string(),
+                    contributor_id: "contributor_1".to_string(),
+                    start_date: chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                    end_date: chrono::NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+                    dataset_type: None,
+                    extrapolation: false,
+                    desc: None,
+                    system: None,
+                }),
+                ..Default::default()
+            };
+            for (route_id, line_id) in [("route_a", "line_a"), ("route_b", "line_b")] {
+                collections
+                    .routes
+                    .push(objects::Route {
+                        id: route_id.to_string(),
+                        line_id: line_id.to_string(),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            for (stop_point_id, fare_zone_id) in [("sp_1", "zone_1"), ("sp_2", "zone_2")] {
+                collections
+                    .stop_points
+                    .push(StopPoint {
+                        id: stop_point_id.to_string(),
+                        fare_zone_id: Some(fare_zone_id.to_string()),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            super::manage_fares(&mut collections, &mut handler).unwrap();
+
+            assert_eq!(vec!["mixed"], extract_ids(&collections.tickets));
+            // The zones only restrict the fare on route_b
+            assert_eq!(
+                vec![("mixed:1", "mixed"), ("mixed:2", "mixed")],
+                collections
+                    .ticket_uses
+                    .values()
+                    .map(|ticket_use| (ticket_use.id.as_str(), ticket_use.ticket_id.as_str()))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                vec![
+                    ("mixed:1", ObjectType::Line, "line_a"),
+                    ("mixed:2", ObjectType::Line, "line_b"),
+                ],
+                collections
+                    .ticket_use_perimeters
+                    .values()
+                    .map(|perimeter| (
+                        perimeter.ticket_use_id.as_str(),
+                        perimeter.object_type.clone(),
+                        perimeter.object_id.as_str()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                vec![("mixed:2", "zone_1", "zone_2")],
+                collections
+                    .ticket_use_restrictions
+                    .values()
+                    .map(|restriction| (
+                        restriction.ticket_use_id.as_str(),
+                        restriction.use_origin.as_str(),
+                        restriction.use_destination.as_str()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
     #[test]
     fn read_shapes_with_no_shapes_file() {
         test_in_tmp_dir(|path| {
@@ -3347,6 +4614,90 @@ mod tests {
         })
     }
     #[test]
+    fn read_bidirectional_pathway_once() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             stoppoint_id,my stop name,0.1,1.2,0,stoparea_id\n\
+                             stoparea_id,stop area name,0.1,1.2,1,\n\
+                             entrance_id,entrance name,0.1,1.2,2,stoparea_id";
+        let pathway_content = "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional\n\
+                               1,entrance_id,stoppoint_id,1,1\n\
+                               2,stoppoint_id,entrance_id,1,1\n\
+                               3,stoppoint_id,entrance_id,2,1\n\
+                               4,entrance_id,unknown_id,1,0";
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "pathways.txt", pathway_content);
+            let mut collections = Collections::default();
+            let mut equipments = EquipmentList::default();
+            let (_, stop_points, stop_locations) =
+                super::read_stops(&mut handler, &mut collections.comments, &mut equipments)
+                    .unwrap();
+            collections.stop_points = stop_points;
+            collections.stop_locations = stop_locations;
+
+            super::manage_pathways(&mut collections, &mut handler).unwrap();
+            // the stairs between the same stops are not the reverse of the
+            // walkway
+            assert_eq!(vec!["1", "3"], extract_ids(&collections.pathways));
+            let pathway = collections.pathways.get("1").unwrap();
+            assert!(pathway.is_bidirectional);
+            assert_eq!(StopType::StopEntrance, pathway.from_stop_type);
+            assert_eq!(StopType::Point, pathway.to_stop_type);
+        })
+    }
+    #[test]
+    fn read_attributions_as_collection() {
+        let attributions_content = "attribution_id,agency_id,route_id,trip_id,organization_name,is_producer,is_operator,is_authority,attribution_url\n\
+                                    1,agency_1,,,producer,1,0,0,http://producer.com\n\
+                                    2,,route_1,,operator,0,1,0,\n\
+                                    3,,,trip_1,no role,,,,\n\
+                                    4,,unknown_route,,operator,0,1,0,\n\
+                                    ,,,,authority,0,0,1,";
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "attributions.txt", attributions_content);
+            let mut collections = Collections::default();
+            collections.networks = CollectionWithId::from(objects::Network {
+                id: "agency_1".to_string(),
+                ..Default::default()
+            });
+            collections.routes = CollectionWithId::from(objects::Route {
+                id: "route_1".to_string(),
+                line_id: "line_1".to_string(),
+                ..Default::default()
+            });
+            collections.vehicle_journeys = CollectionWithId::from(VehicleJourney {
+                id: "trip_1".to_string(),
+                ..Default::default()
+            });
+
+            let gtfs_attributions = read_objects(&mut handler, "attributions.txt", false).unwrap();
+            super::manage_attributions(&mut collections, &gtfs_attributions);
+            assert_eq!(
+                vec!["1", "2", "3", "attribution:5"],
+                extract_ids(&collections.attributions)
+            );
+            let producer = collections.attributions.get("1").unwrap();
+            assert!(producer.is_producer);
+            assert_eq!(Some("agency_1"), producer.network_id.as_deref());
+            assert_eq!(Some("http://producer.com"), producer.url.as_deref());
+            let operator = collections.attributions.get("2").unwrap();
+            assert!(operator.is_operator);
+            assert_eq!(Some("line_1"), operator.line_id.as_deref());
+            let no_role = collections.attributions.get("3").unwrap();
+            assert!(!no_role.is_producer && !no_role.is_operator && !no_role.is_authority);
+            assert_eq!(Some("trip_1"), no_role.vehicle_journey_id.as_deref());
+            assert!(
+                collections
+                    .attributions
+                    .get("attribution:5")
+                    .unwrap()
+                    .is_authority
+            );
+        })
+    }
+    #[test]
     fn read_levels() {
         let stops_content =
             "stop_id,stop_code,stop_name,stop_lat,stop_lon,location_type,parent_station,level_id\n\
@@ -3370,6 +4721,43 @@ mod tests {
         })
     }
     #[test]
+    fn remove_unknown_levels_of_stops() {
+        let stops_content =
+            "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,level_id\n\
+             stoppoint_id,my stop name,0.1,1.2,0,stoparea_id,underground\n\
+             stoparea_id,stop area name,0.1,1.2,1,,unknown\n\
+             entrance_id,entrance name,0.1,1.2,2,stoparea_id,ground";
+        let level_content = "level_id,level_index,level_name\n\
+                             ground,0,Ground\n\
+                             underground,-1.5,Mezzanine";
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "levels.txt", level_content);
+            let mut collections = Collections::default();
+            let mut equipments = EquipmentList::default();
+            let (stop_areas, stop_points, stop_locations) =
+                super::read_stops(&mut handler, &mut collections.comments, &mut equipments)
+                    .unwrap();
+            collections.stop_areas = stop_areas;
+            collections.stop_points = stop_points;
+            collections.stop_locations = stop_locations;
+
+            super::manage_levels(&mut collections, &mut handler).unwrap();
+            assert_eq!(2, collections.levels.len());
+            assert_eq!(
+                -1.5,
+                collections.levels.get("underground").unwrap().level_index
+            );
+            let stop_point = collections.stop_points.get("stoppoint_id").unwrap();
+            assert_eq!(Some("underground"), stop_point.level_id.as_deref());
+            let stop_area = collections.stop_areas.get("stoparea_id").unwrap();
+            assert_eq!(None, stop_area.level_id);
+            let entrance = collections.stop_locations.get("entrance_id").unwrap();
+            assert_eq!(Some("ground"), entrance.level_id.as_deref());
+        })
+    }
+    #[test]
     fn gtfs_stop_times_precision() {
         let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
                               route_1,agency_1,1,My line 1,3,8F7A32,FFFFFF";
@@ -3460,6 +4848,158 @@ mod tests {
         });
     }
 
+    #[test]
+    fn gtfs_frequencies_exact_times() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
+                              route_1,agency_1,1,My line 1,3,8F7A32,FFFFFF";
+
+        let stops_content =
+            "stop_id,stop_name,stop_desc,stop_lat,stop_lon,location_type,parent_station\n\
+                             sp:01,my stop point name 1,my first desc,0.1,1.2,0,\n\
+                             sp:02,my stop point name 2,,0.2,1.5,0,";
+
+        let trips_content =
+            "trip_id,route_id,direction_id,service_id,wheelchair_accessible,bikes_allowed\n\
+             1,route_1,0,service_1,,";
+
+        let stop_times_content = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                                  1,06:00:00,06:00:00,sp:01,1\n\
+                                  1,06:10:00,06:10:00,sp:02,2";
+
+        let frequencies_content = "trip_id,start_time,end_time,headway_secs,exact_times\n\
+                                   1,06:00:00,07:00:00,1800,1\n\
+                                   1,08:00:00,08:25:00,600,0";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "routes.txt", routes_content);
+            create_file_with_content(path, "trips.txt", trips_content);
+            create_file_with_content(path, "stop_times.txt", stop_times_content);
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "frequencies.txt", frequencies_content);
+
+            let mut collections = Collections::default();
+            let (contributor, dataset, _) = read_config(None::<&str>).unwrap();
+            collections.contributors = CollectionWithId::new(vec![contributor]).unwrap();
+            collections.datasets = CollectionWithId::new(vec![dataset]).unwrap();
+
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let (_, stop_points, _) =
+                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            collections.stop_points = stop_points;
+
+            super::read_routes(&mut handler, &mut collections, false).unwrap();
+            super::manage_stop_times(&mut collections, &mut handler, true, None).unwrap();
+            super::manage_frequencies(&mut collections, &mut handler).unwrap();
+
+            let expanded: Vec<_> = collections
+                .vehicle_journeys
+                .values()
+                .map(|vj| {
+                    (
+                        vj.id.as_str(),
+                        vj.stop_times[0].departure_time.unwrap(),
+                        vj.stop_times[0].precision.clone(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                vec![
+                    ("1-0", Time::new(6, 0, 0), Some(StopTimePrecision::Exact)),
+                    ("1-1", Time::new(6, 30, 0), Some(StopTimePrecision::Exact)),
+                    (
+                        "1-2",
+                        Time::new(8, 0, 0),
+                        Some(StopTimePrecision::Estimated)
+                    ),
+                    (
+                        "1-3",
+                        Time::new(8, 10, 0),
+                        Some(StopTimePrecision::Estimated)
+                    ),
+                    (
+                        "1-4",
+                        Time::new(8, 20, 0),
+                        Some(StopTimePrecision::Estimated)
+                    ),
+                ],
+                expanded
+            );
+        });
+    }
+
+    #[test]
+    fn gtfs_frequencies_with_attributions() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
+                              route_1,agency_1,1,My line 1,3,8F7A32,FFFFFF";
+
+        let stops_content =
+            "stop_id,stop_name,stop_desc,stop_lat,stop_lon,location_type,parent_station\n\
+                             sp:01,my stop point name 1,my first desc,0.1,1.2,0,\n\
+                             sp:02,my stop point name 2,,0.2,1.5,0,";
+
+        let trips_content =
+            "trip_id,route_id,direction_id,service_id,wheelchair_accessible,bikes_allowed\n\
+             1,route_1,0,service_1,,";
+
+        let stop_times_content = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                                  1,06:00:00,06:00:00,sp:01,1\n\
+                                  1,06:10:00,06:10:00,sp:02,2";
+
+        let frequencies_content = "trip_id,start_time,end_time,headway_secs,exact_times\n\
+                                   1,06:00:00,07:00:00,1800,1";
+
+        let attributions_content =
+            "attribution_id,trip_id,organization_name,is_producer,is_operator,is_authority\n\
+             attribution_1,1,operator,0,1,0";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "routes.txt", routes_content);
+            create_file_with_content(path, "trips.txt", trips_content);
+            create_file_with_content(path, "stop_times.txt", stop_times_content);
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "frequencies.txt", frequencies_content);
+            create_file_with_content(path, "attributions.txt", attributions_content);
+
+            let mut collections = Collections::default();
+            let (contributor, dataset, _) = read_config(None::<&str>).unwrap();
+            collections.contributors = CollectionWithId::new(vec![contributor]).unwrap();
+            collections.datasets = CollectionWithId::new(vec![dataset]).unwrap();
+
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let (_, stop_points, _) =
+                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            collections.stop_points = stop_points;
+
+            super::read_routes(&mut handler, &mut collections, false).unwrap();
+            super::manage_stop_times(&mut collections, &mut handler, true, None).unwrap();
+            let gtfs_attributions = read_objects(&mut handler, "attributions.txt", false).unwrap();
+            super::manage_attributions(&mut collections, &gtfs_attributions);
+            super::manage_frequencies(&mut collections, &mut handler).unwrap();
+
+            let attributions: Vec<_> = collections
+                .attributions
+                .values()
+                .map(|attribution| {
+                    (
+                        attribution.id.as_str(),
+                        attribution.vehicle_journey_id.as_deref(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                vec![
+                    ("attribution_1-0", Some("1-0")),
+                    ("attribution_1-1", Some("1-1"))
+                ],
+                attributions
+            );
+        });
+    }
+
     mod read_gtfs_routes {
         use super::*;
         use crate::{file_handler::PathFileHandler, model::Collections};
@@ -1022,6 +1022,7 @@ mod tests {
             .push(objects::Calendar {
                 id: "2".to_string(),
                 dates,
+                ..Default::default()
             })
             .unwrap();
         collections
@@ -1300,6 +1301,26 @@ mod tests {
         assert_eq!(expected, transfer);
     }
 
+    #[test]
+    fn ntfs_not_possible_transfers_to_gtfs_transfers() {
+        let transfer = Transfer::from(&NtfsTransfer {
+            from_stop_id: "sp:01".to_string(),
+            to_stop_id: "sp:02".to_string(),
+            min_transfer_time: Some(NtfsTransfer::NOT_POSSIBLE_TRANSFER_TIME),
+            real_min_transfer_time: Some(NtfsTransfer::NOT_POSSIBLE_TRANSFER_TIME),
+            equipment_id: None,
+        });
+
+        let expected = Transfer {
+            from_stop_id: "sp:01".to_string(),
+            to_stop_id: "sp:02".to_string(),
+            transfer_type: TransferType::NotPossible,
+            min_transfer_time: None,
+        };
+
+        assert_eq!(expected, transfer);
+    }
+
     #[test]
     fn write_calendar_file_from_calendar() {
         let mut dates = BTreeSet::new();
@@ -1311,10 +1332,12 @@ mod tests {
             Calendar {
                 id: "1".to_string(),
                 dates,
+                ..Default::default()
             },
             Calendar {
                 id: "2".to_string(),
                 dates: BTreeSet::new(),
+                ..Default::default()
             },
         ])
         .unwrap();
@@ -1336,6 +1359,35 @@ mod tests {
         tmp_dir.close().expect("delete temp dir");
     }
 
+    #[test]
+    fn write_calendar_file_header_has_all_weekdays() {
+        let mut dates = BTreeSet::new();
+        //monday
+        dates.insert(chrono::NaiveDate::from_ymd_opt(2018, 5, 7).unwrap());
+        let calendar = CollectionWithId::new(vec![Calendar {
+            id: "1".to_string(),
+            dates,
+            ..Default::default()
+        }])
+        .unwrap();
+        let tmp_dir = tempdir().expect("create temp dir");
+        write_calendar_dates(tmp_dir.path(), &calendar).unwrap();
+
+        let output_file_path = tmp_dir.path().join("calendar.txt");
+        let mut output_file = File::open(output_file_path.clone())
+            .unwrap_or_else(|_| panic!("file {:?} not found", output_file_path));
+        let mut output_contents = String::new();
+        output_file.read_to_string(&mut output_contents).unwrap();
+        assert_eq!(
+            Some(
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date"
+            ),
+            output_contents.lines().next()
+        );
+
+        tmp_dir.close().expect("delete temp dir");
+    }
+
     #[test]
     fn ntfs_vehicle_journeys_to_stop_times() {
         let stop_points = CollectionWithId::from(StopPoint {
@@ -0,0 +1,490 @@
+// Copyright (C) 2017 Hove and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Apply the trip updates of a [GTFS-Realtime] feed to the static
+//! [`Collections`], see [`apply_trip_updates`].
+//!
+//! [GTFS-Realtime]: https://gtfs.org/realtime/reference/
+
+use crate::{
+    model::Collections,
+    objects::{Calendar, Date, StopTime, Time, VehicleJourney},
+    Result,
+};
+use gtfs_realtime::{trip_descriptor, FeedMessage, TripUpdate};
+use std::{collections::BTreeSet, convert::TryFrom};
+use tracing::warn;
+
+/// Summary of the trip updates applied by [`apply_trip_updates`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TripUpdatesReport {
+    /// Number of trips cancelled on a date
+    pub cancelled: usize,
+    /// Number of trips delayed on a date
+    pub delayed: usize,
+    /// Trip ids of the realtime feed unknown in the static collections, to
+    /// monitor the drift between both feeds
+    pub unknown_trip_ids: BTreeSet<String>,
+}
+
+/// Id of the vehicle journey, and of its calendar, holding the delayed stop
+/// times of the vehicle journey `vehicle_journey_id` on `date` (and id of
+/// its stop times, from the ones of the vehicle journey)
+pub fn realtime_id(vehicle_journey_id: &str, date: Date) -> String {
+    format!("{}:RealTime:{}", vehicle_journey_id, date.format("%Y%m%d"))
+}
+
+/// Apply the trip updates of `feed` to `collections`.
+///
+/// A trip update is matched on its `trip_id` and its `start_date`, which has
+/// to be an active date of the calendar of the vehicle journey:
+/// - a cancelled trip is removed from this date
+/// - a delayed trip is removed from this date and a copy of the vehicle
+///   journey, with its stop times shifted by the delays, is added on this
+///   date only (see [`realtime_id`])
+///
+/// The date is removed from a copy of the calendar when the calendar is
+/// shared with other vehicle journeys. When the trip has already been
+/// delayed on this date, by a previous feed, its realtime vehicle journey is
+/// updated (or cancelled) instead. The delay of a stop time update is
+/// propagated to the next stop times, until the next update, as in
+/// GTFS-Realtime.
+/// The trip updates of unknown trips are ignored and their trip ids
+/// reported, the other ignored trip updates are logged. The emptied
+/// calendars are kept, until [`Collections::sanitize`].
+pub fn apply_trip_updates(
+    collections: &mut Collections,
+    feed: &FeedMessage,
+) -> Result<TripUpdatesReport> {
+    let mut report = TripUpdatesReport::default();
+    let trip_updates = feed
+        .entity
+        .iter()
+        .filter(|entity| !entity.is_deleted())
+        .filter_map(|entity| entity.trip_update.as_ref());
+    for trip_update in trip_updates {
+        let trip_id = trip_update.trip.trip_id();
+        if trip_id.is_empty() {
+            warn!("trip update without trip_id ignored");
+            continue;
+        }
+        let vehicle_journey = match collections.vehicle_journeys.get(trip_id) {
+            Some(vehicle_journey) => vehicle_journey.clone(),
+            None => {
+                report.unknown_trip_ids.insert(trip_id.to_string());
+                continue;
+            }
+        };
+        let start_date = trip_update.trip.start_date();
+        let date = match Date::parse_from_str(start_date, "%Y%m%d") {
+            Ok(date) => date,
+            Err(_) => {
+                warn!(
+                    "trip update of trip {} ignored: invalid start_date {:?}",
+                    trip_id, start_date
+                );
+                continue;
+            }
+        };
+        let is_cancelled = trip_update.trip.schedule_relationship()
+            == trip_descriptor::ScheduleRelationship::Canceled;
+        let id = realtime_id(&vehicle_journey.id, date);
+        if collections.vehicle_journeys.contains_id(&id) {
+            // Already delayed by a previous feed, updated in place
+            if is_cancelled {
+                if let Some(mut calendar) = collections.calendars.get_mut(&id) {
+                    calendar.remove_date(&date);
+                }
+                report.cancelled += 1;
+            } else if let Some(stop_times) =
+                delayed_stop_times(collections, &vehicle_journey, trip_update)
+            {
+                if let Some(mut calendar) = collections.calendars.get_mut(&id) {
+                    calendar.dates.insert(date);
+                }
+                if let Some(mut realtime_vehicle_journey) =
+                    collections.vehicle_journeys.get_mut(&id)
+                {
+                    realtime_vehicle_journey.stop_times = stop_times;
+                }
+                report.delayed += 1;
+            }
+            continue;
+        }
+        let is_active = collections
+            .calendars
+            .get(&vehicle_journey.service_id)
+            .map_or(false, |calendar| calendar.dates.contains(&date));
+        if !is_active {
+            warn!(
+                "trip update of trip {} ignored: service {} is not active on {}",
+                trip_id, vehicle_journey.service_id, date
+            );
+            continue;
+        }
+        if is_cancelled {
+            remove_date(collections, &vehicle_journey, date)?;
+            report.cancelled += 1;
+            continue;
+        }
+        let stop_times = match delayed_stop_times(collections, &vehicle_journey, trip_update) {
+            Some(stop_times) => stop_times,
+            None => continue,
+        };
+        if stop_times == vehicle_journey.stop_times {
+            continue;
+        }
+        remove_date(collections, &vehicle_journey, date)?;
+        let mut calendar = Calendar::new(id.clone());
+        calendar.dates.insert(date);
+        collections.calendars.push(calendar)?;
+        for stop_time in &vehicle_journey.stop_times {
+            let key = (vehicle_journey.id.clone(), stop_time.sequence);
+            let realtime_key = (id.clone(), stop_time.sequence);
+            if let Some(headsign) = collections.stop_time_headsigns.get(&key).cloned() {
+                collections
+                    .stop_time_headsigns
+                    .insert(realtime_key.clone(), headsign);
+            }
+            if let Some(comment) = collections.stop_time_comments.get(&key).cloned() {
+                collections
+                    .stop_time_comments
+                    .insert(realtime_key.clone(), comment);
+            }
+            if let Some(stop_time_id) = collections.stop_time_ids.get(&key).cloned() {
+                let realtime_stop_time_id = realtime_id(&stop_time_id, date);
+                collections
+                    .stop_time_ids
+                    .insert(realtime_key, realtime_stop_time_id);
+            }
+        }
+        collections.vehicle_journeys.push(VehicleJourney {
+            id: id.clone(),
+            service_id: id,
+            stop_times,
+            ..vehicle_journey
+        })?;
+        report.delayed += 1;
+    }
+    Ok(report)
+}
+
+/// Remove `date` from the calendar of `vehicle_journey`, or from a copy of
+/// it if the calendar is shared with other vehicle journeys
+fn remove_date(
+    collections: &mut Collections,
+    vehicle_journey: &VehicleJourney,
+    date: Date,
+) -> Result<()> {
+    let service_id = &vehicle_journey.service_id;
+    let is_shared = collections
+        .vehicle_journeys
+        .values()
+        .any(|vj| &vj.service_id == service_id && vj.id != vehicle_journey.id);
+    if is_shared {
+        let mut calendar = collections.calendars.get(service_id).unwrap().clone();
+        calendar.id = format!("{}:{}", service_id, vehicle_journey.id);
+        calendar.remove_date(&date);
+        let calendar_id = calendar.id.clone();
+        collections.calendars.push(calendar)?;
+        if let Some(mut vj) = collections.vehicle_journeys.get_mut(&vehicle_journey.id) {
+            vj.service_id = calendar_id;
+        }
+    } else if let Some(mut calendar) = collections.calendars.get_mut(service_id) {
+        calendar.remove_date(&date);
+    }
+    Ok(())
+}
+
+/// Shift `time` by `delay` seconds, `None` if it becomes negative
+fn shift(time: Option<Time>, delay: i32) -> Option<Option<Time>> {
+    match time {
+        Some(time) => {
+            let seconds = i64::from(time.total_seconds()) + i64::from(delay);
+            u32::try_from(seconds)
+                .ok()
+                .map(|seconds| Some(Time::new(0, 0, seconds)))
+        }
+        None => Some(None),
+    }
+}
+
+/// The stop times of `vehicle_journey` shifted by the delays of
+/// `trip_update`, `None` if a time would be before the start of the service
+/// day
+fn delayed_stop_times(
+    collections: &Collections,
+    vehicle_journey: &VehicleJourney,
+    trip_update: &TripUpdate,
+) -> Option<Vec<StopTime>> {
+    let mut delay = trip_update.delay.unwrap_or_default();
+    let mut stop_times = Vec::with_capacity(vehicle_journey.stop_times.len());
+    for stop_time in &vehicle_journey.stop_times {
+        let stop_point_id = &collections.stop_points[stop_time.stop_point_idx].id;
+        let update =
+            trip_update
+                .stop_time_update
+                .iter()
+                .find(|update| match update.stop_sequence {
+                    Some(stop_sequence) => stop_sequence == stop_time.sequence,
+                    None => update.stop_id() == stop_point_id.as_str(),
+                });
+        let (arrival_delay, departure_delay) = match update {
+            Some(update) => {
+                let arrival_delay = update
+                    .arrival
+                    .as_ref()
+                    .and_then(|event| event.delay)
+                    .or_else(|| update.departure.as_ref().and_then(|event| event.delay))
+                    .unwrap_or(delay);
+                let departure_delay = update
+                    .departure
+                    .as_ref()
+                    .and_then(|event| event.delay)
+                    .unwrap_or(arrival_delay);
+                (arrival_delay, departure_delay)
+            }
+            None => (delay, delay),
+        };
+        delay = departure_delay;
+        let arrival_time = shift(stop_time.arrival_time, arrival_delay);
+        let departure_time = shift(stop_time.departure_time, departure_delay);
+        let (arrival_time, departure_time) = match (arrival_time, departure_time) {
+            (Some(arrival_time), Some(departure_time)) => (arrival_time, departure_time),
+            _ => {
+                warn!(
+                    "trip update of trip {} ignored: the delay {} at stop {} is before the start of the service day",
+                    vehicle_journey.id, departure_delay, stop_point_id
+                );
+                return None;
+            }
+        };
+        stop_times.push(StopTime {
+            arrival_time,
+            departure_time,
+            ..stop_time.clone()
+        });
+    }
+    Some(stop_times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModelBuilder;
+    use gtfs_realtime::{
+        trip_update::{StopTimeEvent, StopTimeUpdate},
+        FeedEntity, FeedHeader, TripDescriptor,
+    };
+    use pretty_assertions::assert_eq;
+
+    fn collections() -> Collections {
+        ModelBuilder::default()
+            .calendar("c1", &["2020-01-01", "2020-01-02"])
+            .vj("vj1", |vj| {
+                vj.calendar("c1")
+                    .st("A", "10:00:00")
+                    .st("B", "10:10:00")
+                    .st("C", "10:20:00");
+            })
+            .vj("vj2", |vj| {
+                vj.calendar("c1").st("A", "11:00:00").st("B", "11:10:00");
+            })
+            .build()
+            .into_collections()
+    }
+
+    fn feed(trip_updates: Vec<TripUpdate>) -> FeedMessage {
+        FeedMessage {
+            header: FeedHeader {
+                gtfs_realtime_version: "2.0".to_string(),
+                ..Default::default()
+            },
+            entity: trip_updates
+                .into_iter()
+                .enumerate()
+                .map(|(i, trip_update)| FeedEntity {
+                    id: i.to_string(),
+                    trip_update: Some(trip_update),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn trip_update(
+        trip_id: &str,
+        schedule_relationship: trip_descriptor::ScheduleRelationship,
+        stop_time_update: Vec<StopTimeUpdate>,
+    ) -> TripUpdate {
+        TripUpdate {
+            trip: TripDescriptor {
+                trip_id: Some(trip_id.to_string()),
+                start_date: Some("20200101".to_string()),
+                schedule_relationship: Some(schedule_relationship as i32),
+                ..Default::default()
+            },
+            stop_time_update,
+            ..Default::default()
+        }
+    }
+
+    fn dates(collections: &Collections, vehicle_journey_id: &str) -> Vec<String> {
+        let service_id = &collections
+            .vehicle_journeys
+            .get(vehicle_journey_id)
+            .unwrap()
+            .service_id;
+        collections
+            .calendars
+            .get(service_id)
+            .unwrap()
+            .dates
+            .iter()
+            .map(|date| date.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn cancel_trip_on_start_date() {
+        let mut collections = collections();
+        let feed = feed(vec![trip_update(
+            "vj1",
+            trip_descriptor::ScheduleRelationship::Canceled,
+            vec![],
+        )]);
+
+        let report = apply_trip_updates(&mut collections, &feed).unwrap();
+
+        assert_eq!(1, report.cancelled);
+        assert_eq!(vec!["2020-01-02"], dates(&collections, "vj1"));
+        // The calendar is shared, vj2 still runs on both dates
+        assert_eq!(vec!["2020-01-01", "2020-01-02"], dates(&collections, "vj2"));
+    }
+
+    #[test]
+    fn shift_stop_times_by_delay() {
+        let mut collections = collections();
+        let feed = feed(vec![trip_update(
+            "vj1",
+            trip_descriptor::ScheduleRelationship::Scheduled,
+            vec![StopTimeUpdate {
+                stop_id: Some("B".to_string()),
+                arrival: Some(StopTimeEvent {
+                    delay: Some(120),
+                    ..Default::default()
+                }),
+                departure: Some(StopTimeEvent {
+                    delay: Some(300),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+        )]);
+
+        let report = apply_trip_updates(&mut collections, &feed).unwrap();
+
+        assert_eq!(1, report.delayed);
+        assert_eq!(vec!["2020-01-02"], dates(&collections, "vj1"));
+        let realtime_id = "vj1:RealTime:20200101";
+        assert_eq!(vec!["2020-01-01"], dates(&collections, realtime_id));
+        let vj = collections.vehicle_journeys.get(realtime_id).unwrap();
+        assert_eq!(
+            vec![
+                (Time::new(10, 0, 0), Time::new(10, 0, 0)),
+                (Time::new(10, 12, 0), Time::new(10, 15, 0)),
+                (Time::new(10, 25, 0), Time::new(10, 25, 0)),
+            ],
+            vj.stop_times
+                .iter()
+                .map(|st| (st.arrival_time.unwrap(), st.departure_time.unwrap()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn update_delayed_trip_from_refreshed_feed() {
+        let mut collections = collections();
+        collections
+            .stop_time_ids
+            .insert(("vj1".to_string(), 1), "st_B".to_string());
+        let delayed_feed = |delay| {
+            feed(vec![trip_update(
+                "vj1",
+                trip_descriptor::ScheduleRelationship::Scheduled,
+                vec![StopTimeUpdate {
+                    stop_id: Some("B".to_string()),
+                    arrival: Some(StopTimeEvent {
+                        delay: Some(delay),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+            )])
+        };
+        apply_trip_updates(&mut collections, &delayed_feed(120)).unwrap();
+
+        let report = apply_trip_updates(&mut collections, &delayed_feed(60)).unwrap();
+
+        assert_eq!(1, report.delayed);
+        let realtime_id = "vj1:RealTime:20200101";
+        assert_eq!(vec!["2020-01-02"], dates(&collections, "vj1"));
+        assert_eq!(vec!["2020-01-01"], dates(&collections, realtime_id));
+        let vj = collections.vehicle_journeys.get(realtime_id).unwrap();
+        assert_eq!(Some(Time::new(10, 11, 0)), vj.stop_times[1].arrival_time);
+        assert_eq!(
+            "st_B:RealTime:20200101",
+            collections.stop_time_ids[&(realtime_id.to_string(), 1)]
+        );
+
+        let cancelled_feed = feed(vec![trip_update(
+            "vj1",
+            trip_descriptor::ScheduleRelationship::Canceled,
+            vec![],
+        )]);
+        let report = apply_trip_updates(&mut collections, &cancelled_feed).unwrap();
+
+        assert_eq!(1, report.cancelled);
+        assert!(dates(&collections, realtime_id).is_empty());
+    }
+
+    #[test]
+    fn report_unknown_trips() {
+        let mut collections = collections();
+        let feed = feed(vec![
+            trip_update(
+                "unknown",
+                trip_descriptor::ScheduleRelationship::Canceled,
+                vec![],
+            ),
+            trip_update(
+                "vj2",
+                trip_descriptor::ScheduleRelationship::Canceled,
+                vec![],
+            ),
+        ]);
+
+        let report = apply_trip_updates(&mut collections, &feed).unwrap();
+
+        assert_eq!(1, report.cancelled);
+        assert_eq!(
+            vec!["unknown"],
+            report
+                .unknown_trip_ids
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        );
+    }
+}
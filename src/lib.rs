@@ -18,7 +18,8 @@
 //! [NTFS](https://github.com/hove-io/ntfs-specification/blob/master/ntfs_fr.md).
 //!
 //! # Features
-//! `transit_model` has 2 possible features: `proj` and `xmllint`.
+//! `transit_model` has 6 possible features: `proj`, `xmllint`, `gtfs`,
+//! `gtfs-rt`, `parser` and `rusqlite`.
 //!
 //! ## `proj`
 //! `proj` feature is used for geolocation conversion (see
@@ -37,10 +38,18 @@
 //! This is an experimental feature that exposes some gtfs functions for use
 //! in external projects
 //!
+//! ## `gtfs-rt`
+//! Apply the trip updates of a GTFS-Realtime feed to the static collections
+//! (see the `gtfs_rt` module)
+//!
 //! ## `parser`
 //! Some utilities to turn csv files into vector of objects or CollectionWithId (See
 //! https://github.com/hove-io/typed_index_collection/)
 //!
+//! ## `rusqlite`
+//! Export the active dates of the calendars to a SQLite database (see
+//! `calendars::export_calendars_sqlite`)
+//!
 //! [`CONTRIBUTING.md`]: https://github.com/hove-io/transit_model/blob/master/CONTRIBUTING.md
 
 #![deny(missing_docs)]
@@ -60,6 +69,8 @@ pub(crate) mod file_handler;
 #[cfg(feature = "parser")]
 pub mod file_handler;
 pub mod gtfs;
+#[cfg(feature = "gtfs-rt")]
+pub mod gtfs_rt;
 pub mod model;
 pub mod model_builder;
 #[cfg(feature = "proj")]
@@ -14,9 +14,13 @@
 
 //! Definition of the navitia transit model.
 
-use crate::{enhancers, objects::*, Error, Result};
+use crate::{
+    calendars::CalendarParseStats, enhancers, objects::*, serde_utils::ser_option_naive_date,
+    AddPrefix, Error, PrefixConfiguration, Result,
+};
 use anyhow::{anyhow, bail};
-use chrono::NaiveDate;
+use chrono::{DateTime, Datelike, NaiveDate, Weekday};
+use chrono_tz::Tz;
 use derivative::Derivative;
 use geo::algorithm::centroid::Centroid;
 use geo::MultiPoint;
@@ -27,6 +31,7 @@ use std::{
     cmp::{self, Ordering, Reverse},
     collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet},
     convert::TryFrom,
+    fmt,
     hash::{Hash, Hasher},
     ops,
 };
@@ -74,6 +79,9 @@ pub const TRAIN_PHYSICAL_MODE: &str = "Train";
 /// Physical mode for Tramway
 pub const TRAMWAY_PHYSICAL_MODE: &str = "Tramway";
 
+#[cfg(feature = "proj")]
+const WGS84_EPSG: u32 = 4326;
+
 /// The set of collections representing the model.
 #[derive(Derivative, Serialize, Deserialize, Debug)]
 #[derivative(Default)]
@@ -92,6 +100,10 @@ pub struct Collections {
     pub stop_points: CollectionWithId<StopPoint>,
     pub stop_locations: CollectionWithId<StopLocation>,
     pub feed_infos: BTreeMap<String, String>,
+    /// EPSG code of the coordinates of the stops when they have been
+    /// reprojected with [`Collections::reproject_stops`], `None` for WGS84
+    /// longitudes and latitudes
+    pub stops_epsg: Option<u32>,
     pub calendars: CollectionWithId<Calendar>,
     pub companies: CollectionWithId<Company>,
     pub comments: CollectionWithId<Comment>,
@@ -120,6 +132,7 @@ pub struct Collections {
     pub ticket_use_restrictions: Collection<TicketUseRestriction>,
     pub pathways: CollectionWithId<Pathway>,
     pub levels: CollectionWithId<Level>,
+    pub attributions: CollectionWithId<Attribution>,
     pub grid_calendars: CollectionWithId<GridCalendar>,
     pub grid_exception_dates: Collection<GridExceptionDate>,
     pub grid_periods: Collection<GridPeriod>,
@@ -127,9 +140,755 @@ pub struct Collections {
     pub addresses: CollectionWithId<Address>,
     pub administrative_regions: CollectionWithId<AdministrativeRegion>,
     pub occupancies: Collection<Occupancy>,
+    pub timeframes: Collection<Timeframe>,
+    pub translations: Collection<Translation>,
+}
+
+/// Index of the services active on each date, built by
+/// [`Collections::build_service_date_index`].
+///
+/// The index holds one service id per active date of each calendar, which
+/// may be large for feeds with a long validity period: build it only when
+/// many date lookups are expected.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ServiceDateIndex(BTreeMap<Date, Vec<String>>);
+
+impl ServiceDateIndex {
+    /// Returns the ids of the services active on `date`, sorted
+    pub fn services_on(&self, date: Date) -> &[String] {
+        self.0.get(&date).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Iterates over the dates between `start_date` and `end_date` (included)
+    /// with their active services
+    pub fn services_between(
+        &self,
+        start_date: Date,
+        end_date: Date,
+    ) -> impl Iterator<Item = (&Date, &[String])> {
+        self.0
+            .range(start_date..=end_date)
+            .map(|(date, services)| (date, services.as_slice()))
+    }
+}
+
+/// Operating weekdays of a service, sorted from Monday, as found by
+/// [`crate::vptranslator::translate`]. Used as the key of
+/// [`Collections::group_calendars_by_profile`].
+///
+/// `chrono::Weekday` is not ordered, the profiles are ordered on the days
+/// numbered from Monday.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct WeekdayProfile(pub Vec<Weekday>);
+
+impl Ord for WeekdayProfile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let days = |profile: &Self| -> Vec<u32> {
+            profile
+                .0
+                .iter()
+                .map(Weekday::num_days_from_monday)
+                .collect()
+        };
+        days(self).cmp(&days(other))
+    }
+}
+
+impl PartialOrd for WeekdayProfile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Index of the stop points on their coordinates, built by
+/// [`Collections::build_stop_index`], to find the stop points nearest to
+/// many locations.
+///
+/// The stop points are sorted by latitude: a query only computes the
+/// distances to the stop points whose latitude is close enough to be among
+/// the nearest ones. The coordinates must be WGS84 longitudes and latitudes.
+#[derive(Debug, Clone)]
+pub struct StopSpatialIndex<'a> {
+    stop_points: Vec<&'a StopPoint>,
+}
+
+impl<'a> StopSpatialIndex<'a> {
+    /// Returns the `k` stop points nearest to the location, with their
+    /// orthodromic distance in meters, nearest first
+    pub fn nearest_stops(&self, lon: f64, lat: f64, k: usize) -> Vec<(&'a StopPoint, f64)> {
+        let mut nearest: Vec<(&'a StopPoint, f64)> = Vec::with_capacity(k + 1);
+        if k == 0 {
+            return nearest;
+        }
+        let location = Coord { lon, lat };
+        // The distance along the meridian of the location is a lower bound of
+        // the distance to a stop point
+        let lower_bound = |stop_point: &StopPoint| {
+            Coord {
+                lon,
+                lat: stop_point.coord.lat,
+            }
+            .distance_to(&location)
+        };
+        let mut below = self
+            .stop_points
+            .partition_point(|stop_point| stop_point.coord.lat < lat);
+        let mut above = below;
+        loop {
+            let below_bound = below
+                .checked_sub(1)
+                .map(|idx| lower_bound(self.stop_points[idx]));
+            let above_bound = self
+                .stop_points
+                .get(above)
+                .map(|stop_point| lower_bound(stop_point));
+            let (stop_point, bound) = match (below_bound, above_bound) {
+                (Some(below_bound), Some(above_bound)) if below_bound <= above_bound => {
+                    below -= 1;
+                    (self.stop_points[below], below_bound)
+                }
+                (Some(below_bound), None) => {
+                    below -= 1;
+                    (self.stop_points[below], below_bound)
+                }
+                (_, Some(above_bound)) => {
+                    above += 1;
+                    (self.stop_points[above - 1], above_bound)
+                }
+                (None, None) => break,
+            };
+            if nearest.len() == k && bound > nearest[k - 1].1 {
+                break;
+            }
+            let distance = stop_point.coord.distance_to(&location);
+            let position = nearest.partition_point(|(_, d)| *d <= distance);
+            nearest.insert(position, (stop_point, distance));
+            nearest.truncate(k);
+        }
+        nearest
+    }
+}
+
+/// Calendar and trip consistency issues of a [`Collections`], see
+/// [`Collections::audit_calendars`]. All the lists are sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CalendarAudit {
+    /// Services not used by any vehicle journey
+    pub unreferenced_services: Vec<String>,
+    /// Vehicle journeys whose service does not exist, as
+    /// `(vehicle_journey_id, service_id)`
+    pub trips_with_missing_service: Vec<(String, String)>,
+    /// Services without any active date
+    pub empty_calendars: Vec<String>,
+    /// Services whose declared window (see [`Calendar::declared_period`])
+    /// ends before it starts. The calendar.txt readers reject, swap or skip
+    /// such rows, so only calendars built or edited afterwards can have one.
+    pub inverted_windows: Vec<String>,
+    /// Active dates outside the validity period of the datasets, by service
+    pub out_of_feed_window_dates: Vec<(String, Vec<Date>)>,
+}
+
+impl CalendarAudit {
+    /// Returns true if no issue has been found
+    pub fn is_empty(&self) -> bool {
+        self.unreferenced_services.is_empty()
+            && self.trips_with_missing_service.is_empty()
+            && self.empty_calendars.is_empty()
+            && self.inverted_windows.is_empty()
+            && self.out_of_feed_window_dates.is_empty()
+    }
+}
+
+/// A reference to an object missing from the [`Collections`], see
+/// [`Collections::check_referential_integrity`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{collection} {object_id} references the unknown {target_collection} {missing_id}")]
+pub struct IntegrityError {
+    /// Collection of the object holding the reference
+    pub collection: &'static str,
+    /// Identifier of the object holding the reference
+    pub object_id: String,
+    /// Collection in which the referenced object is missing
+    pub target_collection: &'static str,
+    /// Identifier of the missing object
+    pub missing_id: String,
+}
+
+/// Identifiers of the objects of a collection which differ between two
+/// [`Collections`], see [`Collections::diff`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CollectionDiff {
+    /// Objects only present in the other [`Collections`]
+    pub added: BTreeSet<String>,
+    /// Objects only present in the original [`Collections`]
+    pub removed: BTreeSet<String>,
+    /// Objects present in both [`Collections`] with different contents
+    pub changed: BTreeSet<String>,
+}
+
+impl CollectionDiff {
+    /// Returns true if no difference has been found
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Active dates which differ for a service present in both [`Collections`],
+/// see [`Collections::diff`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CalendarDiff {
+    /// Dates only active in the other [`Collections`]
+    pub added_dates: BTreeSet<Date>,
+    /// Dates only active in the original [`Collections`]
+    pub removed_dates: BTreeSet<Date>,
+}
+
+/// Differences between two [`Collections`], see [`Collections::diff`]
+///
+/// Only the collections and the services with differences are kept, in
+/// alphabetical order, so the result (and its [`Display`](std::fmt::Display)
+/// summary) is deterministic.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CollectionsDiff {
+    /// Differences by collection, like `stop_points`
+    pub collections: BTreeMap<&'static str, CollectionDiff>,
+    /// Differences of active dates by service
+    pub calendars: BTreeMap<String, CalendarDiff>,
+}
+
+impl CollectionsDiff {
+    /// Returns true if no difference has been found
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty() && self.calendars.is_empty()
+    }
+}
+
+impl fmt::Display for CollectionsDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, diff) in &self.collections {
+            writeln!(
+                f,
+                "{}: {} added, {} removed, {} changed",
+                name,
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len()
+            )?;
+            for (sign, ids) in [
+                ("+", &diff.added),
+                ("-", &diff.removed),
+                ("~", &diff.changed),
+            ] {
+                for id in ids {
+                    writeln!(f, "  {} {}", sign, id)?;
+                }
+            }
+        }
+        for (service_id, diff) in &self.calendars {
+            write!(f, "calendar {}:", service_id)?;
+            for date in &diff.added_dates {
+                write!(f, " +{}", date.format("%Y%m%d"))?;
+            }
+            for date in &diff.removed_dates {
+                write!(f, " -{}", date.format("%Y%m%d"))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stop of a [`TripPattern`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct PatternStop {
+    /// Identifier of the stop point
+    pub stop_point_id: String,
+    /// Pickup type of the stop times
+    pub pickup_type: u8,
+    /// Drop off type of the stop times
+    pub drop_off_type: u8,
+}
+
+/// Vehicle journeys serving the same stop points in the same order with the
+/// same pickup and drop off types, whatever their times, see
+/// [`Collections::analyze_trip_patterns`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TripPattern {
+    /// Stops of the vehicle journeys, in order
+    pub stops: Vec<PatternStop>,
+    /// Identifiers of the vehicle journeys, sorted
+    pub vehicle_journey_ids: Vec<String>,
+}
+
+/// Counts of the objects of a [`Collections`], see [`Collections::stats`]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelStats {
+    /// Number of networks
+    pub networks: usize,
+    /// Number of lines
+    pub lines: usize,
+    /// Number of routes
+    pub routes: usize,
+    /// Number of vehicle journeys
+    pub vehicle_journeys: usize,
+    /// Number of stop points
+    pub stop_points: usize,
+    /// Number of stop areas
+    pub stop_areas: usize,
+    /// Number of calendars
+    pub calendars: usize,
+    /// Active dates of all the calendars (a date active for 2 calendars is
+    /// counted twice)
+    pub service_days: usize,
+    /// First active date of the calendars
+    #[serde(serialize_with = "ser_option_naive_date")]
+    pub start_date: Option<Date>,
+    /// Last active date of the calendars
+    #[serde(serialize_with = "ser_option_naive_date")]
+    pub end_date: Option<Date>,
+}
+
+impl fmt::Display for ModelStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, count) in [
+            ("networks", self.networks),
+            ("lines", self.lines),
+            ("routes", self.routes),
+            ("vehicle journeys", self.vehicle_journeys),
+            ("stop points", self.stop_points),
+            ("stop areas", self.stop_areas),
+            ("calendars", self.calendars),
+            ("service days", self.service_days),
+        ] {
+            writeln!(f, "{:<18}{:>10}", name, count)?;
+        }
+        if let (Some(start_date), Some(end_date)) = (self.start_date, self.end_date) {
+            writeln!(
+                f,
+                "{:<18}{} - {}",
+                "validity period",
+                start_date.format("%Y%m%d"),
+                end_date.format("%Y%m%d")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn diff_collection<T, F>(
+    collection: &CollectionWithId<T>,
+    other: &CollectionWithId<T>,
+    is_changed: F,
+) -> CollectionDiff
+where
+    T: Id<T>,
+    F: Fn(&T, &T) -> bool,
+{
+    let mut diff = CollectionDiff::default();
+    for object in collection.values() {
+        match other.get(object.id()) {
+            Some(other_object) if is_changed(object, other_object) => {
+                diff.changed.insert(object.id().to_string());
+            }
+            Some(_) => {}
+            None => {
+                diff.removed.insert(object.id().to_string());
+            }
+        }
+    }
+    diff.added = other
+        .values()
+        .filter(|object| !collection.contains_id(object.id()))
+        .map(|object| object.id().to_string())
+        .collect();
+    diff
+}
+
+// Contents are compared on their serialized form, which is what the writers
+// output
+fn is_serialization_changed<T: Serialize>(object: &T, other: &T) -> bool {
+    serde_json::to_value(object).ok() != serde_json::to_value(other).ok()
 }
 
 impl Collections {
+    /// Iterate over the `(service_id, date)` pairs of all the services, without
+    /// building any intermediate structure
+    pub fn iter_service_dates(&self) -> impl Iterator<Item = (&str, &Date)> {
+        self.calendars.values().flat_map(|calendar| {
+            calendar
+                .dates
+                .iter()
+                .map(move |date| (calendar.id.as_str(), date))
+        })
+    }
+
+    /// Resolve the departure time (the arrival time if there is none) of a
+    /// stop time into an absolute date time, for the service `service_id`
+    /// running on `date` in the agency `timezone`.
+    /// See [`Time::on_service_day`] for the handling of the DST changes.
+    pub fn resolve_stop_time(
+        &self,
+        service_id: &str,
+        date: Date,
+        stop_time: &StopTime,
+        timezone: Tz,
+    ) -> Result<DateTime<Tz>> {
+        let calendar = self
+            .calendars
+            .get(service_id)
+            .ok_or_else(|| anyhow!("service {} not found", service_id))?;
+        if !calendar.dates.contains(&date) {
+            bail!("service {} is not active on {}", service_id, date);
+        }
+        let time = stop_time
+            .departure_time
+            .or(stop_time.arrival_time)
+            .ok_or_else(|| anyhow!("stop time {} has no time", stop_time.sequence))?;
+        time.on_service_day(date, timezone).ok_or_else(|| {
+            anyhow!(
+                "noon of {} does not exist in the timezone {}",
+                date,
+                timezone.name()
+            )
+        })
+    }
+
+    /// Returns the active dates of the service, `None` if the service does
+    /// not exist
+    pub fn active_dates(&self, service_id: &str) -> Option<&BTreeSet<Date>> {
+        self.calendars
+            .get(service_id)
+            .map(|calendar| &calendar.dates)
+    }
+
+    /// Returns true if the service exists and is active on `date`
+    pub fn is_active_on(&self, service_id: &str, date: Date) -> bool {
+        self.active_dates(service_id)
+            .map_or(false, |dates| dates.contains(&date))
+    }
+
+    /// Checks the consistency between the calendars and the vehicle journeys,
+    /// going once through each of them. Dates out of the feed window are only
+    /// checked when the validity period of the datasets is known.
+    pub fn audit_calendars(&self) -> CalendarAudit {
+        let mut audit = CalendarAudit::default();
+        let mut referenced_services: HashSet<&str> = HashSet::new();
+        for vj in self.vehicle_journeys.values() {
+            if self.calendars.contains_id(&vj.service_id) {
+                referenced_services.insert(&vj.service_id);
+            } else {
+                audit
+                    .trips_with_missing_service
+                    .push((vj.id.clone(), vj.service_id.clone()));
+            }
+        }
+        let feed_window = self.calculate_validity_period().ok();
+        for calendar in self.calendars.values() {
+            if !referenced_services.contains(calendar.id.as_str()) {
+                audit.unreferenced_services.push(calendar.id.clone());
+            }
+            if calendar.dates.is_empty() {
+                audit.empty_calendars.push(calendar.id.clone());
+            }
+            if let Some(declared_period) = calendar.declared_period() {
+                if declared_period.start_date > declared_period.end_date {
+                    audit.inverted_windows.push(calendar.id.clone());
+                }
+            }
+            if let Some((start_date, end_date)) = feed_window {
+                let out_of_window_dates: Vec<Date> = calendar
+                    .dates
+                    .iter()
+                    .filter(|date| **date < start_date || **date > end_date)
+                    .cloned()
+                    .collect();
+                if !out_of_window_dates.is_empty() {
+                    audit
+                        .out_of_feed_window_dates
+                        .push((calendar.id.clone(), out_of_window_dates));
+                }
+            }
+        }
+        audit.unreferenced_services.sort_unstable();
+        audit.trips_with_missing_service.sort_unstable();
+        audit.empty_calendars.sort_unstable();
+        audit.inverted_windows.sort_unstable();
+        audit.out_of_feed_window_dates.sort_unstable();
+        audit
+    }
+
+    /// Build an index of the services active on each date, see [`ServiceDateIndex`]
+    pub fn build_service_date_index(&self) -> ServiceDateIndex {
+        let mut index: BTreeMap<Date, Vec<String>> = BTreeMap::new();
+        for calendar in self.calendars.values() {
+            for date in &calendar.dates {
+                index.entry(*date).or_default().push(calendar.id.clone());
+            }
+        }
+        for services in index.values_mut() {
+            services.sort_unstable();
+        }
+        ServiceDateIndex(index)
+    }
+
+    /// Propagate the wheelchair accessibility (the `wheelchair_boarding` of
+    /// the equipments) between the stop areas and their stop points:
+    /// - a stop area is accessible if all its stop points are, not accessible
+    ///   if none of them is, and unknown when some of them are and others are
+    ///   not. It is left unchanged when the accessibility of some of its stop
+    ///   points is unknown.
+    /// - a stop point with an unknown accessibility then inherits the one of
+    ///   its stop area.
+    ///
+    /// The accessibility of a stop area is only computed from the original
+    /// accessibility of its stop points, never from an inherited one.
+    ///
+    /// Equipments may be shared, so they are never modified: the changed
+    /// objects use an equipment only differing by its accessibility, created
+    /// if needed.
+    pub fn propagate_accessibility(&mut self) {
+        fn wheelchair_boarding(
+            equipments: &CollectionWithId<Equipment>,
+            equipment_id: &Option<String>,
+        ) -> Availability {
+            equipment_id
+                .as_ref()
+                .and_then(|equipment_id| equipments.get(equipment_id))
+                .map_or(Availability::InformationNotAvailable, |equipment| {
+                    equipment.wheelchair_boarding
+                })
+        }
+        fn equipment_with_wheelchair_boarding(
+            equipments: &mut CollectionWithId<Equipment>,
+            equipment_id: &Option<String>,
+            wheelchair_boarding: Availability,
+        ) -> Option<String> {
+            let mut equipment = equipment_id
+                .as_ref()
+                .and_then(|equipment_id| equipments.get(equipment_id))
+                .cloned()
+                .unwrap_or_default();
+            equipment.wheelchair_boarding = wheelchair_boarding;
+            if equipment.is_similar(&Equipment::default()) {
+                return None;
+            }
+            if let Some(similar_equipment) = equipments.values().find(|e| e.is_similar(&equipment))
+            {
+                return Some(similar_equipment.id.clone());
+            }
+            let mut nb_equipments = equipments.len();
+            equipment.id = loop {
+                let id = format!("equipment:{}", nb_equipments);
+                if !equipments.contains_id(&id) {
+                    break id;
+                }
+                nb_equipments += 1;
+            };
+            let id = equipment.id.clone();
+            equipments.push(equipment).unwrap();
+            Some(id)
+        }
+
+        let mut stop_points_accessibilities: BTreeMap<String, Vec<Availability>> = BTreeMap::new();
+        for stop_point in self.stop_points.values() {
+            stop_points_accessibilities
+                .entry(stop_point.stop_area_id.clone())
+                .or_default()
+                .push(wheelchair_boarding(
+                    &self.equipments,
+                    &stop_point.equipment_id,
+                ));
+        }
+        for (stop_area_id, accessibilities) in stop_points_accessibilities {
+            if accessibilities.contains(&Availability::InformationNotAvailable) {
+                continue;
+            }
+            let is_accessible = accessibilities.contains(&Availability::Available);
+            let is_not_accessible = accessibilities.contains(&Availability::NotAvailable);
+            let accessibility = match (is_accessible, is_not_accessible) {
+                (true, false) => Availability::Available,
+                (false, true) => Availability::NotAvailable,
+                _ => Availability::InformationNotAvailable,
+            };
+            if let Some(mut stop_area) = self.stop_areas.get_mut(&stop_area_id) {
+                if wheelchair_boarding(&self.equipments, &stop_area.equipment_id) != accessibility {
+                    stop_area.equipment_id = equipment_with_wheelchair_boarding(
+                        &mut self.equipments,
+                        &stop_area.equipment_id,
+                        accessibility,
+                    );
+                }
+            }
+        }
+        let stop_area_accessibilities: HashMap<String, Availability> = self
+            .stop_areas
+            .values()
+            .map(|stop_area| {
+                (
+                    stop_area.id.clone(),
+                    wheelchair_boarding(&self.equipments, &stop_area.equipment_id),
+                )
+            })
+            .collect();
+        for idx in self.stop_points.indexes() {
+            let mut stop_point = self.stop_points.index_mut(idx);
+            let stop_area_accessibility = stop_area_accessibilities
+                .get(&stop_point.stop_area_id)
+                .copied()
+                .unwrap_or(Availability::InformationNotAvailable);
+            if wheelchair_boarding(&self.equipments, &stop_point.equipment_id)
+                == Availability::InformationNotAvailable
+                && stop_area_accessibility != Availability::InformationNotAvailable
+            {
+                stop_point.equipment_id = equipment_with_wheelchair_boarding(
+                    &mut self.equipments,
+                    &stop_point.equipment_id,
+                    stop_area_accessibility,
+                );
+            }
+        }
+    }
+
+    /// Group the vehicle journeys by [`TripPattern`]. The patterns are sorted
+    /// by the identifier of their first vehicle journey.
+    pub fn analyze_trip_patterns(&self) -> Vec<TripPattern> {
+        let mut patterns: HashMap<Vec<PatternStop>, Vec<String>> = HashMap::new();
+        for vehicle_journey in self.vehicle_journeys.values() {
+            let stops = vehicle_journey
+                .stop_times
+                .iter()
+                .map(|stop_time| PatternStop {
+                    stop_point_id: self.stop_points[stop_time.stop_point_idx].id.clone(),
+                    pickup_type: stop_time.pickup_type,
+                    drop_off_type: stop_time.drop_off_type,
+                })
+                .collect();
+            patterns
+                .entry(stops)
+                .or_default()
+                .push(vehicle_journey.id.clone());
+        }
+        let mut patterns: Vec<TripPattern> = patterns
+            .into_iter()
+            .map(|(stops, mut vehicle_journey_ids)| {
+                vehicle_journey_ids.sort_unstable();
+                TripPattern {
+                    stops,
+                    vehicle_journey_ids,
+                }
+            })
+            .collect();
+        patterns.sort_unstable_by(|p1, p2| p1.vehicle_journey_ids.cmp(&p2.vehicle_journey_ids));
+        patterns
+    }
+
+    /// Group the ids of the calendars by the weekly profile found by
+    /// [`crate::vptranslator::translate`], so the profiles match the ones of
+    /// the writers. The calendars without weekly pattern (only made of
+    /// exceptions) are grouped under the empty profile. The ids of each
+    /// profile are sorted.
+    pub fn group_calendars_by_profile(&self) -> BTreeMap<WeekdayProfile, Vec<String>> {
+        let mut profiles: BTreeMap<WeekdayProfile, Vec<String>> = BTreeMap::new();
+        for calendar in self.calendars.values() {
+            let operating_days = crate::vptranslator::translate(&calendar.dates).operating_days;
+            profiles
+                .entry(WeekdayProfile(operating_days))
+                .or_default()
+                .push(calendar.id.clone());
+        }
+        for service_ids in profiles.values_mut() {
+            service_ids.sort_unstable();
+        }
+        profiles
+    }
+
+    /// Count the objects of the collections, see [`ModelStats`]
+    pub fn stats(&self) -> ModelStats {
+        let dates = || self.calendars.values().map(|calendar| &calendar.dates);
+        ModelStats {
+            networks: self.networks.len(),
+            lines: self.lines.len(),
+            routes: self.routes.len(),
+            vehicle_journeys: self.vehicle_journeys.len(),
+            stop_points: self.stop_points.len(),
+            stop_areas: self.stop_areas.len(),
+            calendars: self.calendars.len(),
+            service_days: dates().map(BTreeSet::len).sum(),
+            start_date: dates()
+                .filter_map(|dates| dates.iter().next())
+                .min()
+                .copied(),
+            end_date: dates()
+                .filter_map(|dates| dates.iter().next_back())
+                .max()
+                .copied(),
+        }
+    }
+
+    /// Build a spatial index of the stop points, see [`StopSpatialIndex`].
+    /// The stop points with invalid coordinates are not indexed.
+    pub fn build_stop_index(&self) -> StopSpatialIndex<'_> {
+        let mut stop_points: Vec<&StopPoint> = self
+            .stop_points
+            .values()
+            .filter(|stop_point| stop_point.coord.is_valid())
+            .collect();
+        stop_points.sort_by(|sp1, sp2| sp1.coord.lat.total_cmp(&sp2.coord.lat));
+        StopSpatialIndex { stop_points }
+    }
+
+    /// Count, for each `(year, month)`, the active dates of all the services
+    /// (a date active for 2 services is counted twice)
+    pub fn monthly_service_coverage(&self) -> BTreeMap<(i32, u32), usize> {
+        let mut coverage = BTreeMap::new();
+        for calendar in self.calendars.values() {
+            for date in &calendar.dates {
+                *coverage.entry((date.year(), date.month())).or_default() += 1;
+            }
+        }
+        coverage
+    }
+
+    /// Returns true if any service is active on a Saturday or a Sunday,
+    /// stopping at the first one found
+    pub fn has_weekend_service(&self) -> bool {
+        self.iter_service_dates()
+            .any(|(_, date)| matches!(date.weekday(), chrono::Weekday::Sat | chrono::Weekday::Sun))
+    }
+
+    /// Sum, for each date, the durations between the first departure and the
+    /// last arrival of the vehicle journeys running on this date. A journey
+    /// running past midnight is counted on the date of its service.
+    pub fn service_minutes_per_date(&self) -> BTreeMap<Date, chrono::Duration> {
+        let mut service_seconds: HashMap<&str, i64> = HashMap::new();
+        for vehicle_journey in self.vehicle_journeys.values() {
+            if let (Some(departure), Some(arrival)) = (
+                vehicle_journey.first_departure_time(),
+                vehicle_journey.last_arrival_time(),
+            ) {
+                let span =
+                    i64::from(arrival.total_seconds()) - i64::from(departure.total_seconds());
+                *service_seconds
+                    .entry(vehicle_journey.service_id.as_str())
+                    .or_default() += span.max(0);
+            }
+        }
+        let mut minutes_per_date: BTreeMap<Date, chrono::Duration> = BTreeMap::new();
+        for calendar in self.calendars.values() {
+            let seconds = match service_seconds.get(calendar.id.as_str()) {
+                Some(seconds) => *seconds,
+                None => continue,
+            };
+            for date in &calendar.dates {
+                let total = minutes_per_date
+                    .entry(*date)
+                    .or_insert_with(chrono::Duration::zero);
+                *total = *total + chrono::Duration::seconds(seconds);
+            }
+        }
+        minutes_per_date
+    }
+
     /// Remove associated schedules with route points
     pub fn remove_route_points(&mut self) {
         let is_route_point = |stop_time: &StopTime| -> bool {
@@ -157,15 +916,18 @@ impl Collections {
     }
 
     /// Restrict the validity period of the current `Collections` with the start_date and end_date
+    ///
+    /// The dates are service days: a vehicle journey is kept whole, including
+    /// its stop times after midnight (e.g. `25:10:00`), as long as its
+    /// calendar is active on a kept date, and removed when only its overnight
+    /// part runs in the period.
+    ///
+    /// Emptied calendars are kept, until [`Collections::remove_empty_calendars`]
+    /// or [`Collections::sanitize`].
     pub fn restrict_period(&mut self, start_date: NaiveDate, end_date: NaiveDate) -> Result<()> {
         let mut calendars = self.calendars.take();
         for calendar in calendars.iter_mut() {
-            calendar.dates = calendar
-                .dates
-                .iter()
-                .cloned()
-                .filter(|date| *date >= start_date && *date <= end_date)
-                .collect();
+            calendar.retain_dates(|date| *date >= start_date && *date <= end_date);
         }
         let mut data_sets = self.datasets.take();
         for data_set in data_sets.iter_mut() {
@@ -177,57 +939,458 @@ impl Collections {
         Ok(())
     }
 
-    /// Filters vehicle_journeys in collection based on their schedule types
-    /// Multiple choice possible (through Vec<>). See VehicleJourneyScheduleType list.
-    pub fn filter_by_vj_schedule_types(
-        &mut self,
-        types: Vec<VehicleJourneyScheduleType>,
-    ) -> Result<()> {
-        self.vehicle_journeys
-            .retain(|vj| types.contains(&vj.get_schedule_type()));
-        Ok(())
-    }
-
-    /// Keep the collections consistent for the new model by purging unreferenced data by
-    /// calendars
-    pub fn sanitize(&mut self) -> Result<()> {
-        fn log_object_removed(object_type: &str, id: &str) {
-            debug!("{} with ID {} has been removed", object_type, id);
+    /// Remove the calendars without any active date and the vehicle journeys
+    /// using them (with their frequencies and stop time properties).
+    /// Returns the number of removed calendars.
+    pub fn remove_empty_calendars(&mut self) -> usize {
+        let empty_calendars: HashSet<String> = self
+            .calendars
+            .values()
+            .filter(|calendar| calendar.dates.is_empty())
+            .map(|calendar| calendar.id.clone())
+            .collect();
+        if empty_calendars.is_empty() {
+            return 0;
         }
-        fn log_predicate<'a, T, F>(object_type: &'a str, mut f: F) -> impl 'a + FnMut(&T) -> bool
-        where
-            T: Id<T>,
-            F: 'a + FnMut(&T) -> bool,
-        {
-            move |item| {
-                if f(item) {
-                    true
-                } else {
-                    log_object_removed(object_type, item.id());
-                    false
-                }
+        self.calendars.retain(|calendar| {
+            if calendar.dates.is_empty() {
+                debug!(
+                    "Calendar with ID {} has been removed, it has no date",
+                    calendar.id
+                );
+                false
+            } else {
+                true
             }
-        }
-
-        fn dedup_collection<T: Clone + Eq + Hash>(source: &mut Collection<T>) -> Collection<T> {
-            let calculate_hash = |t: &T| -> u64 {
-                let mut s = DefaultHasher::new();
-                t.hash(&mut s);
-                s.finish()
-            };
-            let mut set: BTreeMap<u64, T> = BTreeMap::new();
-            let items = source.take();
-            for item in items {
-                set.insert(calculate_hash(&item), item);
+        });
+        let mut removed_vjs: HashSet<String> = HashSet::new();
+        self.vehicle_journeys.retain(|vj| {
+            if empty_calendars.contains(&vj.service_id) {
+                debug!(
+                    "Vehicle Journey with ID {} has been removed, its calendar {} has no date",
+                    vj.id, vj.service_id
+                );
+                removed_vjs.insert(vj.id.clone());
+                false
+            } else {
+                true
+            }
+        });
+        if !removed_vjs.is_empty() {
+            self.frequencies
+                .retain(|frequency| !removed_vjs.contains(&frequency.vehicle_journey_id));
+            for stop_time_table in [
+                &mut self.stop_time_headsigns,
+                &mut self.stop_time_ids,
+                &mut self.stop_time_comments,
+            ] {
+                stop_time_table.retain(|(vj_id, _), _| !removed_vjs.contains(vj_id));
             }
-            let collection: Vec<T> = set.values().cloned().collect();
-            Collection::new(collection)
         }
+        empty_calendars.len()
+    }
 
-        self.calendars
-            .retain(log_predicate("Calendar", |cal: &Calendar| {
-                !cal.dates.is_empty()
-            }));
+    /// Keep only the dates between `start_date` and `end_date` (both included)
+    /// in all the calendars, then purge the emptied calendars and the objects
+    /// which are no longer referenced (vehicle journeys, routes, lines, stop
+    /// points, etc.)
+    pub fn restrict_calendars(&mut self, start_date: Date, end_date: Date) -> Result<()> {
+        if start_date > end_date {
+            bail!(
+                "cannot restrict the calendars from {} to {}: the start date is after the end date",
+                start_date,
+                end_date
+            );
+        }
+        self.restrict_period(start_date, end_date)?;
+        self.sanitize()
+    }
+
+    /// Apply the exceptions of a calendar_dates.txt content, for example a
+    /// daily delta file, to the calendars already loaded, the same way they
+    /// are applied when reading a feed. The services only defined in the
+    /// content are added.
+    ///
+    /// Adding an active date or removing an inactive one changes nothing, so
+    /// the same content can be applied again: see
+    /// [`CalendarParseStats::dates_added`],
+    /// [`CalendarParseStats::dates_removed`] and
+    /// [`CalendarParseStats::services_from_dates_only`] for what actually
+    /// changed. Emptied calendars are kept, until [`Collections::sanitize`].
+    pub fn apply_calendar_dates<R>(&mut self, reader: R) -> Result<CalendarParseStats>
+    where
+        R: std::io::Read,
+    {
+        crate::calendars::apply_calendar_dates_from_reader(reader, &mut self.calendars)
+    }
+
+    /// Remove the stop times of the stop points outside the bounding box
+    /// (boundaries included) and the vehicle journeys left with less than 2
+    /// stop times, then purge the objects which are no longer referenced
+    /// (stop points, stop areas, calendars, routes, lines, etc.) as in
+    /// [`Collections::restrict_calendars`]. A stop area outside the box is only
+    /// kept if one of its stop points is inside.
+    pub fn filter_by_bounding_box(
+        &mut self,
+        min_lon: f64,
+        min_lat: f64,
+        max_lon: f64,
+        max_lat: f64,
+    ) -> Result<()> {
+        let inside_stop_points: IdxSet<StopPoint> = self
+            .stop_points
+            .iter()
+            .filter(|(_, sp)| {
+                min_lon <= sp.coord.lon
+                    && sp.coord.lon <= max_lon
+                    && min_lat <= sp.coord.lat
+                    && sp.coord.lat <= max_lat
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        let mut removed_stop_times: HashSet<(String, u32)> = HashSet::new();
+        let mut vehicle_journeys = self.vehicle_journeys.take();
+        for vj in &mut vehicle_journeys {
+            let vj_id = &vj.id;
+            vj.stop_times.retain(|st| {
+                let is_inside = inside_stop_points.contains(&st.stop_point_idx);
+                if !is_inside {
+                    removed_stop_times.insert((vj_id.clone(), st.sequence));
+                }
+                is_inside
+            });
+        }
+        vehicle_journeys.retain(|vj| {
+            if vj.stop_times.len() < 2 {
+                debug!(
+                    "Vehicle Journey with ID {} has been removed, less than 2 stop times are in the bounding box",
+                    vj.id
+                );
+                false
+            } else {
+                true
+            }
+        });
+        self.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+        for stop_time_table in [
+            &mut self.stop_time_headsigns,
+            &mut self.stop_time_ids,
+            &mut self.stop_time_comments,
+        ] {
+            stop_time_table.retain(|key, _| !removed_stop_times.contains(key));
+        }
+        self.sanitize()
+    }
+
+    /// Keep only the vehicle journeys of the lines of the networks
+    /// `network_ids`, see [`Collections::retain_lines`]. The identifiers of
+    /// unknown networks are ignored with a warning, and nothing is removed
+    /// when none of the networks is known.
+    pub fn retain_networks(&mut self, network_ids: &HashSet<String>) -> Result<()> {
+        let network_ids: HashSet<&String> = network_ids
+            .iter()
+            .filter(|network_id| {
+                let is_known = self.networks.contains_id(network_id);
+                if !is_known {
+                    warn!("Network {} to keep not found, ignoring it", network_id);
+                }
+                is_known
+            })
+            .collect();
+        if network_ids.is_empty() {
+            warn!("None of the networks to keep has been found, keeping all the networks");
+            return Ok(());
+        }
+        let line_ids: HashSet<String> = self
+            .lines
+            .values()
+            .filter(|line| network_ids.contains(&line.network_id))
+            .map(|line| line.id.clone())
+            .collect();
+        self.retain_lines(&line_ids)
+    }
+
+    /// Keep only the vehicle journeys of the lines `line_ids`, then purge the
+    /// objects which are no longer referenced (routes, lines, networks, stop
+    /// points, calendars, etc.) as in [`Collections::filter_by_bounding_box`].
+    /// The identifiers of unknown lines are ignored with a warning, and
+    /// nothing is removed when none of the lines is known.
+    pub fn retain_lines(&mut self, line_ids: &HashSet<String>) -> Result<()> {
+        let line_ids: HashSet<&String> = line_ids
+            .iter()
+            .filter(|line_id| {
+                let is_known = self.lines.contains_id(line_id);
+                if !is_known {
+                    warn!("Line {} to keep not found, ignoring it", line_id);
+                }
+                is_known
+            })
+            .collect();
+        if line_ids.is_empty() {
+            warn!("None of the lines to keep has been found, keeping all the lines");
+            return Ok(());
+        }
+        let route_ids: HashSet<&str> = self
+            .routes
+            .values()
+            .filter(|route| line_ids.contains(&route.line_id))
+            .map(|route| route.id.as_str())
+            .collect();
+        self.vehicle_journeys.retain(|vj| {
+            if route_ids.contains(vj.route_id.as_str()) {
+                true
+            } else {
+                debug!(
+                    "Vehicle Journey with ID {} has been removed, its route {} is not kept",
+                    vj.id, vj.route_id
+                );
+                false
+            }
+        });
+        self.sanitize()
+    }
+
+    /// Remove the dates before `date` from all the calendars, then purge the
+    /// emptied calendars and the objects which are no longer referenced
+    pub fn drop_dates_before(&mut self, date: Date) -> Result<()> {
+        let mut calendars = self.calendars.take();
+        for calendar in calendars.iter_mut() {
+            calendar.retain_dates(|calendar_date| *calendar_date >= date);
+        }
+        let mut data_sets = self.datasets.take();
+        for data_set in data_sets.iter_mut() {
+            data_set.start_date = cmp::max(date, data_set.start_date);
+        }
+        self.datasets = CollectionWithId::new(data_sets)?;
+        self.calendars = CollectionWithId::new(calendars)?;
+        self.sanitize()
+    }
+
+    /// Split the calendars running on both sides of `cutoff` into a
+    /// `<id>:before` and a `<id>:after` calendar, dates equal to the cutoff
+    /// going to the latter. Calendars entirely on one side are left unchanged.
+    /// Each vehicle journey using a split calendar keeps the "before" one and
+    /// is duplicated into a `<vj_id>:after` vehicle journey using the "after"
+    /// one, with its stop time properties, attributions and translations (the
+    /// ids of the duplicated stop times and attributions being suffixed by
+    /// `:after` too). The time frames of a split calendar apply to both parts.
+    /// The declared period of each part is trimmed to its side of the cutoff.
+    /// Returns, for each split calendar, the ids of both new calendars. The
+    /// collections are left unchanged when one of the new ids is already used.
+    pub fn split_calendars_at(
+        &mut self,
+        cutoff: Date,
+    ) -> Result<BTreeMap<String, (String, String)>> {
+        // Every collection is built before any is replaced, so that the
+        // collections are left untouched when a new id is already used
+        let mut mapping = BTreeMap::new();
+        let mut calendars = vec![];
+        for calendar in self.calendars.values() {
+            let mut before = calendar.clone();
+            before.retain_dates(|date| *date < cutoff);
+            let mut after = calendar.clone();
+            after.retain_dates(|date| *date >= cutoff);
+            if before.dates.is_empty() || after.dates.is_empty() {
+                calendars.push(calendar.clone());
+                continue;
+            }
+            let before_id = format!("{}:before", calendar.id);
+            let after_id = format!("{}:after", calendar.id);
+            before.id.clone_from(&before_id);
+            after.id.clone_from(&after_id);
+            if let Some(declared_period) = before
+                .metadata
+                .as_mut()
+                .and_then(|metadata| metadata.declared_period.as_mut())
+            {
+                if let Some(last_date) = cutoff.pred_opt() {
+                    declared_period.end_date = cmp::min(declared_period.end_date, last_date);
+                }
+            }
+            if let Some(declared_period) = after
+                .metadata
+                .as_mut()
+                .and_then(|metadata| metadata.declared_period.as_mut())
+            {
+                declared_period.start_date = cmp::max(declared_period.start_date, cutoff);
+            }
+            calendars.push(before);
+            calendars.push(after);
+            mapping.insert(calendar.id.clone(), (before_id, after_id));
+        }
+        if mapping.is_empty() {
+            return Ok(mapping);
+        }
+        let calendars = CollectionWithId::new(calendars)?;
+
+        let mut vehicle_journeys = vec![];
+        let mut frequencies = self.frequencies.values().cloned().collect::<Vec<_>>();
+        let mut attributions = self.attributions.values().cloned().collect::<Vec<_>>();
+        let mut translations = self.translations.values().cloned().collect::<Vec<_>>();
+        let mut after_stop_time_headsigns = vec![];
+        let mut after_stop_time_comments = vec![];
+        let mut after_stop_time_ids = vec![];
+        for vehicle_journey in self.vehicle_journeys.values() {
+            let (before_id, after_id) = match mapping.get(&vehicle_journey.service_id) {
+                Some(ids) => ids,
+                None => {
+                    vehicle_journeys.push(vehicle_journey.clone());
+                    continue;
+                }
+            };
+            let after_vj_id = format!("{}:after", vehicle_journey.id);
+            let mut after_ids_of_stop_times = HashMap::new();
+            for stop_time in &vehicle_journey.stop_times {
+                let key = (vehicle_journey.id.clone(), stop_time.sequence);
+                let after_key = (after_vj_id.clone(), stop_time.sequence);
+                if let Some(headsign) = self.stop_time_headsigns.get(&key) {
+                    after_stop_time_headsigns.push((after_key.clone(), headsign.clone()));
+                }
+                if let Some(comment_id) = self.stop_time_comments.get(&key) {
+                    after_stop_time_comments.push((after_key.clone(), comment_id.clone()));
+                }
+                if let Some(stop_time_id) = self.stop_time_ids.get(&key) {
+                    let after_stop_time_id = format!("{}:after", stop_time_id);
+                    after_stop_time_ids.push((after_key, after_stop_time_id.clone()));
+                    after_ids_of_stop_times.insert(stop_time_id.clone(), after_stop_time_id);
+                }
+            }
+            let after_attributions: Vec<Attribution> = self
+                .attributions
+                .values()
+                .filter(|attribution| {
+                    attribution.vehicle_journey_id.as_ref() == Some(&vehicle_journey.id)
+                })
+                .map(|attribution| Attribution {
+                    id: format!("{}:after", attribution.id),
+                    vehicle_journey_id: Some(after_vj_id.clone()),
+                    ..attribution.clone()
+                })
+                .collect();
+            attributions.extend(after_attributions);
+            let after_translations: Vec<Translation> = self
+                .translations
+                .values()
+                .filter_map(|translation| {
+                    let object_id = match translation.object_type {
+                        ObjectType::VehicleJourney
+                            if translation.object_id == vehicle_journey.id =>
+                        {
+                            after_vj_id.clone()
+                        }
+                        ObjectType::StopTime => {
+                            after_ids_of_stop_times.get(&translation.object_id)?.clone()
+                        }
+                        _ => return None,
+                    };
+                    Some(Translation {
+                        object_id,
+                        ..translation.clone()
+                    })
+                })
+                .collect();
+            translations.extend(after_translations);
+            let after_frequencies: Vec<Frequency> = self
+                .frequencies
+                .values()
+                .filter(|frequency| frequency.vehicle_journey_id == vehicle_journey.id)
+                .map(|frequency| Frequency {
+                    vehicle_journey_id: after_vj_id.clone(),
+                    ..frequency.clone()
+                })
+                .collect();
+            frequencies.extend(after_frequencies);
+            vehicle_journeys.push(VehicleJourney {
+                service_id: before_id.clone(),
+                ..vehicle_journey.clone()
+            });
+            vehicle_journeys.push(VehicleJourney {
+                id: after_vj_id,
+                service_id: after_id.clone(),
+                ..vehicle_journey.clone()
+            });
+        }
+        let vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+        let attributions = CollectionWithId::new(attributions)?;
+        // A time frame of a split service applies to both parts
+        let mut timeframes = vec![];
+        for timeframe in self.timeframes.values() {
+            match mapping.get(&timeframe.service_id) {
+                Some((before_id, after_id)) => {
+                    for service_id in [before_id, after_id] {
+                        timeframes.push(Timeframe {
+                            service_id: service_id.clone(),
+                            ..timeframe.clone()
+                        });
+                    }
+                }
+                None => timeframes.push(timeframe.clone()),
+            }
+        }
+
+        self.calendars = calendars;
+        self.vehicle_journeys = vehicle_journeys;
+        self.frequencies = Collection::new(frequencies);
+        self.attributions = attributions;
+        self.translations = Collection::new(translations);
+        self.timeframes = Collection::new(timeframes);
+        self.stop_time_headsigns.extend(after_stop_time_headsigns);
+        self.stop_time_comments.extend(after_stop_time_comments);
+        self.stop_time_ids.extend(after_stop_time_ids);
+        Ok(mapping)
+    }
+
+    /// Filters vehicle_journeys in collection based on their schedule types
+    /// Multiple choice possible (through Vec<>). See VehicleJourneyScheduleType list.
+    pub fn filter_by_vj_schedule_types(
+        &mut self,
+        types: Vec<VehicleJourneyScheduleType>,
+    ) -> Result<()> {
+        self.vehicle_journeys
+            .retain(|vj| types.contains(&vj.get_schedule_type()));
+        Ok(())
+    }
+
+    /// Keep the collections consistent for the new model by purging unreferenced data by
+    /// calendars
+    pub fn sanitize(&mut self) -> Result<()> {
+        fn log_object_removed(object_type: &str, id: &str) {
+            debug!("{} with ID {} has been removed", object_type, id);
+        }
+        fn log_predicate<'a, T, F>(object_type: &'a str, mut f: F) -> impl 'a + FnMut(&T) -> bool
+        where
+            T: Id<T>,
+            F: 'a + FnMut(&T) -> bool,
+        {
+            move |item| {
+                if f(item) {
+                    true
+                } else {
+                    log_object_removed(object_type, item.id());
+                    false
+                }
+            }
+        }
+
+        fn dedup_collection<T: Clone + Eq + Hash>(source: &mut Collection<T>) -> Collection<T> {
+            let calculate_hash = |t: &T| -> u64 {
+                let mut s = DefaultHasher::new();
+                t.hash(&mut s);
+                s.finish()
+            };
+            let mut set: BTreeMap<u64, T> = BTreeMap::new();
+            let items = source.take();
+            for item in items {
+                set.insert(calculate_hash(&item), item);
+            }
+            let collection: Vec<T> = set.values().cloned().collect();
+            Collection::new(collection)
+        }
+
+        self.calendars
+            .retain(log_predicate("Calendar", |cal: &Calendar| {
+                !cal.dates.is_empty()
+            }));
 
         let mut geometries_used = HashSet::<String>::new();
         let mut companies_used = HashSet::<String>::new();
@@ -591,6 +1754,8 @@ impl Collections {
         self.levels
             .retain(|level| level_id_used.contains(&level.id));
         self.calendars.retain(|c| calendars_used.contains(&c.id));
+        self.timeframes
+            .retain(|timeframe| calendars_used.contains(&timeframe.service_id));
         self.addresses
             .retain(|address| addresses_used.contains(&address.id));
 
@@ -654,9 +1819,55 @@ impl Collections {
         self.grid_rel_calendar_line = dedup_collection(&mut self.grid_rel_calendar_line);
         self.occupancies = dedup_collection(&mut self.occupancies);
 
+        let translations = self.translations.take();
+        self.translations = Collection::new(
+            translations
+                .into_iter()
+                .filter(|translation| {
+                    self.contains_object(&translation.object_type, &translation.object_id)
+                })
+                .collect(),
+        );
+        self.translations = dedup_collection(&mut self.translations);
+
+        let networks = &self.networks;
+        let lines = &self.lines;
+        let vehicle_journeys = &self.vehicle_journeys;
+        self.attributions
+            .retain(log_predicate("Attribution", |attribution: &Attribution| {
+                attribution
+                    .network_id
+                    .as_ref()
+                    .map_or(true, |id| networks.contains_id(id))
+                    && attribution
+                        .line_id
+                        .as_ref()
+                        .map_or(true, |id| lines.contains_id(id))
+                    && attribution
+                        .vehicle_journey_id
+                        .as_ref()
+                        .map_or(true, |id| vehicle_journeys.contains_id(id))
+            }));
+
         Ok(())
     }
 
+    /// Returns false if no object of this type has this identifier, true if
+    /// found or if objects of this type are not identified in the collections
+    pub(crate) fn contains_object(&self, object_type: &ObjectType, object_id: &str) -> bool {
+        match object_type {
+            ObjectType::StopArea => self.stop_areas.contains_id(object_id),
+            ObjectType::StopPoint => self.stop_points.contains_id(object_id),
+            ObjectType::Network => self.networks.contains_id(object_id),
+            ObjectType::Line => self.lines.contains_id(object_id),
+            ObjectType::Route => self.routes.contains_id(object_id),
+            ObjectType::VehicleJourney => self.vehicle_journeys.contains_id(object_id),
+            ObjectType::Ticket => self.tickets.contains_id(object_id),
+            ObjectType::Company => self.companies.contains_id(object_id),
+            ObjectType::StopTime | ObjectType::LineGroup => true,
+        }
+    }
+
     /// Sets the opening and closing times of lines (if they are missing).
     pub fn enhance_line_opening_time(&mut self) {
         type TimeTable = BTreeMap<u8, Time>;
@@ -935,25 +2146,84 @@ impl Collections {
         self.vehicle_journeys = CollectionWithId::new(vehicle_journeys).unwrap();
     }
 
-    /// Many calendars are identical and can be deduplicate
+    /// Many calendars are identical and can be deduplicate. The calendars
+    /// used neither by a vehicle journey nor by a timeframe are removed, then
+    /// the duplicates are merged as in [`Collections::deduplicate_calendars`].
     pub fn calendar_deduplication(&mut self) {
-        let mut calendars_used: Vec<Calendar> = vec![];
         let mut vehicle_journeys = self.vehicle_journeys.take();
         vehicle_journeys.sort_unstable_by(|vj1, vj2| vj1.service_id.cmp(&vj2.service_id));
-        for vehicle_journey in &mut vehicle_journeys {
-            if let Some(calendar) = self.calendars.get(&vehicle_journey.service_id) {
-                if let Some(dup_calendar) =
-                    calendars_used.iter().find(|c| c.dates == calendar.dates)
-                {
-                    vehicle_journey.service_id.clone_from(&dup_calendar.id);
-                } else {
-                    calendars_used.push(calendar.clone());
-                }
+        self.vehicle_journeys = CollectionWithId::new(vehicle_journeys).unwrap();
+        let used_service_ids: HashSet<String> = self
+            .vehicle_journeys
+            .values()
+            .map(|vj| vj.service_id.clone())
+            .chain(
+                self.timeframes
+                    .values()
+                    .map(|timeframe| timeframe.service_id.clone()),
+            )
+            .collect();
+        self.calendars
+            .retain(|calendar| used_service_ids.contains(&calendar.id));
+        self.merge_duplicate_calendars();
+    }
+
+    /// Merges the calendars having exactly the same dates, whether they are
+    /// used or not, keeping the one with the smallest id. The service ids of
+    /// the vehicle journeys and of the timeframes are updated accordingly.
+    /// Calendars without any date are removed (the vehicle journeys using them
+    /// are left for [`Collections::sanitize`] to clean).
+    ///
+    /// Returns, for each removed duplicate, the id of the calendar kept
+    /// instead of it.
+    pub fn deduplicate_calendars(&mut self) -> HashMap<String, String> {
+        self.calendars.retain(|calendar| !calendar.dates.is_empty());
+        self.merge_duplicate_calendars()
+    }
+
+    // Merges the calendars with the same dates into the one with the smallest
+    // id, and updates every service id referencing a removed calendar
+    fn merge_duplicate_calendars(&mut self) -> HashMap<String, String> {
+        let mut representatives: HashMap<&BTreeSet<Date>, &str> = HashMap::new();
+        for calendar in self.calendars.values() {
+            let representative_id = representatives
+                .entry(&calendar.dates)
+                .or_insert(&calendar.id);
+            if calendar.id.as_str() < *representative_id {
+                *representative_id = &calendar.id;
             }
         }
+        let deduplicated_ids: HashMap<String, String> = self
+            .calendars
+            .values()
+            .filter_map(|calendar| {
+                let representative_id = representatives[&calendar.dates];
+                (representative_id != calendar.id)
+                    .then(|| (calendar.id.clone(), representative_id.to_string()))
+            })
+            .collect();
         self.calendars
-            .retain(|calendar| calendars_used.contains(calendar));
-        self.vehicle_journeys = CollectionWithId::new(vehicle_journeys).unwrap();
+            .retain(|calendar| !deduplicated_ids.contains_key(&calendar.id));
+        if deduplicated_ids.is_empty() {
+            return deduplicated_ids;
+        }
+
+        for vj_idx in self.vehicle_journeys.indexes() {
+            if let Some(service_id) =
+                deduplicated_ids.get(&self.vehicle_journeys[vj_idx].service_id)
+            {
+                self.vehicle_journeys
+                    .index_mut(vj_idx)
+                    .service_id
+                    .clone_from(service_id);
+            }
+        }
+        for timeframe in self.timeframes.values_mut() {
+            if let Some(service_id) = deduplicated_ids.get(&timeframe.service_id) {
+                timeframe.service_id.clone_from(service_id);
+            }
+        }
+        deduplicated_ids
     }
 
     /// Some comments are identical and can be deduplicated
@@ -1339,62 +2609,451 @@ impl Collections {
             .ok_or_else(|| anyhow!("Cannot calculate validity period because there is no dataset"))
     }
 
-    /// Convert given frequencies to stoptimes
-    /// by creating new duplicated vehicle_journeys, calendars and comments if necessary
-    pub fn convert_frequencies_to_stoptimes(&mut self, frequencies: Vec<Frequency>) -> Result<()> {
-        let mut trip_id_sequence: HashMap<String, u32> = HashMap::new();
-        let mut new_vehicle_journeys: Vec<VehicleJourney> = vec![];
+    /// The `feed_start_date` and `feed_end_date` of the feed infos, when set
+    /// and valid
+    pub(crate) fn feed_infos_validity_period(&self) -> (Option<Date>, Option<Date>) {
+        let date = |param: &str| {
+            self.feed_infos
+                .get(param)
+                .and_then(|date| Date::parse_from_str(date, "%Y%m%d").ok())
+        };
+        (date("feed_start_date"), date("feed_end_date"))
+    }
 
-        for frequency in &frequencies {
-            if frequency.start_time == frequency.end_time {
+    /// Returns the dates of the calendars outside of the validity period of
+    /// the feed, as `(service_id, date)` pairs sorted by service id then date.
+    /// The validity period is the one of the feed infos (`feed_start_date`
+    /// and `feed_end_date`), its missing bounds being taken from
+    /// [`Collections::calculate_validity_period`]. Nothing is returned when
+    /// there is no validity period at all.
+    ///
+    /// The calendars are left untouched, see
+    /// [`Collections::restrict_calendars`] to remove these dates.
+    pub fn validate_calendar_bounds(&self) -> Vec<(String, Date)> {
+        let (start_date, end_date) = match self.feed_infos_validity_period() {
+            (Some(start_date), Some(end_date)) => (start_date, end_date),
+            (None, None) => match self.calculate_validity_period() {
+                Ok(validity_period) => validity_period,
+                Err(_) => return vec![],
+            },
+            (start_date, end_date) => {
+                let (validity_start_date, validity_end_date) = self
+                    .calculate_validity_period()
+                    .unwrap_or((Date::MIN, Date::MAX));
+                (
+                    start_date.unwrap_or(validity_start_date),
+                    end_date.unwrap_or(validity_end_date),
+                )
+            }
+        };
+        let mut calendars: Vec<&Calendar> = self.calendars.values().collect();
+        calendars.sort_unstable_by(|c1, c2| c1.id.cmp(&c2.id));
+        let mut out_of_bounds_dates = vec![];
+        for calendar in calendars {
+            let nb_dates = out_of_bounds_dates.len();
+            out_of_bounds_dates.extend(
+                calendar
+                    .dates
+                    .iter()
+                    .filter(|date| **date < start_date || **date > end_date)
+                    .map(|date| (calendar.id.clone(), *date)),
+            );
+            if out_of_bounds_dates.len() > nb_dates {
                 warn!(
-                    "frequency for trip {:?} has same start and end time",
-                    frequency.vehicle_journey_id
+                    "service {} has {} dates outside of the feed validity period from {} to {}",
+                    calendar.id,
+                    out_of_bounds_dates.len() - nb_dates,
+                    start_date,
+                    end_date
                 );
-                continue;
             }
-            let corresponding_vj = skip_error_and_warn!(self
-                .vehicle_journeys
-                .get(&frequency.vehicle_journey_id)
-                .cloned()
-                .ok_or_else(|| anyhow!(
-                    "frequency mapped to an unexisting trip {:?}",
-                    frequency.vehicle_journey_id
-                )));
-            let mut start_time = frequency.start_time;
+        }
+        out_of_bounds_dates
+    }
 
-            let Some(st) = corresponding_vj.stop_times.iter().min() else {
-                warn!(
-                    "frequency mapped to trip {:?} with no stop_times",
-                    frequency.vehicle_journey_id
+    /// Check that all the references between the objects resolve: the
+    /// calendar, route, physical mode, dataset, company, trip property and
+    /// stop points of the vehicle journeys, the line of the routes, the
+    /// network and commercial mode of the lines, the stop area of the stop
+    /// points, the contributor of the datasets, the vehicle journey of the
+    /// frequencies and the stop points of the transfers.
+    /// All the missing references are returned.
+    pub fn check_referential_integrity(&self) -> std::result::Result<(), Vec<IntegrityError>> {
+        let mut errors = vec![];
+        let mut check = |collection: &'static str,
+                         object_id: &str,
+                         target_collection: &'static str,
+                         missing_id: &str,
+                         is_found: bool| {
+            if !is_found {
+                errors.push(IntegrityError {
+                    collection,
+                    object_id: object_id.to_string(),
+                    target_collection,
+                    missing_id: missing_id.to_string(),
+                });
+            }
+        };
+        let stop_point_idxs: IdxSet<StopPoint> = self.stop_points.indexes().collect();
+        for vj in self.vehicle_journeys.values() {
+            for (target_collection, target_id, is_found) in [
+                (
+                    "calendar",
+                    &vj.service_id,
+                    self.calendars.contains_id(&vj.service_id),
+                ),
+                ("route", &vj.route_id, self.routes.contains_id(&vj.route_id)),
+                (
+                    "physical_mode",
+                    &vj.physical_mode_id,
+                    self.physical_modes.contains_id(&vj.physical_mode_id),
+                ),
+                (
+                    "dataset",
+                    &vj.dataset_id,
+                    self.datasets.contains_id(&vj.dataset_id),
+                ),
+                (
+                    "company",
+                    &vj.company_id,
+                    self.companies.contains_id(&vj.company_id),
+                ),
+            ] {
+                check(
+                    "vehicle_journey",
+                    &vj.id,
+                    target_collection,
+                    target_id,
+                    is_found,
                 );
-                continue;
-            };
-
-            if corresponding_vj.stop_times.iter().any(|st| {
-                st.start_pickup_drop_off_window.is_some() || st.end_pickup_drop_off_window.is_some()
-            }) {
-                warn!(
-                    "frequency mapped to trip {:?} with drop_off_window stop_times not supported",
-                    frequency.vehicle_journey_id
+            }
+            if let Some(trip_property_id) = &vj.trip_property_id {
+                check(
+                    "vehicle_journey",
+                    &vj.id,
+                    "trip_property",
+                    trip_property_id,
+                    self.trip_properties.contains_id(trip_property_id),
                 );
-                continue;
             }
-
-            let mut arrival_time_delta = st
-                .arrival_time
-                .expect("`stop_time.arrival_time` should not be empty");
-
-            while start_time < frequency.end_time {
-                trip_id_sequence
-                    .entry(frequency.vehicle_journey_id.clone())
-                    .and_modify(|counter| *counter += 1)
-                    .or_insert(0);
-                let generated_trip_id = format!(
-                    "{}-{}",
-                    frequency.vehicle_journey_id, trip_id_sequence[&frequency.vehicle_journey_id]
+            for stop_time in &vj.stop_times {
+                check(
+                    "stop_time",
+                    &format!("{}-{}", vj.id, stop_time.sequence),
+                    "stop_point",
+                    &format!("{:?}", stop_time.stop_point_idx),
+                    stop_point_idxs.contains(&stop_time.stop_point_idx),
                 );
-                // the following handles generated trip starting after midnight, we need to generate a
+            }
+        }
+        for route in self.routes.values() {
+            check(
+                "route",
+                &route.id,
+                "line",
+                &route.line_id,
+                self.lines.contains_id(&route.line_id),
+            );
+        }
+        for line in self.lines.values() {
+            check(
+                "line",
+                &line.id,
+                "network",
+                &line.network_id,
+                self.networks.contains_id(&line.network_id),
+            );
+            check(
+                "line",
+                &line.id,
+                "commercial_mode",
+                &line.commercial_mode_id,
+                self.commercial_modes.contains_id(&line.commercial_mode_id),
+            );
+        }
+        for stop_point in self.stop_points.values() {
+            check(
+                "stop_point",
+                &stop_point.id,
+                "stop_area",
+                &stop_point.stop_area_id,
+                self.stop_areas.contains_id(&stop_point.stop_area_id),
+            );
+        }
+        for dataset in self.datasets.values() {
+            check(
+                "dataset",
+                &dataset.id,
+                "contributor",
+                &dataset.contributor_id,
+                self.contributors.contains_id(&dataset.contributor_id),
+            );
+        }
+        for frequency in self.frequencies.values() {
+            check(
+                "frequency",
+                &frequency.vehicle_journey_id,
+                "vehicle_journey",
+                &frequency.vehicle_journey_id,
+                self.vehicle_journeys
+                    .contains_id(&frequency.vehicle_journey_id),
+            );
+        }
+        for transfer in self.transfers.values() {
+            for stop_id in [&transfer.from_stop_id, &transfer.to_stop_id] {
+                check(
+                    "transfer",
+                    &format!("{}-{}", transfer.from_stop_id, transfer.to_stop_id),
+                    "stop_point",
+                    stop_id,
+                    self.stop_points.contains_id(stop_id),
+                );
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Compare with `other`, reporting by collection the identifiers of the
+    /// added, removed and changed objects, and by service the added and
+    /// removed active dates.
+    /// The contents of the objects are compared on their serialized form, plus
+    /// the stop times of the vehicle journeys (the stop points being compared
+    /// on their identifiers) and the active dates of the calendars.
+    pub fn diff(&self, other: &Collections) -> CollectionsDiff {
+        let mut collections = BTreeMap::new();
+        let mut add = |name: &'static str, diff: CollectionDiff| {
+            if !diff.is_empty() {
+                collections.insert(name, diff);
+            }
+        };
+        macro_rules! diff_serialization {
+            ($($field:ident),*) => {
+                $(add(
+                    stringify!($field),
+                    diff_collection(&self.$field, &other.$field, is_serialization_changed),
+                );)*
+            };
+        }
+        diff_serialization!(
+            contributors,
+            datasets,
+            networks,
+            commercial_modes,
+            lines,
+            routes,
+            physical_modes,
+            stop_areas,
+            stop_points,
+            stop_locations,
+            companies,
+            comments,
+            booking_rules,
+            equipments,
+            trip_properties,
+            geometries,
+            tickets,
+            ticket_uses,
+            pathways,
+            levels,
+            attributions,
+            grid_calendars,
+            addresses,
+            administrative_regions
+        );
+        add(
+            "calendars",
+            diff_collection(
+                &self.calendars,
+                &other.calendars,
+                |calendar, other_calendar| {
+                    calendar.dates != other_calendar.dates
+                        || is_serialization_changed(calendar, other_calendar)
+                },
+            ),
+        );
+        add(
+            "vehicle_journeys",
+            diff_collection(
+                &self.vehicle_journeys,
+                &other.vehicle_journeys,
+                |vj, other_vj| {
+                    is_serialization_changed(vj, other_vj)
+                        || vj.stop_times.len() != other_vj.stop_times.len()
+                        || vj.stop_times.iter().zip(other_vj.stop_times.iter()).any(
+                            |(st, other_st)| {
+                                self.stop_points[st.stop_point_idx].id
+                                    != other.stop_points[other_st.stop_point_idx].id
+                                    || StopTime {
+                                        stop_point_idx: other_st.stop_point_idx,
+                                        ..st.clone()
+                                    } != *other_st
+                            },
+                        )
+                },
+            ),
+        );
+        let calendars = self
+            .calendars
+            .values()
+            .filter_map(|calendar| {
+                let other_calendar = other.calendars.get(&calendar.id)?;
+                let diff = CalendarDiff {
+                    added_dates: other_calendar
+                        .dates
+                        .difference(&calendar.dates)
+                        .copied()
+                        .collect(),
+                    removed_dates: calendar
+                        .dates
+                        .difference(&other_calendar.dates)
+                        .copied()
+                        .collect(),
+                };
+                if diff.added_dates.is_empty() && diff.removed_dates.is_empty() {
+                    None
+                } else {
+                    Some((calendar.id.clone(), diff))
+                }
+            })
+            .collect();
+        CollectionsDiff {
+            collections,
+            calendars,
+        }
+    }
+
+    /// Calculate the validity period from the calendars, from the first to the
+    /// last active date of all of them.
+    /// Unlike [`Collections::calculate_validity_period`], the datasets are
+    /// not used. If no calendar has any date, an error is returned.
+    pub fn calculate_calendars_validity_period(&self) -> Result<ValidityPeriod> {
+        let start_date = self
+            .calendars
+            .values()
+            .filter_map(|calendar| calendar.dates.iter().next())
+            .min();
+        let end_date = self
+            .calendars
+            .values()
+            .filter_map(|calendar| calendar.dates.iter().next_back())
+            .max();
+        start_date
+            .zip(end_date)
+            .map(|(start_date, end_date)| ValidityPeriod {
+                start_date: *start_date,
+                end_date: *end_date,
+            })
+            .ok_or_else(|| {
+                anyhow!("Cannot calculate validity period because there is no calendar date")
+            })
+    }
+
+    /// Convert given frequencies to stoptimes
+    /// by creating new duplicated vehicle_journeys, calendars and comments if necessary
+    pub fn convert_frequencies_to_stoptimes(&mut self, frequencies: Vec<Frequency>) -> Result<()> {
+        let frequencies = frequencies
+            .into_iter()
+            .map(|frequency| (frequency, true))
+            .collect();
+        self.expand_frequencies(frequencies)
+    }
+
+    /// Expand each frequency into vehicle journeys starting at `start_time`,
+    /// then every `headway_secs`, the last one being the last departure
+    /// strictly before `end_time`.
+    /// Frequencies not flagged as exact (GTFS `exact_times=0`) are still
+    /// expanded but their stop_times are marked as estimated. The attributions
+    /// and translations of an expanded vehicle journey are duplicated for
+    /// each generated one.
+    pub(crate) fn expand_frequencies(&mut self, frequencies: Vec<(Frequency, bool)>) -> Result<()> {
+        let mut trip_id_sequence: HashMap<String, u32> = HashMap::new();
+        let mut new_vehicle_journeys: Vec<VehicleJourney> = vec![];
+        let mut new_calendars: Vec<Calendar> = vec![];
+        let mut new_attributions: Vec<Attribution> = vec![];
+        let mut new_translations: Vec<Translation> = vec![];
+        let mut new_stop_time_comments: HashMap<(String, u32), String> = HashMap::new();
+        let mut new_stop_time_ids: HashMap<(String, u32), String> = HashMap::new();
+        let frequency_vj_ids: HashSet<&String> = frequencies
+            .iter()
+            .map(|(frequency, _)| &frequency.vehicle_journey_id)
+            .collect();
+        let mut attributions_by_vj: HashMap<&String, Vec<&Attribution>> = HashMap::new();
+        for attribution in self.attributions.values() {
+            if let Some(vj_id) = attribution.vehicle_journey_id.as_ref() {
+                if frequency_vj_ids.contains(vj_id) {
+                    attributions_by_vj
+                        .entry(vj_id)
+                        .or_default()
+                        .push(attribution);
+                }
+            }
+        }
+        let mut translations_by_vj: HashMap<&String, Vec<&Translation>> = HashMap::new();
+        for translation in self.translations.values() {
+            if translation.object_type == ObjectType::VehicleJourney
+                && frequency_vj_ids.contains(&translation.object_id)
+            {
+                translations_by_vj
+                    .entry(&translation.object_id)
+                    .or_default()
+                    .push(translation);
+            }
+        }
+
+        for (frequency, exact_times) in &frequencies {
+            if frequency.start_time == frequency.end_time {
+                warn!(
+                    "frequency for trip {:?} has same start and end time",
+                    frequency.vehicle_journey_id
+                );
+                continue;
+            }
+            let corresponding_vj = skip_error_and_warn!(self
+                .vehicle_journeys
+                .get(&frequency.vehicle_journey_id)
+                .cloned()
+                .ok_or_else(|| anyhow!(
+                    "frequency mapped to an unexisting trip {:?}",
+                    frequency.vehicle_journey_id
+                )));
+            let mut start_time = frequency.start_time;
+
+            let Some(st) = corresponding_vj.stop_times.iter().min() else {
+                warn!(
+                    "frequency mapped to trip {:?} with no stop_times",
+                    frequency.vehicle_journey_id
+                );
+                continue;
+            };
+
+            if corresponding_vj.stop_times.iter().any(|st| {
+                st.start_pickup_drop_off_window.is_some() || st.end_pickup_drop_off_window.is_some()
+            }) {
+                warn!(
+                    "frequency mapped to trip {:?} with drop_off_window stop_times not supported",
+                    frequency.vehicle_journey_id
+                );
+                continue;
+            }
+
+            let mut arrival_time_delta = st
+                .arrival_time
+                .expect("`stop_time.arrival_time` should not be empty");
+
+            while start_time < frequency.end_time {
+                trip_id_sequence
+                    .entry(frequency.vehicle_journey_id.clone())
+                    .and_modify(|counter| *counter += 1)
+                    .or_insert(0);
+                let generated_trip_id = format!(
+                    "{}-{}",
+                    frequency.vehicle_journey_id, trip_id_sequence[&frequency.vehicle_journey_id]
+                );
+                // the following handles generated trip starting after midnight, we need to generate a
                 // new service in case the next day is not covered
                 let service_id = if start_time.hours() >= 24 {
                     let nb_days = u64::from(start_time.hours() / 24);
@@ -1404,7 +3063,11 @@ impl Collections {
                         .cloned()
                         .unwrap();
                     let new_service_id = format!("{}:+{}days", service.id, nb_days);
-                    if !self.calendars.contains_id(&new_service_id) {
+                    if !self.calendars.contains_id(&new_service_id)
+                        && !new_calendars
+                            .iter()
+                            .any(|calendar| calendar.id == new_service_id)
+                    {
                         arrival_time_delta = arrival_time_delta + Time::new(24, 0, 0);
                         let new_dates: BTreeSet<_> = service
                             .dates
@@ -1415,8 +3078,9 @@ impl Collections {
                         let new_service = Calendar {
                             id: new_service_id.clone(),
                             dates: new_dates,
+                            ..Default::default()
                         };
-                        self.calendars.push(new_service)?;
+                        new_calendars.push(new_service);
                     }
                     new_service_id
                 } else {
@@ -1444,7 +3108,11 @@ impl Collections {
                             pickup_type: stop_time.pickup_type,
                             drop_off_type: stop_time.drop_off_type,
                             local_zone_id: stop_time.local_zone_id,
-                            precision: stop_time.precision.clone(),
+                            precision: if *exact_times {
+                                stop_time.precision.clone()
+                            } else {
+                                Some(StopTimePrecision::Estimated)
+                            },
                         }
                     })
                     .collect();
@@ -1456,6 +3124,28 @@ impl Collections {
                     ..corresponding_vj.clone()
                 };
                 new_vehicle_journeys.push(generated_vj);
+                let sequence = trip_id_sequence[&frequency.vehicle_journey_id];
+                for attribution in attributions_by_vj
+                    .get(&frequency.vehicle_journey_id)
+                    .into_iter()
+                    .flatten()
+                {
+                    new_attributions.push(Attribution {
+                        id: format!("{}-{}", attribution.id, sequence),
+                        vehicle_journey_id: Some(generated_trip_id.clone()),
+                        ..(*attribution).clone()
+                    });
+                }
+                for translation in translations_by_vj
+                    .get(&frequency.vehicle_journey_id)
+                    .into_iter()
+                    .flatten()
+                {
+                    new_translations.push(Translation {
+                        object_id: generated_trip_id.clone(),
+                        ..(*translation).clone()
+                    });
+                }
                 let stop_time_comments: HashMap<(String, u32), String> = corresponding_vj
                     .stop_times
                     .iter()
@@ -1480,21 +3170,60 @@ impl Collections {
                         )
                     })
                     .collect();
-                self.stop_time_comments.extend(stop_time_comments);
-                self.stop_time_ids.extend(stop_time_ids);
+                new_stop_time_comments.extend(stop_time_comments);
+                new_stop_time_ids.extend(stop_time_ids);
             }
         }
-        let mut vehicle_journeys = self.vehicle_journeys.take();
+        let trip_ids_to_remove: Vec<_> = frequencies
+            .iter()
+            .map(|(f, _)| &f.vehicle_journey_id)
+            .collect();
 
-        let trip_ids_to_remove: Vec<_> =
-            frequencies.iter().map(|f| &f.vehicle_journey_id).collect();
+        // the generated ids may already be used, which is checked before any
+        // collection is modified
+        if let Some(vehicle_journey) = new_vehicle_journeys.iter().find(|vehicle_journey| {
+            self.vehicle_journeys.contains_id(&vehicle_journey.id)
+                && !trip_ids_to_remove.contains(&&vehicle_journey.id)
+        }) {
+            bail!(
+                "vehicle journey {} generated from a frequency already exists",
+                vehicle_journey.id
+            );
+        }
+        let mut attributions: Vec<Attribution> = self
+            .attributions
+            .values()
+            .filter(|attribution| {
+                attribution
+                    .vehicle_journey_id
+                    .as_ref()
+                    .map_or(true, |vj_id| !trip_ids_to_remove.contains(&vj_id))
+            })
+            .cloned()
+            .collect();
+        attributions.append(&mut new_attributions);
+        let attributions = CollectionWithId::new(attributions)?;
 
-        vehicle_journeys.retain(|vj| !trip_ids_to_remove.contains(&&vj.id));
+        for calendar in new_calendars {
+            self.calendars.push(calendar)?;
+        }
+        self.attributions = attributions;
         self.stop_time_ids
             .retain(|(vj_id, _), _| !trip_ids_to_remove.contains(&vj_id));
+        self.stop_time_ids.extend(new_stop_time_ids);
         self.stop_time_comments
             .retain(|(vj_id, _), _| !trip_ids_to_remove.contains(&vj_id));
+        self.stop_time_comments.extend(new_stop_time_comments);
+        self.translations.retain(|translation| {
+            translation.object_type != ObjectType::VehicleJourney
+                || !trip_ids_to_remove.contains(&&translation.object_id)
+        });
+        for translation in new_translations {
+            self.translations.push(translation);
+        }
 
+        let mut vehicle_journeys = self.vehicle_journeys.take();
+        vehicle_journeys.retain(|vj| !trip_ids_to_remove.contains(&&vj.id));
         vehicle_journeys.append(&mut new_vehicle_journeys);
         self.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
 
@@ -1508,6 +3237,240 @@ impl Collections {
         let frequencies = self.frequencies.take();
         self.convert_frequencies_to_stoptimes(frequencies)
     }
+
+    /// Reproject the coordinates of the stop points, stop areas and stop
+    /// locations from their current CRS (WGS84 unless already reprojected)
+    /// to the CRS of EPSG code `target_epsg`, which is recorded in
+    /// `stops_epsg`: `lon` then holds the easting and `lat` the northing.
+    /// Reprojecting to WGS84 (EPSG:4326) resets `stops_epsg` to `None`.
+    ///
+    /// Fails without changing any coordinate when the EPSG code is not
+    /// supported or when a coordinate can't be converted.
+    #[cfg(feature = "proj")]
+    pub fn reproject_stops(&mut self, target_epsg: u32) -> Result<()> {
+        if target_epsg == self.stops_epsg.unwrap_or(WGS84_EPSG) {
+            return Ok(());
+        }
+        let from = format!("EPSG:{}", self.stops_epsg.unwrap_or(WGS84_EPSG));
+        let to = format!("EPSG:{}", target_epsg);
+        let converter = proj::Proj::new_known_crs(&from, &to, None)
+            .map_err(|e| anyhow!("cannot convert coordinates from {} to {}: {}", from, to, e))?;
+        let convert = |id: &str, coord: &Coord| -> Result<Coord> {
+            converter
+                .convert(*coord)
+                .map_err(|e| anyhow!("cannot convert coordinates of stop {}: {}", id, e))
+        };
+        let stop_point_coords = self
+            .stop_points
+            .values()
+            .map(|stop_point| convert(&stop_point.id, &stop_point.coord))
+            .collect::<Result<Vec<_>>>()?;
+        let stop_area_coords = self
+            .stop_areas
+            .values()
+            .map(|stop_area| convert(&stop_area.id, &stop_area.coord))
+            .collect::<Result<Vec<_>>>()?;
+        let stop_location_coords = self
+            .stop_locations
+            .values()
+            .map(|stop_location| convert(&stop_location.id, &stop_location.coord))
+            .collect::<Result<Vec<_>>>()?;
+
+        let stop_point_idxs: Vec<_> = self.stop_points.iter().map(|(idx, _)| idx).collect();
+        for (idx, coord) in stop_point_idxs.into_iter().zip(stop_point_coords) {
+            self.stop_points.index_mut(idx).coord = coord;
+        }
+        let stop_area_idxs: Vec<_> = self.stop_areas.iter().map(|(idx, _)| idx).collect();
+        for (idx, coord) in stop_area_idxs.into_iter().zip(stop_area_coords) {
+            self.stop_areas.index_mut(idx).coord = coord;
+        }
+        let stop_location_idxs: Vec<_> = self.stop_locations.iter().map(|(idx, _)| idx).collect();
+        for (idx, coord) in stop_location_idxs.into_iter().zip(stop_location_coords) {
+            self.stop_locations.index_mut(idx).coord = coord;
+        }
+        self.stops_epsg = (target_epsg != WGS84_EPSG).then_some(target_epsg);
+        Ok(())
+    }
+
+    /// Merge `other` into the collections after prefixing all its
+    /// identifiers, and the references between its objects, with `prefix`.
+    ///
+    /// Fails without merging anything if some identifiers of `other` still
+    /// collide with the existing ones once prefixed, listing them in the error.
+    /// Commercial modes, physical modes, addresses and administrative regions
+    /// are not prefixed: the existing ones win over the ones of `other` with
+    /// the same identifier.
+    pub fn try_merge(&mut self, mut other: Collections, prefix: &str) -> Result<()> {
+        if self.stops_epsg != other.stops_epsg {
+            bail!(
+                "Cannot merge collections with stops in different CRS: {:?} and {:?}",
+                self.stops_epsg,
+                other.stops_epsg
+            );
+        }
+        let mut prefix_conf = PrefixConfiguration::default();
+        prefix_conf.set_data_prefix(prefix);
+        other.prefix(&prefix_conf);
+
+        let mut collisions: Vec<String> = vec![];
+        macro_rules! check_collisions {
+            ($($field:ident),*) => {
+                $(
+                    collisions.extend(
+                        other
+                            .$field
+                            .values()
+                            .filter(|object| self.$field.contains_id(object.id()))
+                            .map(|object| format!("{} {}", stringify!($field), object.id())),
+                    );
+                )*
+            };
+        }
+        macro_rules! merge_with_id {
+            ($($field:ident),*) => {
+                $(
+                    let mut objects = self.$field.take();
+                    objects.extend(other.$field.take());
+                    self.$field = CollectionWithId::new(objects)?;
+                )*
+            };
+        }
+        macro_rules! merge_keeping_existing {
+            ($($field:ident),*) => {
+                $(
+                    let mut objects = self.$field.take();
+                    let existing_ids: HashSet<String> =
+                        objects.iter().map(|object| object.id().to_string()).collect();
+                    objects.extend(
+                        other
+                            .$field
+                            .take()
+                            .into_iter()
+                            .filter(|object| !existing_ids.contains(object.id())),
+                    );
+                    self.$field = CollectionWithId::new(objects)?;
+                )*
+            };
+        }
+        macro_rules! merge_without_id {
+            ($($field:ident),*) => {
+                $(
+                    let mut objects = self.$field.take();
+                    objects.extend(other.$field.take());
+                    self.$field = Collection::new(objects);
+                )*
+            };
+        }
+
+        check_collisions!(
+            contributors,
+            datasets,
+            networks,
+            lines,
+            routes,
+            vehicle_journeys,
+            stop_areas,
+            stop_points,
+            stop_locations,
+            calendars,
+            companies,
+            comments,
+            booking_rules,
+            equipments,
+            trip_properties,
+            geometries,
+            tickets,
+            ticket_uses,
+            pathways,
+            levels,
+            attributions,
+            grid_calendars
+        );
+        if !collisions.is_empty() {
+            bail!(
+                "Cannot merge collections prefixed with {:?}, these ids already exist: {}",
+                prefix,
+                collisions.join(", ")
+            );
+        }
+
+        // stop times reference the stop points by index, which changes when
+        // the stop points are merged
+        let mut vehicle_journeys = other.vehicle_journeys.take();
+        let stop_point_ids: Vec<Vec<String>> = vehicle_journeys
+            .iter()
+            .map(|vj| {
+                vj.stop_times
+                    .iter()
+                    .map(|st| other.stop_points[st.stop_point_idx].id.clone())
+                    .collect()
+            })
+            .collect();
+        merge_with_id!(
+            contributors,
+            datasets,
+            networks,
+            lines,
+            routes,
+            stop_areas,
+            stop_points,
+            stop_locations,
+            calendars,
+            companies,
+            comments,
+            booking_rules,
+            equipments,
+            trip_properties,
+            geometries,
+            tickets,
+            ticket_uses,
+            pathways,
+            levels,
+            attributions,
+            grid_calendars
+        );
+        for (vj, stop_point_ids) in vehicle_journeys.iter_mut().zip(stop_point_ids) {
+            for (st, stop_point_id) in vj.stop_times.iter_mut().zip(stop_point_ids) {
+                st.stop_point_idx = self
+                    .stop_points
+                    .get_idx(&stop_point_id)
+                    .ok_or_else(|| anyhow!("stop point {} not found", stop_point_id))?;
+            }
+        }
+        let mut objects = self.vehicle_journeys.take();
+        objects.extend(vehicle_journeys);
+        self.vehicle_journeys = CollectionWithId::new(objects)?;
+        merge_keeping_existing!(
+            commercial_modes,
+            physical_modes,
+            addresses,
+            administrative_regions
+        );
+        merge_without_id!(
+            frequencies,
+            transfers,
+            admin_stations,
+            prices_v1,
+            od_fares_v1,
+            fares_v1,
+            ticket_prices,
+            ticket_use_perimeters,
+            ticket_use_restrictions,
+            grid_exception_dates,
+            grid_periods,
+            grid_rel_calendar_line,
+            occupancies,
+            timeframes,
+            translations
+        );
+        for (key, value) in other.feed_infos {
+            self.feed_infos.entry(key).or_insert(value);
+        }
+        self.stop_time_headsigns.extend(other.stop_time_headsigns);
+        self.stop_time_ids.extend(other.stop_time_ids);
+        self.stop_time_comments.extend(other.stop_time_comments);
+        Ok(())
+    }
 }
 
 /// The navitia transit model.
@@ -1752,68 +3715,590 @@ impl ops::Deref for Model {
 mod tests {
     use super::*;
 
-    mod enhance_trip_headsign {
+    mod calculate_calendars_validity_period {
         use super::*;
         use pretty_assertions::assert_eq;
 
-        fn collections(trip_headsign: Option<String>) -> Collections {
+        fn calendar(id: &str, dates: &[Date]) -> Calendar {
+            let mut calendar = Calendar::new(String::from(id));
+            calendar.dates.extend(dates);
+            calendar
+        }
+
+        #[test]
+        fn single_one_day_calendar() {
+            let date = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+            let mut collections = Collections::default();
+            collections.calendars.push(calendar("c1", &[date])).unwrap();
+            assert_eq!(
+                ValidityPeriod {
+                    start_date: date,
+                    end_date: date,
+                },
+                collections.calculate_calendars_validity_period().unwrap()
+            );
+        }
+
+        #[test]
+        fn disjoint_calendars() {
+            let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
             let mut collections = Collections::default();
             collections
-                .stop_points
-                .push(StopPoint {
-                    id: String::from("stop_point_id"),
-                    name: String::from("Stop Name"),
-                    ..Default::default()
-                })
-                .unwrap();
-            let stop_time = StopTime {
-                stop_point_idx: collections.stop_points.get_idx("stop_point_id").unwrap(),
-                sequence: 0,
-                arrival_time: Some(Time::new(0, 0, 0)),
-                departure_time: Some(Time::new(0, 0, 0)),
-                start_pickup_drop_off_window: None,
-                end_pickup_drop_off_window: None,
-                boarding_duration: 0,
-                alighting_duration: 0,
-                pickup_type: 0,
-                drop_off_type: 0,
-                local_zone_id: Some(0),
-                precision: None,
-            };
-            collections
-                .vehicle_journeys
-                .push(VehicleJourney {
-                    id: String::from("vehicle_journey_id_1"),
-                    stop_times: vec![stop_time],
-                    headsign: trip_headsign,
-                    ..Default::default()
-                })
+                .calendars
+                .push(calendar("c1", &[date(3, 1), date(3, 10)]))
                 .unwrap();
             collections
-                .vehicle_journeys
-                .push(VehicleJourney {
-                    id: String::from("vehicle_journey_id_2"),
-                    headsign: Some(String::from("Headsign")),
-                    ..Default::default()
-                })
+                .calendars
+                .push(calendar("c2", &[date(1, 5), date(1, 6)]))
                 .unwrap();
-            collections
+            collections.calendars.push(calendar("empty", &[])).unwrap();
+            assert_eq!(
+                ValidityPeriod {
+                    start_date: date(1, 5),
+                    end_date: date(3, 10),
+                },
+                collections.calculate_calendars_validity_period().unwrap()
+            );
         }
 
         #[test]
-        fn enhance() {
-            let mut collections = collections(None);
-            collections.enhance_trip_headsign();
-            let vehicle_journey = collections
-                .vehicle_journeys
-                .get("vehicle_journey_id_1")
-                .unwrap();
-            assert_eq!("Stop Name", vehicle_journey.headsign.as_ref().unwrap());
-            let vehicle_journey = collections
-                .vehicle_journeys
-                .get("vehicle_journey_id_2")
-                .unwrap();
-            assert_eq!("Headsign", vehicle_journey.headsign.as_ref().unwrap());
+        fn no_calendar_date() {
+            let mut collections = Collections::default();
+            assert!(collections.calculate_calendars_validity_period().is_err());
+            collections.calendars.push(calendar("empty", &[])).unwrap();
+            assert!(collections.calculate_calendars_validity_period().is_err());
+        }
+    }
+
+    mod validate_calendar_bounds {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn dates_outside_of_feed_infos() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = Collections::default();
+            let mut calendar = Calendar::new("c1".to_string());
+            calendar.dates.extend([date(1), date(5), date(10)]);
+            collections.calendars.push(calendar).unwrap();
+            let mut calendar = Calendar::new("c2".to_string());
+            calendar.dates.insert(date(5));
+            collections.calendars.push(calendar).unwrap();
+            assert!(collections.validate_calendar_bounds().is_empty());
+
+            collections
+                .feed_infos
+                .insert("feed_start_date".to_string(), "20200102".to_string());
+            collections
+                .feed_infos
+                .insert("feed_end_date".to_string(), "20200109".to_string());
+            assert_eq!(
+                vec![("c1".to_string(), date(1)), ("c1".to_string(), date(10))],
+                collections.validate_calendar_bounds()
+            );
+
+            // The missing end date comes from the datasets
+            collections
+                .datasets
+                .push(Dataset {
+                    id: "d1".to_string(),
+                    start_date: date(1),
+                    end_date: date(5),
+                    ..Default::default()
+                })
+                .unwrap();
+            collections.feed_infos.remove("feed_end_date");
+            assert_eq!(
+                vec![("c1".to_string(), date(1)), ("c1".to_string(), date(10))],
+                collections.validate_calendar_bounds()
+            );
+
+            collections.feed_infos.clear();
+            assert_eq!(
+                vec![("c1".to_string(), date(10))],
+                collections.validate_calendar_bounds()
+            );
+        }
+    }
+
+    mod check_referential_integrity {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn valid_collections() {
+            let collections = ModelBuilder::default()
+                .vj("vj1", |vj| {
+                    vj.st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .build()
+                .into_collections();
+            assert_eq!(Ok(()), collections.check_referential_integrity());
+        }
+
+        #[test]
+        fn collect_all_missing_references() {
+            let mut collections = ModelBuilder::default()
+                .vj("vj1", |vj| {
+                    vj.st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .build()
+                .into_collections();
+            collections
+                .vehicle_journeys
+                .get_mut("vj1")
+                .unwrap()
+                .service_id = String::from("unknown_calendar");
+            let route_id = collections.routes.values().next().unwrap().id.clone();
+            collections.routes.get_mut(&route_id).unwrap().line_id = String::from("unknown_line");
+
+            let errors = collections.check_referential_integrity().unwrap_err();
+            assert_eq!(
+                vec![
+                    IntegrityError {
+                        collection: "vehicle_journey",
+                        object_id: String::from("vj1"),
+                        target_collection: "calendar",
+                        missing_id: String::from("unknown_calendar"),
+                    },
+                    IntegrityError {
+                        collection: "route",
+                        object_id: route_id,
+                        target_collection: "line",
+                        missing_id: String::from("unknown_line"),
+                    },
+                ],
+                errors
+            );
+            assert_eq!(
+                "vehicle_journey vj1 references the unknown calendar unknown_calendar",
+                errors[0].to_string()
+            );
+        }
+    }
+
+    mod diff {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        fn collections() -> Collections {
+            ModelBuilder::default()
+                .calendar("c1", &["2020-01-01", "2020-01-02"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.calendar("c1").st("A", "12:00:00").st("B", "13:00:00");
+                })
+                .build()
+                .into_collections()
+        }
+
+        #[test]
+        fn no_difference() {
+            let diff = collections().diff(&collections());
+            assert!(diff.is_empty());
+            assert_eq!("", diff.to_string());
+        }
+
+        #[test]
+        fn report_differences() {
+            let collections = collections();
+            let mut other = ModelBuilder::default()
+                .calendar("c1", &["2020-01-02", "2020-01-03"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("C", "11:00:00");
+                })
+                .vj("vj3", |vj| {
+                    vj.calendar("c1").st("A", "12:00:00").st("B", "13:00:00");
+                })
+                .build()
+                .into_collections();
+            other.stop_points.get_mut("A").unwrap().name = String::from("renamed");
+
+            let diff = collections.diff(&other);
+            assert_eq!(
+                vec!["calendars", "stop_areas", "stop_points", "vehicle_journeys"],
+                diff.collections.keys().copied().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                CollectionDiff {
+                    added: vec![String::from("vj3")].into_iter().collect(),
+                    removed: vec![String::from("vj2")].into_iter().collect(),
+                    changed: vec![String::from("vj1")].into_iter().collect(),
+                },
+                diff.collections["vehicle_journeys"]
+            );
+            assert_eq!(
+                "calendars: 0 added, 0 removed, 1 changed\n  \
+                 ~ c1\n\
+                 stop_areas: 1 added, 0 removed, 0 changed\n  \
+                 + sa:C\n\
+                 stop_points: 1 added, 0 removed, 1 changed\n  \
+                 + C\n  \
+                 ~ A\n\
+                 vehicle_journeys: 1 added, 1 removed, 1 changed\n  \
+                 + vj3\n  \
+                 - vj2\n  \
+                 ~ vj1\n\
+                 calendar c1: +20200103 -20200101\n",
+                diff.to_string()
+            );
+        }
+    }
+
+    mod resolve_stop_time {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn resolve_on_active_dates_only() {
+            let collections = ModelBuilder::default()
+                .calendar("c1", &["2020-03-29"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "25:30:00").st("B", "26:00:00");
+                })
+                .build()
+                .into_collections();
+            let stop_time = &collections.vehicle_journeys.get("vj1").unwrap().stop_times[0];
+            let date = NaiveDate::from_ymd_opt(2020, 3, 29).unwrap();
+            assert_eq!(
+                "2020-03-30T01:30:00+02:00",
+                collections
+                    .resolve_stop_time("c1", date, stop_time, chrono_tz::Europe::Paris)
+                    .unwrap()
+                    .to_rfc3339()
+            );
+            assert!(collections
+                .resolve_stop_time("c1", date.succ_opt().unwrap(), stop_time, chrono_tz::UTC)
+                .is_err());
+            assert!(collections
+                .resolve_stop_time("unknown", date, stop_time, chrono_tz::UTC)
+                .is_err());
+        }
+    }
+
+    mod remove_empty_calendars {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn remove_calendars_and_their_vehicle_journeys() {
+            let mut collections = ModelBuilder::default()
+                .calendar("c1", &["2020-01-01"])
+                .calendar("c2", &["2020-01-02"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.calendar("c2").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .build()
+                .into_collections();
+            collections
+                .stop_time_headsigns
+                .insert(("vj2".to_string(), 0), "headsign".to_string());
+            collections.calendars.get_mut("c2").unwrap().dates.clear();
+
+            assert_eq!(1, collections.remove_empty_calendars());
+            assert_eq!(1, collections.calendars.len());
+            assert!(collections.calendars.contains_id("c1"));
+            assert_eq!(1, collections.vehicle_journeys.len());
+            assert!(collections.vehicle_journeys.contains_id("vj1"));
+            assert!(collections.stop_time_headsigns.is_empty());
+            assert_eq!(0, collections.remove_empty_calendars());
+        }
+
+        #[test]
+        fn restrict_period_keeps_empty_calendars() {
+            let mut collections = ModelBuilder::default()
+                .calendar("c1", &["2020-01-01"])
+                .calendar("c2", &["2020-01-10"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.calendar("c2").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .build()
+                .into_collections();
+            collections
+                .restrict_period(
+                    NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                    NaiveDate::from_ymd_opt(2020, 1, 5).unwrap(),
+                )
+                .unwrap();
+            assert_eq!(2, collections.calendars.len());
+            assert!(collections.calendars.get("c2").unwrap().dates.is_empty());
+            assert_eq!(2, collections.vehicle_journeys.len());
+
+            assert_eq!(1, collections.remove_empty_calendars());
+            assert!(collections.calendars.contains_id("c1"));
+            assert_eq!(1, collections.vehicle_journeys.len());
+            assert!(collections.vehicle_journeys.contains_id("vj1"));
+        }
+    }
+
+    #[cfg(feature = "proj")]
+    mod reproject_stops {
+        use super::*;
+        use approx::assert_relative_eq;
+        use pretty_assertions::assert_eq;
+
+        fn collections() -> Collections {
+            let mut collections = Collections::default();
+            collections.stop_areas = CollectionWithId::from(StopArea {
+                id: "sa".to_string(),
+                coord: Coord {
+                    lon: 2.3522,
+                    lat: 48.8566,
+                },
+                ..Default::default()
+            });
+            collections.stop_points = CollectionWithId::from(StopPoint {
+                id: "sp".to_string(),
+                stop_area_id: "sa".to_string(),
+                coord: Coord {
+                    lon: 2.3488,
+                    lat: 48.8534,
+                },
+                ..Default::default()
+            });
+            collections
+        }
+
+        #[test]
+        fn reproject_to_lambert_93_and_back() {
+            let mut collections = collections();
+            collections.reproject_stops(2154).unwrap();
+            assert_eq!(Some(2154), collections.stops_epsg);
+            let coord = collections.stop_areas.get("sa").unwrap().coord;
+            assert_relative_eq!(652_470.0, coord.lon, epsilon = 1_000.0);
+            assert_relative_eq!(6_862_035.0, coord.lat, epsilon = 1_000.0);
+
+            collections.reproject_stops(4326).unwrap();
+            assert_eq!(None, collections.stops_epsg);
+            let coord = collections.stop_points.get("sp").unwrap().coord;
+            // 1e-7 degree is about 1 centimeter
+            assert_relative_eq!(2.3488, coord.lon, epsilon = 1e-7);
+            assert_relative_eq!(48.8534, coord.lat, epsilon = 1e-7);
+        }
+
+        #[test]
+        fn unsupported_epsg() {
+            let mut collections = collections();
+            assert!(collections.reproject_stops(999_999).is_err());
+            assert_eq!(None, collections.stops_epsg);
+            let coord = collections.stop_points.get("sp").unwrap().coord;
+            assert_eq!(2.3488, coord.lon);
+            assert_eq!(48.8534, coord.lat);
+        }
+    }
+
+    mod stop_spatial_index {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn stop_point(id: &str, lon: f64, lat: f64) -> StopPoint {
+            StopPoint {
+                id: id.to_string(),
+                coord: Coord { lon, lat },
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn nearest_stops() {
+            let mut collections = Collections::default();
+            collections.stop_points = CollectionWithId::new(vec![
+                stop_point("north", 2.35, 48.87),
+                stop_point("far_east", 2.60, 48.85),
+                stop_point("center", 2.35, 48.85),
+                stop_point("south", 2.35, 48.84),
+                stop_point("west", 2.33, 48.85),
+                stop_point("invalid", 200.0, 48.85),
+            ])
+            .unwrap();
+            let index = collections.build_stop_index();
+
+            let nearest = index.nearest_stops(2.351, 48.851, 3);
+            let ids: Vec<&str> = nearest.iter().map(|(sp, _)| sp.id.as_str()).collect();
+            assert_eq!(vec!["center", "south", "west"], ids);
+            let center = Coord {
+                lon: 2.35,
+                lat: 48.85,
+            };
+            let expected = center.distance_to(&Coord {
+                lon: 2.351,
+                lat: 48.851,
+            });
+            assert_eq!(expected, nearest[0].1);
+            assert!(nearest.windows(2).all(|w| w[0].1 <= w[1].1));
+
+            let ids: Vec<&str> = index
+                .nearest_stops(2.70, 48.0, 10)
+                .iter()
+                .map(|(sp, _)| sp.id.as_str())
+                .collect();
+            assert_eq!(5, ids.len());
+            assert_eq!("far_east", ids[0]);
+            assert!(index.nearest_stops(2.35, 48.85, 0).is_empty());
+        }
+    }
+
+    mod stats {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn count_objects() {
+            let collections = ModelBuilder::default()
+                .calendar("c1", &["2020-01-01", "2020-01-02"])
+                .calendar("c2", &["2020-01-05"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.calendar("c2").st("B", "10:00:00").st("C", "11:00:00");
+                })
+                .build()
+                .into_collections();
+
+            let stats = collections.stats();
+            assert_eq!(2, stats.vehicle_journeys);
+            assert_eq!(3, stats.stop_points);
+            assert_eq!(2, stats.calendars);
+            assert_eq!(3, stats.service_days);
+            assert_eq!(NaiveDate::from_ymd_opt(2020, 1, 1), stats.start_date);
+            assert_eq!(NaiveDate::from_ymd_opt(2020, 1, 5), stats.end_date);
+            let json = serde_json::to_value(&stats).unwrap();
+            assert_eq!(3, json["service_days"]);
+            assert_eq!("20200105", json["end_date"]);
+            assert!(stats
+                .to_string()
+                .contains("validity period   20200101 - 20200105"));
+        }
+    }
+
+    mod try_merge {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        fn collections() -> Collections {
+            ModelBuilder::default()
+                .calendar("1", &["2020-01-01"])
+                .vj("vj1", |vj| {
+                    vj.calendar("1").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .build()
+                .into_collections()
+        }
+
+        #[test]
+        fn merge_prefixed_collections() {
+            let mut collections = collections();
+            collections.try_merge(self::collections(), "other").unwrap();
+
+            let mut calendar_ids: Vec<&str> = collections
+                .calendars
+                .values()
+                .map(|c| c.id.as_str())
+                .collect();
+            calendar_ids.sort_unstable();
+            assert_eq!(vec!["1", "other:1"], calendar_ids);
+            let vj = collections.vehicle_journeys.get("other:vj1").unwrap();
+            assert_eq!("other:1", vj.service_id);
+            assert_eq!(
+                "other:A",
+                collections.stop_points[vj.stop_times[0].stop_point_idx].id
+            );
+            assert_eq!(1, collections.physical_modes.len());
+        }
+
+        #[test]
+        fn ids_colliding_after_prefix() {
+            let mut collections = collections();
+            collections
+                .calendars
+                .push(Calendar::new(String::from("other:1")))
+                .unwrap();
+            let error = collections
+                .try_merge(self::collections(), "other")
+                .unwrap_err();
+            assert!(error.to_string().contains("calendars other:1"));
+            assert_eq!(2, collections.calendars.len());
+            assert!(!collections.vehicle_journeys.contains_id("other:vj1"));
+        }
+    }
+
+    mod enhance_trip_headsign {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn collections(trip_headsign: Option<String>) -> Collections {
+            let mut collections = Collections::default();
+            collections
+                .stop_points
+                .push(StopPoint {
+                    id: String::from("stop_point_id"),
+                    name: String::from("Stop Name"),
+                    ..Default::default()
+                })
+                .unwrap();
+            let stop_time = StopTime {
+                stop_point_idx: collections.stop_points.get_idx("stop_point_id").unwrap(),
+                sequence: 0,
+                arrival_time: Some(Time::new(0, 0, 0)),
+                departure_time: Some(Time::new(0, 0, 0)),
+                start_pickup_drop_off_window: None,
+                end_pickup_drop_off_window: None,
+                boarding_duration: 0,
+                alighting_duration: 0,
+                pickup_type: 0,
+                drop_off_type: 0,
+                local_zone_id: Some(0),
+                precision: None,
+            };
+            collections
+                .vehicle_journeys
+                .push(VehicleJourney {
+                    id: String::from("vehicle_journey_id_1"),
+                    stop_times: vec![stop_time],
+                    headsign: trip_headsign,
+                    ..Default::default()
+                })
+                .unwrap();
+            collections
+                .vehicle_journeys
+                .push(VehicleJourney {
+                    id: String::from("vehicle_journey_id_2"),
+                    headsign: Some(String::from("Headsign")),
+                    ..Default::default()
+                })
+                .unwrap();
+            collections
+        }
+
+        #[test]
+        fn enhance() {
+            let mut collections = collections(None);
+            collections.enhance_trip_headsign();
+            let vehicle_journey = collections
+                .vehicle_journeys
+                .get("vehicle_journey_id_1")
+                .unwrap();
+            assert_eq!("Stop Name", vehicle_journey.headsign.as_ref().unwrap());
+            let vehicle_journey = collections
+                .vehicle_journeys
+                .get("vehicle_journey_id_2")
+                .unwrap();
+            assert_eq!("Headsign", vehicle_journey.headsign.as_ref().unwrap());
         }
 
         #[test]
@@ -1824,108 +4309,1166 @@ mod tests {
                 .vehicle_journeys
                 .get("vehicle_journey_id_1")
                 .unwrap();
-            assert_eq!("Stop Name", vehicle_journey.headsign.as_ref().unwrap());
-            let vehicle_journey = collections
-                .vehicle_journeys
-                .get("vehicle_journey_id_2")
+            assert_eq!("Stop Name", vehicle_journey.headsign.as_ref().unwrap());
+            let vehicle_journey = collections
+                .vehicle_journeys
+                .get("vehicle_journey_id_2")
+                .unwrap();
+            assert_eq!("Headsign", vehicle_journey.headsign.as_ref().unwrap());
+        }
+    }
+
+    mod deduplicate_calendars {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn merge_calendars_with_same_dates() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = Collections::default();
+            for (service_id, days) in [
+                ("c3", vec![1, 2]),
+                ("c1", vec![1, 2]),
+                ("c2", vec![3]),
+                ("c4", vec![1, 2]),
+                ("empty", vec![]),
+            ] {
+                let mut calendar = Calendar::new(String::from(service_id));
+                calendar.dates.extend(days.into_iter().map(date));
+                collections.calendars.push(calendar).unwrap();
+            }
+            for (vj_id, service_id) in [("vj1", "c3"), ("vj2", "c2"), ("vj3", "c4")] {
+                collections
+                    .vehicle_journeys
+                    .push(VehicleJourney {
+                        id: String::from(vj_id),
+                        service_id: String::from(service_id),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            collections
+                .timeframes
+                .push(Timeframe {
+                    timeframe_group_id: String::from("tf"),
+                    start_time: None,
+                    end_time: None,
+                    service_id: String::from("c4"),
+                })
+                .unwrap();
+
+            let deduplicated_ids = collections.deduplicate_calendars();
+
+            let expected: HashMap<String, String> = vec![
+                (String::from("c3"), String::from("c1")),
+                (String::from("c4"), String::from("c1")),
+            ]
+            .into_iter()
+            .collect();
+            assert_eq!(expected, deduplicated_ids);
+            let mut calendar_ids: Vec<&str> = collections
+                .calendars
+                .values()
+                .map(|c| c.id.as_str())
+                .collect();
+            calendar_ids.sort_unstable();
+            assert_eq!(vec!["c1", "c2"], calendar_ids);
+            let service_ids: Vec<&str> = collections
+                .vehicle_journeys
+                .values()
+                .map(|vj| vj.service_id.as_str())
+                .collect();
+            assert_eq!(vec!["c1", "c2", "c1"], service_ids);
+            assert_eq!(
+                "c1",
+                collections.timeframes.values().next().unwrap().service_id
+            );
+        }
+    }
+
+    mod calendar_deduplication {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn enhance() {
+            let mut collections = Collections::default();
+
+            let mut service_1 = Calendar::new(String::from("service_1"));
+            service_1
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 1).unwrap());
+            service_1
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 2).unwrap());
+            service_1
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 3).unwrap());
+            service_1
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 10).unwrap());
+            collections.calendars.push(service_1).unwrap();
+
+            let mut service_2 = Calendar::new(String::from("service_2"));
+            service_2
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 1).unwrap());
+            service_2
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 2).unwrap());
+            service_2
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 3).unwrap());
+            service_2
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 10).unwrap());
+            collections.calendars.push(service_2).unwrap();
+
+            let mut service_3 = Calendar::new(String::from("service_3"));
+            service_3
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 1).unwrap());
+            service_3
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 3).unwrap());
+            service_3
+                .dates
+                .insert(NaiveDate::from_ymd_opt(2019, 10, 10).unwrap());
+            collections.calendars.push(service_3).unwrap();
+
+            collections
+                .vehicle_journeys
+                .push(VehicleJourney {
+                    id: String::from("vehicle_journey_id_1"),
+                    service_id: String::from("service_1"),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            collections
+                .vehicle_journeys
+                .push(VehicleJourney {
+                    id: String::from("vehicle_journey_id_2"),
+                    service_id: String::from("service_2"),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            collections
+                .vehicle_journeys
+                .push(VehicleJourney {
+                    id: String::from("vehicle_journey_id_3"),
+                    service_id: String::from("service_3"),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            collections.calendar_deduplication();
+
+            let vehicle_journey = collections
+                .vehicle_journeys
+                .get("vehicle_journey_id_2")
+                .unwrap();
+            assert_eq!("service_1", vehicle_journey.service_id);
+
+            let vehicle_journey = collections
+                .vehicle_journeys
+                .get("vehicle_journey_id_3")
+                .unwrap();
+            assert_eq!("service_3", vehicle_journey.service_id);
+
+            let calendar = collections.calendars.get("service_2");
+            assert_eq!(None, calendar);
+        }
+
+        #[test]
+        fn keep_calendars_of_timeframes() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = Collections::default();
+            for (service_id, days) in [("c1", vec![1, 2]), ("c2", vec![1, 2]), ("c3", vec![3])] {
+                let mut calendar = Calendar::new(String::from(service_id));
+                calendar.dates.extend(days.into_iter().map(date));
+                collections.calendars.push(calendar).unwrap();
+            }
+            collections
+                .vehicle_journeys
+                .push(VehicleJourney {
+                    id: String::from("vj1"),
+                    service_id: String::from("c1"),
+                    ..Default::default()
+                })
+                .unwrap();
+            collections
+                .timeframes
+                .push(Timeframe {
+                    timeframe_group_id: String::from("tf"),
+                    start_time: None,
+                    end_time: None,
+                    service_id: String::from("c2"),
+                })
+                .unwrap();
+
+            // c3 is unused, c2 is only used by the timeframe
+            collections.calendar_deduplication();
+
+            assert_eq!(
+                vec!["c1"],
+                collections
+                    .calendars
+                    .values()
+                    .map(|c| c.id.as_str())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                "c1",
+                collections.timeframes.values().next().unwrap().service_id
+            );
+        }
+    }
+
+    mod build_service_date_index {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn lookup_services_by_date() {
+            let mut collections = Collections::default();
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut service_2 = Calendar::new(String::from("service_2"));
+            service_2.dates.extend([date(2), date(3)]);
+            collections.calendars.push(service_2).unwrap();
+            let mut service_1 = Calendar::new(String::from("service_1"));
+            service_1.dates.extend([date(1), date(2)]);
+            collections.calendars.push(service_1).unwrap();
+
+            let index = collections.build_service_date_index();
+
+            assert_eq!(vec!["service_1"], index.services_on(date(1)));
+            assert_eq!(vec!["service_1", "service_2"], index.services_on(date(2)));
+            assert!(index.services_on(date(4)).is_empty());
+            assert_eq!(
+                vec![date(2), date(3)],
+                index
+                    .services_between(date(2), date(10))
+                    .map(|(date, _)| *date)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    mod monthly_service_coverage {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn count_service_dates_by_month() {
+            let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+            let mut collections = Collections::default();
+            let mut service_1 = Calendar::new(String::from("service_1"));
+            service_1.dates.extend([date(1, 1), date(1, 2), date(2, 1)]);
+            collections.calendars.push(service_1).unwrap();
+            let mut service_2 = Calendar::new(String::from("service_2"));
+            service_2.dates.extend([date(1, 1)]);
+            collections.calendars.push(service_2).unwrap();
+
+            let coverage = collections.monthly_service_coverage();
+
+            assert_eq!(
+                vec![((2020, 1), 3), ((2020, 2), 1)],
+                coverage.into_iter().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    mod active_dates {
+        use super::*;
+
+        #[test]
+        fn dates_of_known_and_unknown_services() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = Collections::default();
+            let mut service = Calendar::new(String::from("service"));
+            service.dates.insert(date(1));
+            collections.calendars.push(service).unwrap();
+            collections
+                .calendars
+                .push(Calendar::new(String::from("never")))
+                .unwrap();
+
+            assert_eq!(
+                Some(1),
+                collections.active_dates("service").map(BTreeSet::len)
+            );
+            assert_eq!(
+                Some(0),
+                collections.active_dates("never").map(BTreeSet::len)
+            );
+            assert_eq!(None, collections.active_dates("unknown"));
+            assert!(collections.is_active_on("service", date(1)));
+            assert!(!collections.is_active_on("service", date(2)));
+            assert!(!collections.is_active_on("unknown", date(1)));
+        }
+    }
+
+    mod audit_calendars {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn report_calendar_issues() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = Collections::default();
+            collections
+                .datasets
+                .push(Dataset {
+                    id: String::from("dataset"),
+                    start_date: date(1),
+                    end_date: date(10),
+                    ..Default::default()
+                })
+                .unwrap();
+            let mut used = Calendar::new(String::from("used"));
+            used.dates.extend([date(2), date(12)]);
+            collections.calendars.push(used).unwrap();
+            collections
+                .calendars
+                .push(Calendar::new(String::from("empty")))
+                .unwrap();
+            let mut inverted = Calendar::new(String::from("inverted"));
+            inverted.dates.insert(date(3));
+            inverted.metadata_mut().declared_period = Some(ValidityPeriod {
+                start_date: date(5),
+                end_date: date(1),
+            });
+            collections.calendars.push(inverted).unwrap();
+            for (vj_id, service_id) in [("vj1", "used"), ("vj2", "unknown")] {
+                collections
+                    .vehicle_journeys
+                    .push(VehicleJourney {
+                        id: String::from(vj_id),
+                        service_id: String::from(service_id),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+
+            assert_eq!(
+                CalendarAudit {
+                    unreferenced_services: vec![String::from("empty"), String::from("inverted")],
+                    trips_with_missing_service: vec![(
+                        String::from("vj2"),
+                        String::from("unknown")
+                    )],
+                    empty_calendars: vec![String::from("empty")],
+                    inverted_windows: vec![String::from("inverted")],
+                    out_of_feed_window_dates: vec![(String::from("used"), vec![date(12)])],
+                },
+                collections.audit_calendars()
+            );
+        }
+    }
+
+    mod has_weekend_service {
+        use super::*;
+
+        #[test]
+        fn detect_weekend_dates() {
+            // 2020-01-03 is a Friday
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = Collections::default();
+            assert!(!collections.has_weekend_service());
+            let mut weekdays = Calendar::new(String::from("weekdays"));
+            weekdays.dates.extend([date(2), date(3), date(6)]);
+            collections.calendars.push(weekdays).unwrap();
+            assert!(!collections.has_weekend_service());
+            let mut sundays = Calendar::new(String::from("sundays"));
+            sundays.dates.extend([date(5)]);
+            collections.calendars.push(sundays).unwrap();
+            assert!(collections.has_weekend_service());
+        }
+    }
+
+    mod service_minutes_per_date {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn sum_journey_spans() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let model = crate::ModelBuilder::default()
+                .calendar("c1", &[date(1), date(2)])
+                .calendar("c2", &[date(2)])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.calendar("c2").st("A", "23:30:00").st("B", "24:15:00");
+                })
+                .build();
+
+            let minutes = model.service_minutes_per_date();
+
+            assert_eq!(Some(&chrono::Duration::minutes(60)), minutes.get(&date(1)));
+            assert_eq!(Some(&chrono::Duration::minutes(105)), minutes.get(&date(2)));
+        }
+    }
+
+    mod restrict_calendars {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn keep_dates_in_range_and_purge() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = crate::ModelBuilder::new("2020-01-01", "2020-01-10")
+                .calendar("c1", &[date(1), date(3), date(5), date(7)])
+                .calendar("c2", &[date(2)])
+                .vj("vj1", |vj| {
+                    vj.route("r1")
+                        .calendar("c1")
+                        .st("A", "10:00:00")
+                        .st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.route("r2")
+                        .calendar("c2")
+                        .st("A", "10:00:00")
+                        .st("C", "11:00:00");
+                })
+                .build()
+                .into_collections();
+
+            assert!(collections.restrict_calendars(date(5), date(3)).is_err());
+            collections.restrict_calendars(date(3), date(5)).unwrap();
+
+            assert_eq!(
+                vec![date(3), date(5)],
+                collections
+                    .calendars
+                    .get("c1")
+                    .unwrap()
+                    .dates
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            );
+            assert!(collections.calendars.get("c2").is_none());
+            assert!(collections.vehicle_journeys.get("vj2").is_none());
+            assert!(collections.routes.get("r2").is_none());
+            assert!(collections.stop_points.get("C").is_none());
+            assert!(collections.routes.get("r1").is_some());
+        }
+
+        #[test]
+        fn keep_overnight_trips_on_their_service_day() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = crate::ModelBuilder::new("2020-01-01", "2020-01-10")
+                .calendar("c1", &[date(5), date(6)])
+                .calendar("c2", &[date(2)])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1")
+                        .st("A", "23:50:00")
+                        .st("B", "24:30:00")
+                        .st("C", "25:10:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.calendar("c2")
+                        .st("A", "23:50:00")
+                        .st("B", "24:30:00")
+                        .st("C", "25:10:00");
+                })
+                .build()
+                .into_collections();
+
+            collections.restrict_calendars(date(3), date(5)).unwrap();
+
+            // vj1 starts on the last kept date, its stop times of the 6th are kept
+            let vj = collections.vehicle_journeys.get("vj1").unwrap();
+            assert_eq!(
+                vec![
+                    Time::new(23, 50, 0),
+                    Time::new(24, 30, 0),
+                    Time::new(25, 10, 0)
+                ],
+                vj.stop_times
+                    .iter()
+                    .map(|st| st.departure_time)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                vec![date(5)],
+                collections
+                    .calendars
+                    .get("c1")
+                    .unwrap()
+                    .dates
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            );
+            // vj2 starts before the period, only its overnight part runs on the 3rd
+            assert!(collections.vehicle_journeys.get("vj2").is_none());
+            assert!(collections.calendars.get("c2").is_none());
+        }
+    }
+
+    mod apply_calendar_dates {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn apply_twice() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = Collections::default();
+            let mut calendar = Calendar::new("c1".to_string());
+            calendar.dates.extend([date(1), date(2)]);
+            collections.calendars.push(calendar).unwrap();
+            let delta = "service_id,date,exception_type\n\
+                         c1,20200101,2\n\
+                         c1,20200103,1\n\
+                         c2,20200103,1";
+
+            let stats = collections.apply_calendar_dates(delta.as_bytes()).unwrap();
+            assert_eq!(
+                (2, 1, 1),
+                (
+                    stats.dates_added,
+                    stats.dates_removed,
+                    stats.services_from_dates_only
+                )
+            );
+            let calendars_after_first_delta: Vec<Calendar> =
+                collections.calendars.values().cloned().collect();
+            assert_eq!(
+                vec![date(2), date(3)],
+                collections
+                    .calendars
+                    .get("c1")
+                    .unwrap()
+                    .dates
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>()
+            );
+
+            let stats = collections.apply_calendar_dates(delta.as_bytes()).unwrap();
+            assert_eq!(
+                (0, 0, 0),
+                (
+                    stats.dates_added,
+                    stats.dates_removed,
+                    stats.services_from_dates_only
+                )
+            );
+            assert_eq!(
+                calendars_after_first_delta,
+                collections.calendars.into_vec()
+            );
+        }
+    }
+
+    mod propagate_accessibility {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn between_stop_areas_and_stop_points() {
+            let equipment = |id: &str, wheelchair_boarding| Equipment {
+                id: id.to_string(),
+                wheelchair_boarding,
+                ..Default::default()
+            };
+            let stop_area = |id: &str, equipment_id: Option<&str>| StopArea {
+                id: id.to_string(),
+                equipment_id: equipment_id.map(str::to_string),
+                ..Default::default()
+            };
+            let stop_point = |id: &str, stop_area_id: &str, equipment_id: Option<&str>| StopPoint {
+                id: id.to_string(),
+                stop_area_id: stop_area_id.to_string(),
+                equipment_id: equipment_id.map(str::to_string),
+                ..Default::default()
+            };
+            let mut collections = Collections {
+                equipments: CollectionWithId::new(vec![
+                    equipment("accessible", Availability::Available),
+                    equipment("not_accessible", Availability::NotAvailable),
+                ])
+                .unwrap(),
+                stop_areas: CollectionWithId::new(vec![
+                    stop_area("A", Some("accessible")),
+                    stop_area("B", None),
+                    stop_area("C", None),
+                    stop_area("D", Some("accessible")),
+                ])
+                .unwrap(),
+                stop_points: CollectionWithId::new(vec![
+                    stop_point("A1", "A", None),
+                    stop_point("A2", "A", Some("not_accessible")),
+                    stop_point("B1", "B", Some("accessible")),
+                    stop_point("B2", "B", Some("accessible")),
+                    stop_point("C1", "C", None),
+                    stop_point("C2", "C", Some("accessible")),
+                    stop_point("D1", "D", Some("accessible")),
+                    stop_point("D2", "D", Some("not_accessible")),
+                ])
+                .unwrap(),
+                ..Default::default()
+            };
+
+            collections.propagate_accessibility();
+
+            let stop_point_equipment = |id: &str| {
+                collections
+                    .stop_points
+                    .get(id)
+                    .unwrap()
+                    .equipment_id
+                    .as_deref()
+            };
+            assert_eq!(Some("accessible"), stop_point_equipment("A1"));
+            assert_eq!(None, stop_point_equipment("C1"));
+            let stop_area_equipment = |id: &str| {
+                collections
+                    .stop_areas
+                    .get(id)
+                    .unwrap()
+                    .equipment_id
+                    .as_deref()
+            };
+            // Not computed from the accessibility inherited by A1
+            assert_eq!(Some("accessible"), stop_area_equipment("A"));
+            assert_eq!(Some("accessible"), stop_area_equipment("B"));
+            assert_eq!(None, stop_area_equipment("C"));
+            // Conflicting stop points
+            assert_eq!(None, stop_area_equipment("D"));
+            assert_eq!(2, collections.equipments.len());
+        }
+    }
+
+    mod analyze_trip_patterns {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn group_by_stops() {
+            let collections = ModelBuilder::default()
+                .vj("vj3", |vj| {
+                    vj.st("A", "10:00:00").st("B", "10:10:00");
+                })
+                .vj("vj1", |vj| {
+                    vj.st("A", "08:00:00").st("B", "08:15:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.st("B", "09:00:00").st("A", "09:10:00");
+                })
+                .vj("vj4", |vj| {
+                    vj.st_mut("A", "11:00:00", "11:00:00", 1, 0, None, |_st| {})
+                        .st("B", "11:10:00");
+                })
+                .build()
+                .into_collections();
+            let patterns = collections.analyze_trip_patterns();
+
+            assert_eq!(
+                vec![
+                    (vec!["A", "B"], vec!["vj1", "vj3"]),
+                    (vec!["B", "A"], vec!["vj2"]),
+                    (vec!["A", "B"], vec!["vj4"]),
+                ],
+                patterns
+                    .iter()
+                    .map(|pattern| (
+                        pattern
+                            .stops
+                            .iter()
+                            .map(|stop| stop.stop_point_id.as_str())
+                            .collect::<Vec<_>>(),
+                        pattern
+                            .vehicle_journey_ids
+                            .iter()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(0, patterns[0].stops[0].pickup_type);
+            assert_eq!(1, patterns[2].stops[0].pickup_type);
+        }
+    }
+
+    mod group_calendars_by_profile {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn group_by_operating_days() {
+            let collections = ModelBuilder::default()
+                // Mondays
+                .calendar("c2", &["2020-01-06", "2020-01-13", "2020-01-20"])
+                // Mondays and Tuesdays
+                .calendar(
+                    "c3",
+                    &[
+                        "2020-01-06",
+                        "2020-01-07",
+                        "2020-01-13",
+                        "2020-01-14",
+                        "2020-01-20",
+                        "2020-01-21",
+                    ],
+                )
+                // Mondays
+                .calendar("c1", &["2020-01-06", "2020-01-13", "2020-01-20"])
+                .calendar("c4", &[] as &[&str])
+                .build()
+                .into_collections();
+            let profiles = collections.group_calendars_by_profile();
+
+            assert_eq!(
+                vec![
+                    (vec![], vec!["c4"]),
+                    (vec![Weekday::Mon], vec!["c1", "c2"]),
+                    (vec![Weekday::Mon, Weekday::Tue], vec!["c3"]),
+                ],
+                profiles
+                    .iter()
+                    .map(|(profile, service_ids)| (
+                        profile.0.clone(),
+                        service_ids.iter().map(String::as_str).collect::<Vec<_>>()
+                    ))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    mod filter_by_bounding_box {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn keep_stops_inside_the_box() {
+            let mut collections = crate::ModelBuilder::default()
+                .calendar("c1", &["2020-01-01"])
+                .calendar("c2", &["2020-01-02"])
+                .vj("vj1", |vj| {
+                    vj.route("r1")
+                        .calendar("c1")
+                        .st("A", "10:00:00")
+                        .st("B", "11:00:00")
+                        .st("C", "12:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.route("r2")
+                        .calendar("c2")
+                        .st("B", "10:00:00")
+                        .st("C", "11:00:00");
+                })
+                .build()
+                .into_collections();
+            for (stop_point_id, lon, lat) in [("A", 0.5, 0.5), ("B", 1.0, 0.0), ("C", 5.0, 5.0)] {
+                collections
+                    .stop_points
+                    .get_mut(stop_point_id)
+                    .unwrap()
+                    .coord = Coord { lon, lat };
+            }
+
+            collections
+                .filter_by_bounding_box(0.0, 0.0, 1.0, 1.0)
+                .unwrap();
+
+            let vj = collections.vehicle_journeys.get("vj1").unwrap();
+            let stop_point_ids: Vec<&str> = vj
+                .stop_times
+                .iter()
+                .map(|st| collections.stop_points[st.stop_point_idx].id.as_str())
+                .collect();
+            assert_eq!(vec!["A", "B"], stop_point_ids);
+            assert!(collections.stop_points.get("C").is_none());
+            assert!(collections.vehicle_journeys.get("vj2").is_none());
+            assert!(collections.calendars.get("c2").is_none());
+            assert!(collections.routes.get("r2").is_none());
+            assert!(collections.routes.get("r1").is_some());
+        }
+    }
+
+    mod retain_lines {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        fn collections() -> Collections {
+            ModelBuilder::default()
+                .vj("vj1", |vj| {
+                    vj.network("n1")
+                        .line("l1")
+                        .route("r1")
+                        .st("A", "10:00:00")
+                        .st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.network("n1")
+                        .line("l2")
+                        .route("r2")
+                        .st("B", "10:00:00")
+                        .st("C", "11:00:00");
+                })
+                .vj("vj3", |vj| {
+                    vj.network("n2")
+                        .line("l3")
+                        .route("r3")
+                        .st("C", "10:00:00")
+                        .st("D", "11:00:00");
+                })
+                .build()
+                .into_collections()
+        }
+
+        fn ids(ids: &[&str]) -> HashSet<String> {
+            ids.iter().map(|id| id.to_string()).collect()
+        }
+
+        #[test]
+        fn keep_selected_lines() {
+            let mut collections = collections();
+            collections.retain_lines(&ids(&["l1", "unknown"])).unwrap();
+            assert_eq!(1, collections.vehicle_journeys.len());
+            assert!(collections.vehicle_journeys.contains_id("vj1"));
+            assert_eq!(1, collections.routes.len());
+            assert_eq!(1, collections.lines.len());
+            assert_eq!(1, collections.networks.len());
+            assert!(collections.networks.contains_id("n1"));
+            assert_eq!(2, collections.stop_points.len());
+            assert!(!collections.stop_points.contains_id("C"));
+        }
+
+        #[test]
+        fn keep_selected_networks() {
+            let mut collections = collections();
+            collections.retain_networks(&ids(&["n1"])).unwrap();
+            assert_eq!(2, collections.vehicle_journeys.len());
+            assert!(!collections.vehicle_journeys.contains_id("vj3"));
+            assert_eq!(2, collections.lines.len());
+            assert!(!collections.networks.contains_id("n2"));
+            assert!(!collections.stop_points.contains_id("D"));
+        }
+
+        #[test]
+        fn ignore_unknown_ids() {
+            let mut collections = collections();
+            collections.retain_networks(&ids(&["unknown"])).unwrap();
+            assert_eq!(3, collections.vehicle_journeys.len());
+            assert_eq!(2, collections.networks.len());
+
+            collections.retain_lines(&ids(&["unknown"])).unwrap();
+            assert_eq!(3, collections.vehicle_journeys.len());
+            assert_eq!(3, collections.lines.len());
+            assert_eq!(4, collections.stop_points.len());
+        }
+    }
+
+    mod drop_dates_before {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn drop_past_dates_and_emptied_services() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = crate::ModelBuilder::new("2020-01-01", "2020-01-10")
+                .calendar("c1", &[date(1), date(5)])
+                .calendar("c2", &[date(2)])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "11:00:00");
+                })
+                .vj("vj2", |vj| {
+                    vj.calendar("c2").st("A", "10:00:00").st("C", "11:00:00");
+                })
+                .build()
+                .into_collections();
+
+            collections.drop_dates_before(date(3)).unwrap();
+
+            assert_eq!(
+                vec![date(5)],
+                collections
+                    .calendars
+                    .get("c1")
+                    .unwrap()
+                    .dates
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            );
+            assert!(collections.calendars.get("c2").is_none());
+            assert!(collections.vehicle_journeys.get("vj2").is_none());
+            assert!(collections.stop_points.get("C").is_none());
+        }
+    }
+
+    mod expand_frequencies {
+        use super::*;
+        use crate::ModelBuilder;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn duplicate_translations() {
+            let mut collections = ModelBuilder::default()
+                .calendar("c1", &["2020-01-01"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "10:10:00");
+                })
+                .build()
+                .into_collections();
+            collections.translations.push(Translation {
+                object_type: ObjectType::VehicleJourney,
+                object_id: String::from("vj1"),
+                field_name: String::from("headsign"),
+                language: String::from("fr"),
+                translation: String::from("girouette"),
+            });
+            let frequency = Frequency {
+                vehicle_journey_id: String::from("vj1"),
+                start_time: Time::new(10, 0, 0),
+                end_time: Time::new(11, 0, 0),
+                headway_secs: 1800,
+            };
+
+            collections
+                .expand_frequencies(vec![(frequency, true)])
                 .unwrap();
-            assert_eq!("Headsign", vehicle_journey.headsign.as_ref().unwrap());
+
+            let translated_ids: Vec<&str> = collections
+                .translations
+                .values()
+                .map(|translation| translation.object_id.as_str())
+                .collect();
+            assert_eq!(vec!["vj1-0", "vj1-1"], translated_ids);
+        }
+
+        #[test]
+        fn existing_attribution_id_leaves_collections_unchanged() {
+            let mut collections = ModelBuilder::default()
+                .calendar("c1", &["2020-01-01"])
+                .vj("vj1", |vj| {
+                    vj.calendar("c1").st("A", "10:00:00").st("B", "10:10:00");
+                })
+                .build()
+                .into_collections();
+            // the attribution of the second generated vehicle journey would
+            // be "attribution-1", already used by another attribution
+            for (attribution_id, vj_id) in [("attribution", Some("vj1")), ("attribution-1", None)] {
+                collections
+                    .attributions
+                    .push(Attribution {
+                        id: String::from(attribution_id),
+                        vehicle_journey_id: vj_id.map(String::from),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            let frequency = Frequency {
+                vehicle_journey_id: String::from("vj1"),
+                start_time: Time::new(10, 0, 0),
+                end_time: Time::new(11, 0, 0),
+                headway_secs: 1800,
+            };
+
+            assert!(collections
+                .expand_frequencies(vec![(frequency, true)])
+                .is_err());
+            assert_eq!(2, collections.attributions.len());
+            assert!(collections.vehicle_journeys.contains_id("vj1"));
+            assert_eq!(1, collections.vehicle_journeys.len());
         }
     }
 
-    mod calendar_deduplication {
+    mod split_calendars_at {
         use super::*;
+        use crate::ModelBuilder;
         use pretty_assertions::assert_eq;
 
         #[test]
-        fn enhance() {
+        fn split_at_cutoff() {
             let mut collections = Collections::default();
-
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
             let mut service_1 = Calendar::new(String::from("service_1"));
-            service_1
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 1).unwrap());
-            service_1
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 2).unwrap());
-            service_1
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 3).unwrap());
-            service_1
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 10).unwrap());
+            service_1.dates.extend([date(1), date(2), date(3)]);
+            service_1.metadata_mut().declared_period = Some(ValidityPeriod {
+                start_date: date(1),
+                end_date: date(5),
+            });
             collections.calendars.push(service_1).unwrap();
-
             let mut service_2 = Calendar::new(String::from("service_2"));
-            service_2
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 1).unwrap());
-            service_2
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 2).unwrap());
-            service_2
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 3).unwrap());
-            service_2
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 10).unwrap());
+            service_2.dates.extend([date(3), date(4)]);
             collections.calendars.push(service_2).unwrap();
-
-            let mut service_3 = Calendar::new(String::from("service_3"));
-            service_3
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 1).unwrap());
-            service_3
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 3).unwrap());
-            service_3
-                .dates
-                .insert(NaiveDate::from_ymd_opt(2019, 10, 10).unwrap());
-            collections.calendars.push(service_3).unwrap();
-
-            collections
-                .vehicle_journeys
-                .push(VehicleJourney {
-                    id: String::from("vehicle_journey_id_1"),
-                    service_id: String::from("service_1"),
+            let stop_point_idx = collections
+                .stop_points
+                .push(StopPoint {
+                    id: String::from("stop_point_id"),
                     ..Default::default()
                 })
                 .unwrap();
-
+            let stop_time = StopTime {
+                stop_point_idx,
+                sequence: 0,
+                arrival_time: Some(Time::new(0, 0, 0)),
+                departure_time: Some(Time::new(0, 0, 0)),
+                start_pickup_drop_off_window: None,
+                end_pickup_drop_off_window: None,
+                boarding_duration: 0,
+                alighting_duration: 0,
+                pickup_type: 0,
+                drop_off_type: 0,
+                local_zone_id: None,
+                precision: None,
+            };
             collections
                 .vehicle_journeys
                 .push(VehicleJourney {
-                    id: String::from("vehicle_journey_id_2"),
-                    service_id: String::from("service_2"),
+                    id: String::from("vj_1"),
+                    service_id: String::from("service_1"),
+                    stop_times: vec![stop_time],
                     ..Default::default()
                 })
                 .unwrap();
-
             collections
-                .vehicle_journeys
-                .push(VehicleJourney {
-                    id: String::from("vehicle_journey_id_3"),
-                    service_id: String::from("service_3"),
+                .stop_time_headsigns
+                .insert((String::from("vj_1"), 0), String::from("headsign"));
+            collections
+                .stop_time_ids
+                .insert((String::from("vj_1"), 0), String::from("st_1"));
+            collections
+                .attributions
+                .push(Attribution {
+                    id: String::from("attribution_1"),
+                    vehicle_journey_id: Some(String::from("vj_1")),
                     ..Default::default()
                 })
                 .unwrap();
+            collections.translations.push(Translation {
+                object_type: ObjectType::StopTime,
+                object_id: String::from("st_1"),
+                field_name: String::from("stop_headsign"),
+                language: String::from("fr"),
+                translation: String::from("girouette"),
+            });
+            collections.timeframes.push(Timeframe {
+                timeframe_group_id: String::from("peak"),
+                start_time: None,
+                end_time: None,
+                service_id: String::from("service_1"),
+            });
 
-            collections.calendar_deduplication();
+            let mapping = collections.split_calendars_at(date(2)).unwrap();
 
-            let vehicle_journey = collections
-                .vehicle_journeys
-                .get("vehicle_journey_id_2")
-                .unwrap();
-            assert_eq!("service_1", vehicle_journey.service_id);
+            assert_eq!(
+                (
+                    String::from("service_1:before"),
+                    String::from("service_1:after")
+                ),
+                mapping["service_1"]
+            );
+            assert_eq!(1, mapping.len());
+            let before = collections.calendars.get("service_1:before").unwrap();
+            assert_eq!(
+                vec![date(1)],
+                before.dates.iter().cloned().collect::<Vec<_>>()
+            );
+            let after = collections.calendars.get("service_1:after").unwrap();
+            assert_eq!(
+                vec![date(2), date(3)],
+                after.dates.iter().cloned().collect::<Vec<_>>()
+            );
+            assert!(collections.calendars.get("service_2").is_some());
+            assert_eq!(
+                "service_1:before",
+                collections.vehicle_journeys.get("vj_1").unwrap().service_id
+            );
+            assert_eq!(
+                "service_1:after",
+                collections
+                    .vehicle_journeys
+                    .get("vj_1:after")
+                    .unwrap()
+                    .service_id
+            );
+            assert_eq!(
+                "headsign",
+                collections.stop_time_headsigns[&(String::from("vj_1:after"), 0)]
+            );
+            assert_eq!(
+                "st_1:after",
+                collections.stop_time_ids[&(String::from("vj_1:after"), 0)]
+            );
+            assert_eq!(
+                Some("vj_1:after"),
+                collections
+                    .attributions
+                    .get("attribution_1:after")
+                    .unwrap()
+                    .vehicle_journey_id
+                    .as_deref()
+            );
+            let translated_stop_time_ids: Vec<&str> = collections
+                .translations
+                .values()
+                .map(|translation| translation.object_id.as_str())
+                .collect();
+            assert_eq!(vec!["st_1", "st_1:after"], translated_stop_time_ids);
+            let timeframe_service_ids: Vec<&str> = collections
+                .timeframes
+                .values()
+                .map(|timeframe| timeframe.service_id.as_str())
+                .collect();
+            assert_eq!(
+                vec!["service_1:before", "service_1:after"],
+                timeframe_service_ids
+            );
+            assert_eq!(
+                Some(&ValidityPeriod {
+                    start_date: date(1),
+                    end_date: date(1),
+                }),
+                before.declared_period()
+            );
+            assert_eq!(
+                Some(&ValidityPeriod {
+                    start_date: date(2),
+                    end_date: date(5),
+                }),
+                after.declared_period()
+            );
+        }
 
-            let vehicle_journey = collections
-                .vehicle_journeys
-                .get("vehicle_journey_id_3")
-                .unwrap();
-            assert_eq!("service_3", vehicle_journey.service_id);
+        #[test]
+        fn existing_id_leaves_collections_unchanged() {
+            let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+            let mut collections = ModelBuilder::default()
+                .calendar("service_1", &["2020-01-01", "2020-01-02"])
+                .vj("vj_1", |vj| {
+                    vj.calendar("service_1")
+                        .st("A", "10:00:00")
+                        .st("B", "11:00:00");
+                })
+                .vj("vj_1:after", |vj| {
+                    vj.calendar("service_1")
+                        .st("A", "12:00:00")
+                        .st("B", "13:00:00");
+                })
+                .build()
+                .into_collections();
 
-            let calendar = collections.calendars.get("service_2");
-            assert_eq!(None, calendar);
+            assert!(collections.split_calendars_at(date(2)).is_err());
+            assert!(collections.calendars.contains_id("service_1"));
+            assert!(!collections.calendars.contains_id("service_1:before"));
+            assert_eq!(
+                "service_1",
+                collections.vehicle_journeys.get("vj_1").unwrap().service_id
+            );
+            assert_eq!(2, collections.vehicle_journeys.len());
         }
     }
 
@@ -23,7 +23,7 @@ use crate::{
     objects::{Date, Line, Network},
     Result,
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use chrono::prelude::*;
 use minidom::{Element, Node};
 use minidom_writer::ElementWriter;
@@ -153,6 +153,12 @@ impl<'a> Exporter<'a> {
     where
         P: AsRef<Path>,
     {
+        if let Some(epsg) = self.model.stops_epsg {
+            bail!(
+                "Cannot write NeTEx France with stops reprojected to EPSG:{}, longitudes and latitudes are expected",
+                epsg
+            );
+        }
         std::fs::create_dir_all(&path)?;
         self.write_lines(&path)?;
         self.write_stops(&path)?;
@@ -798,6 +798,7 @@ mod tests {
             dates: vec![Date::from_ymd_opt(2020, 1, 1).unwrap()]
                 .into_iter()
                 .collect(),
+            ..Default::default()
         });
         collections.vehicle_journeys = CollectionWithId::from(VehicleJourney {
             id: String::from("vj_id_1"),
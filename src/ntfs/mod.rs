@@ -19,7 +19,7 @@ mod read;
 mod write;
 
 use crate::{
-    calendars::{manage_calendars, write_calendar_dates},
+    calendars::{self, write_calendar_files, ReadConfiguration, WriteConfiguration},
     file_handler::{FileHandler, PathFileHandler, ZipHandler},
     model::{Collections, Model},
     objects::*,
@@ -27,14 +27,13 @@ use crate::{
     utils::*,
     Result,
 };
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use chrono::{DateTime, FixedOffset};
 use chrono_tz::Tz;
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
-use std::path;
-use tempfile::tempdir;
-use tracing::info;
+use std::{io, path};
+use tracing::{info, warn};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct StopTime {
@@ -205,7 +204,7 @@ pub fn from_zip<P: AsRef<path::Path>>(p: P) -> Result<Model> {
 pub fn collections_from_zip<P: AsRef<path::Path>>(p: P) -> Result<Collections> {
     let reader = std::fs::File::open(p.as_ref())?;
     let mut file_handler = ZipHandler::new(reader, p)?;
-    read_collections_file_handler(&mut file_handler)
+    read_collections_file_handler(&mut file_handler, &ReadConfiguration::default())
 }
 
 /// Imports `Collections` from the
@@ -213,7 +212,7 @@ pub fn collections_from_zip<P: AsRef<path::Path>>(p: P) -> Result<Collections> {
 /// files in the given directory.
 pub fn collections_from_dir<P: AsRef<path::Path>>(p: P) -> Result<Collections> {
     let mut file_handle = PathFileHandler::new(p.as_ref().to_path_buf());
-    read_collections_file_handler(&mut file_handle)
+    read_collections_file_handler(&mut file_handle, &ReadConfiguration::default())
 }
 
 /// Imports a `Model` from an object implementing `Read` and `Seek` and containing a zip file with a
@@ -284,18 +283,64 @@ pub fn read_collections<P: AsRef<path::Path>>(path: P) -> Result<Collections> {
     }
 }
 
+/// Imports `Collections` from the
+/// [NTFS](https://github.com/hove-io/ntfs-specification/blob/master/ntfs_fr.md)
+/// files in the given directory, checking that all the references between
+/// the objects are resolved (see [`Collections::check_referential_integrity`]).
+///
+/// A missing required file is an error, the missing optional files are
+/// skipped, as with [collections_from_dir].
+pub fn read_ntfs<P: AsRef<path::Path>>(path: P) -> Result<Collections> {
+    read_ntfs_with_configuration(path, &ReadConfiguration::default())
+}
+
+/// Same as [read_ntfs], calendar.txt and calendar_dates.txt being read with
+/// the given options, for example
+/// [`ReadConfiguration::keep_declared_days`] to write back the calendar.txt
+/// rows of the unchanged services.
+pub fn read_ntfs_with_configuration<P: AsRef<path::Path>>(
+    path: P,
+    calendar_configuration: &ReadConfiguration,
+) -> Result<Collections> {
+    let p = path.as_ref();
+    if !p.is_dir() {
+        bail!(
+            "file {:?} is not a directory, cannot read a ntfs from it",
+            p
+        );
+    }
+    let mut file_handle = PathFileHandler::new(p.to_path_buf());
+    let collections = read_collections_file_handler(&mut file_handle, calendar_configuration)
+        .with_context(|| format!("impossible to read ntfs directory from {:?}", p))?;
+    if let Err(errors) = collections.check_referential_integrity() {
+        for error in &errors {
+            warn!("{}", error);
+        }
+        bail!(
+            "the ntfs directory {:?} has {} unresolved references, the first one: {}",
+            p,
+            errors.len(),
+            errors[0]
+        );
+    }
+    Ok(collections)
+}
+
 fn read_file_handler<H>(file_handler: &mut H) -> Result<Model>
 where
     for<'a> &'a mut H: FileHandler,
 {
-    let collections = read_collections_file_handler(file_handler)?;
+    let collections = read_collections_file_handler(file_handler, &ReadConfiguration::default())?;
     info!("Indexing");
     let res = Model::new(collections)?;
     info!("Loading NTFS done");
     Ok(res)
 }
 
-fn read_collections_file_handler<H>(file_handler: &mut H) -> Result<Collections>
+fn read_collections_file_handler<H>(
+    file_handler: &mut H,
+    calendar_configuration: &ReadConfiguration,
+) -> Result<Collections>
 where
     for<'a> &'a mut H: FileHandler,
 {
@@ -321,6 +366,7 @@ where
         ticket_use_perimeters: make_opt_collection(file_handler, "ticket_use_perimeters.txt")?,
         ticket_use_restrictions: make_opt_collection(file_handler, "ticket_use_restrictions.txt")?,
         levels: make_opt_collection_with_id(file_handler, "levels.txt")?,
+        attributions: make_opt_collection_with_id(file_handler, "attributions.txt")?,
         grid_calendars: make_opt_collection_with_id(file_handler, "grid_calendars.txt")?,
         grid_exception_dates: make_opt_collection(file_handler, "grid_exception_dates.txt")?,
         grid_periods: make_opt_collection(file_handler, "grid_periods.txt")?,
@@ -330,9 +376,10 @@ where
             file_handler,
             "administrative_regions.txt",
         )?,
+        translations: make_opt_collection(file_handler, "translations.txt")?,
         ..Default::default()
     };
-    manage_calendars(file_handler, &mut collections)?;
+    calendars::_manage_calendars(file_handler, &mut collections, calendar_configuration)?;
     read::manage_geometries(&mut collections, file_handler)?;
     read::manage_feed_infos(&mut collections, file_handler)?;
     read::manage_stops(&mut collections, file_handler)?;
@@ -359,75 +406,95 @@ pub fn write<P: AsRef<path::Path>>(
     let path = path.as_ref();
     std::fs::create_dir_all(path)?;
     info!("Writing NTFS to {:?}", path);
+    write_files(model, path, current_datetime)
+}
 
-    write::write_feed_infos(path, model, current_datetime)?;
-    write_collection_with_id(path, "contributors.txt", &model.contributors)?;
-    write_collection_with_id(path, "datasets.txt", &model.datasets)?;
-    write_collection_with_id(path, "networks.txt", &model.networks)?;
-    write_collection_with_id(path, "commercial_modes.txt", &model.commercial_modes)?;
-    write_collection_with_id(path, "companies.txt", &model.companies)?;
-    write_collection_with_id(path, "lines.txt", &model.lines)?;
-    write_collection_with_id(path, "physical_modes.txt", &model.physical_modes)?;
-    write_collection_with_id(path, "equipments.txt", &model.equipments)?;
-    write_collection_with_id(path, "routes.txt", &model.routes)?;
-    write_collection_with_id(path, "trip_properties.txt", &model.trip_properties)?;
-    write_collection_with_id(path, "geometries.txt", &model.geometries)?;
-    write_collection(path, "transfers.txt", &model.transfers)?;
-    write_collection(path, "admin_stations.txt", &model.admin_stations)?;
-    write_collection_with_id(path, "tickets.txt", &model.tickets)?;
-    write_collection_with_id(path, "ticket_uses.txt", &model.ticket_uses)?;
-    write_collection(path, "ticket_prices.txt", &model.ticket_prices)?;
+fn write_files<F: FileWriter>(
+    model: &Model,
+    mut output: F,
+    current_datetime: DateTime<FixedOffset>,
+) -> Result<()> {
+    if let Some(epsg) = model.stops_epsg {
+        bail!(
+            "Cannot write NTFS with stops reprojected to EPSG:{}, longitudes and latitudes are expected",
+            epsg
+        );
+    }
+    write::write_feed_infos(&mut output, model, current_datetime)?;
+    write_collection_with_id(&mut output, "contributors.txt", &model.contributors)?;
+    write_collection_with_id(&mut output, "datasets.txt", &model.datasets)?;
+    write_collection_with_id(&mut output, "networks.txt", &model.networks)?;
+    write_collection_with_id(&mut output, "commercial_modes.txt", &model.commercial_modes)?;
+    write_collection_with_id(&mut output, "companies.txt", &model.companies)?;
+    write_collection_with_id(&mut output, "lines.txt", &model.lines)?;
+    write_collection_with_id(&mut output, "physical_modes.txt", &model.physical_modes)?;
+    write_collection_with_id(&mut output, "equipments.txt", &model.equipments)?;
+    write_collection_with_id(&mut output, "routes.txt", &model.routes)?;
+    write_collection_with_id(&mut output, "trip_properties.txt", &model.trip_properties)?;
+    write_collection_with_id(&mut output, "geometries.txt", &model.geometries)?;
+    write_collection(&mut output, "transfers.txt", &model.transfers)?;
+    write_collection(&mut output, "admin_stations.txt", &model.admin_stations)?;
+    write_collection_with_id(&mut output, "tickets.txt", &model.tickets)?;
+    write_collection_with_id(&mut output, "ticket_uses.txt", &model.ticket_uses)?;
+    write_collection(&mut output, "ticket_prices.txt", &model.ticket_prices)?;
     write_collection(
-        path,
+        &mut output,
         "ticket_use_perimeters.txt",
         &model.ticket_use_perimeters,
     )?;
     write_collection(
-        path,
+        &mut output,
         "ticket_use_restrictions.txt",
         &model.ticket_use_restrictions,
     )?;
-    write_collection_with_id(path, "grid_calendars.txt", &model.grid_calendars)?;
+    write_collection_with_id(&mut output, "grid_calendars.txt", &model.grid_calendars)?;
     write_collection(
-        path,
+        &mut output,
         "grid_exception_dates.txt",
         &model.grid_exception_dates,
     )?;
-    write_collection(path, "grid_periods.txt", &model.grid_periods)?;
+    write_collection(&mut output, "grid_periods.txt", &model.grid_periods)?;
     write_collection(
-        path,
+        &mut output,
         "grid_rel_calendar_line.txt",
         &model.grid_rel_calendar_line,
     )?;
     write::write_vehicle_journeys_and_stop_times(
-        path,
+        &mut output,
         &model.vehicle_journeys,
         &model.stop_points,
         &model.stop_time_headsigns,
         &model.stop_time_ids,
     )?;
-    write_collection(path, "frequencies.txt", &model.frequencies)?;
-    write_calendar_dates(path, &model.calendars)?;
+    write_collection(&mut output, "frequencies.txt", &model.frequencies)?;
+    write_calendar_files(
+        &mut output,
+        &model.calendars,
+        &WriteConfiguration::default(),
+        |_| true,
+    )?;
     write::write_stops(
-        path,
+        &mut output,
         &model.stop_points,
         &model.stop_areas,
         &model.stop_locations,
     )?;
-    write::write_comments(path, model)?;
-    write::write_booking_rules(path, model)?;
-    write::write_codes(path, model)?;
-    write::write_object_properties(path, model)?;
-    write::write_fares_v1(path, model)?;
-    write_collection_with_id(path, "pathways.txt", &model.pathways)?;
-    write_collection_with_id(path, "levels.txt", &model.levels)?;
-    write_collection_with_id(path, "addresses.txt", &model.addresses)?;
+    write::write_comments(&mut output, model)?;
+    write::write_booking_rules(&mut output, model)?;
+    write::write_codes(&mut output, model)?;
+    write::write_object_properties(&mut output, model)?;
+    write::write_fares_v1(&mut output, model)?;
+    write_collection_with_id(&mut output, "pathways.txt", &model.pathways)?;
+    write_collection_with_id(&mut output, "levels.txt", &model.levels)?;
+    write_collection_with_id(&mut output, "attributions.txt", &model.attributions)?;
+    write_collection_with_id(&mut output, "addresses.txt", &model.addresses)?;
     write_collection_with_id(
-        path,
+        &mut output,
         "administrative_regions.txt",
         &model.administrative_regions,
     )?;
-    write_collection(path, "occupancies.txt", &model.occupancies)?;
+    write_collection(&mut output, "occupancies.txt", &model.occupancies)?;
+    write_collection(&mut output, "translations.txt", &model.translations)?;
 
     Ok(())
 }
@@ -442,13 +509,26 @@ pub fn write_to_zip<P: AsRef<path::Path>>(
 ) -> Result<()> {
     let path = path.as_ref();
     info!("Writing NTFS to ZIP File {:?}", path);
-    let input_tmp_dir = tempdir()?;
-    write(model, input_tmp_dir.path(), current_datetime)?;
-    zip_to(input_tmp_dir.path(), path)?;
-    input_tmp_dir.close()?;
+    let file = std::fs::File::create(path).with_context(|| format!("Error creating {:?}", path))?;
+    write_to_zip_writer(model, file, current_datetime)?;
     Ok(())
 }
 
+/// Exports a `Model` to a
+/// [NTFS](https://github.com/hove-io/ntfs-specification/blob/master/ntfs_fr.md)
+/// ZIP archive streamed into the writer, each file being directly written
+/// in its ZIP entry without any intermediate directory.
+/// The writer is returned once the archive is finished.
+pub fn write_to_zip_writer<W: io::Write + io::Seek>(
+    model: &Model,
+    writer: W,
+    current_datetime: DateTime<FixedOffset>,
+) -> Result<W> {
+    let mut output = ZipFileWriter::new(writer);
+    write_files(model, &mut output, current_datetime)?;
+    output.finish()
+}
+
 #[cfg(test)]
 mod tests {
     use super::Collections;
@@ -546,6 +626,63 @@ mod tests {
         });
     }
 
+    #[test]
+    fn feed_infos_dates_from_source() {
+        let dataset = Dataset {
+            id: "Foo:0".to_string(),
+            contributor_id: "Foo".to_string(),
+            start_date: chrono::NaiveDate::from_ymd_opt(2018, 1, 30).unwrap(),
+            end_date: chrono::NaiveDate::from_ymd_opt(2018, 1, 31).unwrap(),
+            dataset_type: None,
+            extrapolation: false,
+            desc: None,
+            system: None,
+        };
+        let feed_infos = vec![
+            ("feed_publisher_name", "Hove"),
+            ("feed_start_date", "20180131"),
+            ("feed_end_date", "20180301"),
+        ]
+        .into_iter()
+        .map(|(param, value)| (param.to_string(), value.to_string()))
+        .collect();
+        let mut collections = Collections {
+            datasets: CollectionWithId::from(dataset),
+            feed_infos,
+            ..Default::default()
+        };
+
+        test_in_tmp_dir(|path| {
+            write::write_feed_infos(path, &collections, get_test_datetime()).unwrap();
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            read::manage_feed_infos(&mut collections, &mut handler).unwrap();
+            // the source start date narrows the period of the data
+            assert_eq!(
+                Some("20180131"),
+                collections
+                    .feed_infos
+                    .get("feed_start_date")
+                    .map(String::as_str)
+            );
+            // the source end date is clamped to the period of the data
+            assert_eq!(
+                Some("20180131"),
+                collections
+                    .feed_infos
+                    .get("feed_end_date")
+                    .map(String::as_str)
+            );
+            assert_eq!(
+                Some("Hove"),
+                collections
+                    .feed_infos
+                    .get("feed_publisher_name")
+                    .map(String::as_str)
+            );
+            assert!(!collections.feed_infos.contains_key("feed_version"));
+        });
+    }
+
     #[test]
     fn networks_serialization_deserialization() {
         test_serialize_deserialize_collection_with_id(vec![
@@ -973,10 +1110,12 @@ mod tests {
             Calendar {
                 id: "0".to_string(),
                 dates: dates1,
+                ..Default::default()
             },
             Calendar {
                 id: "1".to_string(),
                 dates: dates2,
+                ..Default::default()
             },
         ])
         .unwrap();
@@ -18,25 +18,25 @@ use super::{
 use crate::model::Collections;
 use crate::ntfs::{has_fares_v1, has_fares_v2};
 use crate::objects::*;
+use crate::utils::FileWriter;
 use crate::NTFS_VERSION;
 use anyhow::{anyhow, bail, Context};
 use chrono::{DateTime, Days, FixedOffset};
 use csv::Writer;
 use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::cmp;
 use std::collections::{BTreeSet, HashMap};
-use std::fs::File;
-use std::path;
+use std::io;
 use tracing::{info, warn};
 use typed_index_collection::{Collection, CollectionWithId, Id};
 
-pub fn write_feed_infos(
-    path: &path::Path,
+pub fn write_feed_infos<F: FileWriter>(
+    mut output: F,
     collections: &Collections,
     current_datetime: DateTime<FixedOffset>,
 ) -> Result<()> {
     let file = "feed_infos.txt";
     info!(file_name = %file, "Writing");
-    let path = path.join(file);
     let mut feed_infos = collections.feed_infos.clone();
     feed_infos.insert(
         "feed_creation_date".to_string(),
@@ -51,7 +51,21 @@ pub fn write_feed_infos(
         current_datetime.to_rfc3339(),
     );
     feed_infos.insert("ntfs_version".to_string(), NTFS_VERSION.to_string());
-    let (start_date, end_date) = collections.calculate_validity_period()?;
+    // the dates of the source feed are kept when they narrow the period of
+    // the data, which stays the reference
+    let (validity_start_date, validity_end_date) = collections.calculate_validity_period()?;
+    let (feed_start_date, feed_end_date) = collections.feed_infos_validity_period();
+    let start_date = feed_start_date.map_or(validity_start_date, |feed_start_date| {
+        cmp::max(feed_start_date, validity_start_date)
+    });
+    let end_date = feed_end_date.map_or(validity_end_date, |feed_end_date| {
+        cmp::min(feed_end_date, validity_end_date)
+    });
+    let (start_date, end_date) = if start_date <= end_date {
+        (start_date, end_date)
+    } else {
+        (validity_start_date, validity_end_date)
+    };
     feed_infos.insert(
         "feed_start_date".to_string(),
         start_date.format("%Y%m%d").to_string(),
@@ -61,21 +75,20 @@ pub fn write_feed_infos(
         end_date.format("%Y%m%d").to_string(),
     );
 
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|| format!("Error reading {:?}", path))?;
+    let mut wtr = csv::Writer::from_writer(output.create_file(file)?);
     wtr.write_record(["feed_info_param", "feed_info_value"])
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
     for feed_info in feed_infos {
         wtr.serialize(feed_info)
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file))?;
     }
     wtr.flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
     Ok(())
 }
 
-pub fn write_vehicle_journeys_and_stop_times(
-    path: &path::Path,
+pub fn write_vehicle_journeys_and_stop_times<F: FileWriter>(
+    mut output: F,
     vehicle_journeys: &CollectionWithId<VehicleJourney>,
     stop_points: &CollectionWithId<StopPoint>,
     stop_time_headsigns: &HashMap<(String, u32), String>,
@@ -85,17 +98,20 @@ pub fn write_vehicle_journeys_and_stop_times(
     let stop_times = "stop_times.txt";
     info!(file_name = %trip, file_name = %stop_times, "Writing");
 
-    let trip_path = path.join(trip);
-    let stop_times_path = path.join(stop_times);
-    let mut vj_wtr = csv::Writer::from_path(&trip_path)
-        .with_context(|| format!("Error reading {:?}", trip_path))?;
-    let mut st_wtr = csv::Writer::from_path(&stop_times_path)
-        .with_context(|| format!("Error reading {:?}", stop_times_path))?;
-    for (vj_idx, vj) in vehicle_journeys.iter() {
+    // the files are written one after the other, to be streamable
+    let mut vj_wtr = csv::Writer::from_writer(output.create_file(trip)?);
+    for vj in vehicle_journeys.values() {
         vj_wtr
             .serialize(vj)
-            .with_context(|| format!("Error reading {:?}", trip_path))?;
+            .with_context(|| format!("Error reading {:?}", trip))?;
+    }
+    vj_wtr
+        .flush()
+        .with_context(|| format!("Error reading {:?}", trip))?;
+    drop(vj_wtr);
 
+    let mut st_wtr = csv::Writer::from_writer(output.create_file(stop_times)?);
+    for (vj_idx, vj) in vehicle_journeys.iter() {
         for st in &vj.stop_times {
             let precision = st.precision.clone();
             st_wtr
@@ -121,21 +137,18 @@ pub fn write_vehicle_journeys_and_stop_times(
                         .cloned(),
                     precision,
                 })
-                .with_context(|| format!("Error reading {:?}", st_wtr))?;
+                .with_context(|| format!("Error reading {:?}", stop_times))?;
         }
     }
     st_wtr
         .flush()
-        .with_context(|| format!("Error reading {:?}", stop_times_path))?;
-    vj_wtr
-        .flush()
-        .with_context(|| format!("Error reading {:?}", trip_path))?;
+        .with_context(|| format!("Error reading {:?}", stop_times))?;
 
     Ok(())
 }
 
-fn do_write_fares_v1(
-    base_path: &path::Path,
+fn do_write_fares_v1<F: FileWriter>(
+    mut output: F,
     prices_v1: &Collection<PriceV1>,
     od_fares_v1: &Collection<OdFareV1>,
     fares_v1: &Collection<FareV1>,
@@ -149,30 +162,25 @@ fn do_write_fares_v1(
     builder.has_headers(false);
 
     info!(file_name = %file_prices, "Writing");
-    let path = base_path.join(file_prices);
-    let mut prices_wtr = builder
-        .from_path(&path)
-        .with_context(|| format!("Error reading {:?}", path))?;
+    let mut prices_wtr = builder.from_writer(output.create_file(file_prices)?);
     for price_v1 in prices_v1.values() {
         prices_wtr
             .serialize(price_v1)
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file_prices))?;
     }
     prices_wtr
         .flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file_prices))?;
+    drop(prices_wtr);
 
     builder.has_headers(true);
 
     info!(file_name = %file_od_fares, "Writing");
-    let path = base_path.join(file_od_fares);
-    let mut od_fares_wtr = builder
-        .from_path(&path)
-        .with_context(|| format!("Error reading {:?}", path))?;
+    let mut od_fares_wtr = builder.from_writer(output.create_file(file_od_fares)?);
     for od_fare_v1 in od_fares_v1.values() {
         od_fares_wtr
             .serialize(od_fare_v1)
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file_od_fares))?;
     }
     // Write file header if collection is empty (normally done by serialize)
     if od_fares_v1.is_empty() {
@@ -188,7 +196,8 @@ fn do_write_fares_v1(
     }
     od_fares_wtr
         .flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file_od_fares))?;
+    drop(od_fares_wtr);
 
     if fares_v1.is_empty() {
         info!("Writing skipped {}", file_fares);
@@ -196,18 +205,15 @@ fn do_write_fares_v1(
     }
 
     info!(file_name = %file_fares, "Writing");
-    let path = base_path.join(file_fares);
-    let mut fares_wtr = builder
-        .from_path(&path)
-        .with_context(|| format!("Error reading {:?}", path))?;
+    let mut fares_wtr = builder.from_writer(output.create_file(file_fares)?);
     for fare_v1 in fares_v1.values() {
         fares_wtr
             .serialize(fare_v1)
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file_fares))?;
     }
     fares_wtr
         .flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file_fares))?;
 
     Ok(())
 }
@@ -486,24 +492,24 @@ fn construct_fare_v1_from_v2(fares: &Fares) -> Result<(BTreeSet<PriceV1>, BTreeS
     Ok((prices_v1, fares_v1))
 }
 
-fn do_write_fares_v1_from_v2(base_path: &path::Path, fares: &Fares) -> Result<()> {
+fn do_write_fares_v1_from_v2<F: FileWriter>(output: F, fares: &Fares) -> Result<()> {
     let (prices_v1, fares_v1) = construct_fare_v1_from_v2(fares)?;
 
     if prices_v1.is_empty() || fares_v1.is_empty() {
         bail!("Cannot convert Fares V2 to V1. Prices or fares are empty.")
     }
     do_write_fares_v1(
-        base_path,
+        output,
         &Collection::new(prices_v1.into_iter().collect()),
         &Collection::default(),
         &Collection::new(fares_v1.into_iter().collect()),
     )
 }
 
-pub fn write_fares_v1(base_path: &path::Path, collections: &Collections) -> Result<()> {
+pub fn write_fares_v1<F: FileWriter>(output: F, collections: &Collections) -> Result<()> {
     if has_fares_v2(collections) {
         return do_write_fares_v1_from_v2(
-            base_path,
+            output,
             &Fares {
                 tickets: &collections.tickets,
                 ticket_prices: &collections.ticket_prices,
@@ -515,7 +521,7 @@ pub fn write_fares_v1(base_path: &path::Path, collections: &Collections) -> Resu
     }
     if has_fares_v1(collections) {
         return do_write_fares_v1(
-            base_path,
+            output,
             &collections.prices_v1,
             &collections.od_fares_v1,
             &collections.fares_v1,
@@ -524,14 +530,14 @@ pub fn write_fares_v1(base_path: &path::Path, collections: &Collections) -> Resu
     Ok(())
 }
 
-pub fn write_stops(
-    path: &path::Path,
+pub fn write_stops<F: FileWriter>(
+    mut output: F,
     stop_points: &CollectionWithId<StopPoint>,
     stop_areas: &CollectionWithId<StopArea>,
     stop_locations: &CollectionWithId<StopLocation>,
 ) -> Result<()> {
-    fn write_stop_locations(
-        wtr: &mut Writer<File>,
+    fn write_stop_locations<W: io::Write>(
+        wtr: &mut Writer<W>,
         stop_locations: &CollectionWithId<StopLocation>,
     ) -> Result<()> {
         for sl in stop_locations.values() {
@@ -558,9 +564,7 @@ pub fn write_stops(
     }
     let file = "stops.txt";
     info!(file_name = %file, "Writing");
-    let path = path.join(file);
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|| format!("Error reading {:?}", path))?;
+    let mut wtr = csv::Writer::from_writer(output.create_file(file)?);
     for st in stop_points.values() {
         let location_type = if st.stop_type == StopType::Zone {
             StopLocationType::GeographicArea
@@ -584,7 +588,7 @@ pub fn write_stops(
             platform_code: st.platform_code.clone(),
             address_id: st.address_id.clone(),
         })
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
     }
 
     for sa in stop_areas.values() {
@@ -605,12 +609,12 @@ pub fn write_stops(
             platform_code: None,
             address_id: sa.address_id.clone(),
         })
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
     }
     write_stop_locations(&mut wtr, stop_locations)
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
     wtr.flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
 
     Ok(())
 }
@@ -618,7 +622,7 @@ pub fn write_stops(
 fn write_comment_links_from_collection_with_id<W, T>(
     wtr: &mut csv::Writer<W>,
     collection: &CollectionWithId<T>,
-    path: &path::Path,
+    file: &str,
 ) -> Result<()>
 where
     T: Id<T> + Links<Comment> + GetObjectType,
@@ -631,7 +635,7 @@ where
                 object_type: T::get_object_type(),
                 comment_id: comment_id.to_string(),
             })
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file))?;
         }
     }
 
@@ -641,7 +645,7 @@ where
 fn write_booking_rule_links_from_collection_with_id<W, T>(
     wtr: &mut csv::Writer<W>,
     collection: &CollectionWithId<T>,
-    path: &path::Path,
+    file: &str,
 ) -> Result<()>
 where
     T: Id<T> + Links<BookingRule> + GetObjectType,
@@ -654,7 +658,7 @@ where
                 object_type: T::get_object_type(),
                 booking_rule_id: id.to_string(),
             })
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file))?;
         }
     }
 
@@ -665,7 +669,7 @@ fn write_stop_time_comment_links<W>(
     wtr: &mut csv::Writer<W>,
     stop_time_ids: &HashMap<(String, u32), String>,
     stop_time_comments: &HashMap<(String, u32), String>,
-    path: &path::Path,
+    file: &str,
 ) -> Result<()>
 where
     W: ::std::io::Write,
@@ -678,13 +682,13 @@ where
             object_type: ObjectType::StopTime,
             comment_id: id_comment.to_string(),
         })
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
     }
 
     Ok(())
 }
 
-pub fn write_comments(path: &path::Path, collections: &Collections) -> Result<()> {
+pub fn write_comments<F: FileWriter>(mut output: F, collections: &Collections) -> Result<()> {
     if collections.comments.is_empty() {
         return Ok(());
     }
@@ -692,65 +696,53 @@ pub fn write_comments(path: &path::Path, collections: &Collections) -> Result<()
     let comment_links = "comment_links.txt";
     info!(file_name = %comments, file_name = %comment_links, "Writing");
 
-    let comments_path = path.join(comments);
-    let comment_links_path = path.join(comment_links);
-
-    let mut c_wtr = csv::Writer::from_path(&comments_path)
-        .with_context(|| format!("Error reading {:?}", comments_path))?;
-    let mut cl_wtr = csv::Writer::from_path(&comment_links_path)
-        .with_context(|| format!("Error reading {:?}", comment_links_path))?;
+    let mut c_wtr = csv::Writer::from_writer(output.create_file(comments)?);
     for c in collections.comments.values() {
         c_wtr
             .serialize(c)
-            .with_context(|| format!("Error reading {:?}", comments_path))?;
+            .with_context(|| format!("Error reading {:?}", comments))?;
     }
+    c_wtr
+        .flush()
+        .with_context(|| format!("Error reading {:?}", comments))?;
+    drop(c_wtr);
 
+    let mut cl_wtr = csv::Writer::from_writer(output.create_file(comment_links)?);
     write_comment_links_from_collection_with_id(
         &mut cl_wtr,
         &collections.stop_areas,
-        &comment_links_path,
+        comment_links,
     )?;
     write_comment_links_from_collection_with_id(
         &mut cl_wtr,
         &collections.stop_points,
-        &comment_links_path,
-    )?;
-    write_comment_links_from_collection_with_id(
-        &mut cl_wtr,
-        &collections.lines,
-        &comment_links_path,
-    )?;
-    write_comment_links_from_collection_with_id(
-        &mut cl_wtr,
-        &collections.routes,
-        &comment_links_path,
+        comment_links,
     )?;
+    write_comment_links_from_collection_with_id(&mut cl_wtr, &collections.lines, comment_links)?;
+    write_comment_links_from_collection_with_id(&mut cl_wtr, &collections.routes, comment_links)?;
     write_comment_links_from_collection_with_id(
         &mut cl_wtr,
         &collections.vehicle_journeys,
-        &comment_links_path,
+        comment_links,
     )?;
 
     write_stop_time_comment_links(
         &mut cl_wtr,
         &collections.stop_time_ids,
         &collections.stop_time_comments,
-        &comment_links_path,
+        comment_links,
     )?;
 
     // TODO: add line_groups
 
     cl_wtr
         .flush()
-        .with_context(|| format!("Error reading {:?}", comment_links_path))?;
-    c_wtr
-        .flush()
-        .with_context(|| format!("Error reading {:?}", comments_path))?;
+        .with_context(|| format!("Error reading {:?}", comment_links))?;
 
     Ok(())
 }
 
-pub fn write_booking_rules(path: &path::Path, collections: &Collections) -> Result<()> {
+pub fn write_booking_rules<F: FileWriter>(mut output: F, collections: &Collections) -> Result<()> {
     if collections.booking_rules.is_empty() {
         return Ok(());
     }
@@ -758,36 +750,32 @@ pub fn write_booking_rules(path: &path::Path, collections: &Collections) -> Resu
     let booking_rule_links = "booking_rule_links.txt";
     info!(file_name = %booking_rules, file_name = %booking_rule_links, "Writing");
 
-    let booking_rules_path = path.join(booking_rules);
-    let booking_rule_links_path = path.join(booking_rule_links);
-
-    let mut c_wtr = csv::Writer::from_path(&booking_rules_path)
-        .with_context(|| format!("Error reading {:?}", booking_rules_path))?;
-    let mut cl_wtr = csv::Writer::from_path(&booking_rule_links_path)
-        .with_context(|| format!("Error reading {:?}", booking_rule_links_path))?;
+    let mut c_wtr = csv::Writer::from_writer(output.create_file(booking_rules)?);
     for c in collections.booking_rules.values() {
         c_wtr
             .serialize(c)
-            .with_context(|| format!("Error reading {:?}", booking_rules_path))?;
+            .with_context(|| format!("Error reading {:?}", booking_rules))?;
     }
+    c_wtr
+        .flush()
+        .with_context(|| format!("Error reading {:?}", booking_rules))?;
+    drop(c_wtr);
 
+    let mut cl_wtr = csv::Writer::from_writer(output.create_file(booking_rule_links)?);
     write_booking_rule_links_from_collection_with_id(
         &mut cl_wtr,
         &collections.lines,
-        &booking_rule_links_path,
+        booking_rule_links,
     )?;
     write_booking_rule_links_from_collection_with_id(
         &mut cl_wtr,
         &collections.vehicle_journeys,
-        &booking_rule_links_path,
+        booking_rule_links,
     )?;
 
     cl_wtr
         .flush()
-        .with_context(|| format!("Error reading {:?}", booking_rule_links_path))?;
-    c_wtr
-        .flush()
-        .with_context(|| format!("Error reading {:?}", booking_rules_path))?;
+        .with_context(|| format!("Error reading {:?}", booking_rule_links))?;
 
     Ok(())
 }
@@ -795,7 +783,7 @@ pub fn write_booking_rules(path: &path::Path, collections: &Collections) -> Resu
 fn write_codes_from_collection_with_id<W, T>(
     wtr: &mut csv::Writer<W>,
     collections: &CollectionWithId<T>,
-    path: &path::Path,
+    file: &str,
 ) -> Result<()>
 where
     T: Id<T> + Codes + GetObjectType,
@@ -809,14 +797,14 @@ where
                 object_system: c.0.clone(),
                 object_code: c.1.clone(),
             })
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file))?;
         }
     }
 
     Ok(())
 }
 
-pub fn write_codes(path: &path::Path, collections: &Collections) -> Result<()> {
+pub fn write_codes<F: FileWriter>(mut output: F, collections: &Collections) -> Result<()> {
     fn collection_has_no_codes<T: Codes>(collection: &CollectionWithId<T>) -> bool {
         collection.values().all(|c| c.codes().is_empty())
     }
@@ -834,20 +822,17 @@ pub fn write_codes(path: &path::Path, collections: &Collections) -> Result<()> {
     let file = "object_codes.txt";
     info!(file_name = %file, "Writing");
 
-    let path = path.join(file);
-
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|| format!("Error reading {:?}", path))?;
-    write_codes_from_collection_with_id(&mut wtr, &collections.stop_areas, &path)?;
-    write_codes_from_collection_with_id(&mut wtr, &collections.stop_points, &path)?;
-    write_codes_from_collection_with_id(&mut wtr, &collections.networks, &path)?;
-    write_codes_from_collection_with_id(&mut wtr, &collections.lines, &path)?;
-    write_codes_from_collection_with_id(&mut wtr, &collections.routes, &path)?;
-    write_codes_from_collection_with_id(&mut wtr, &collections.vehicle_journeys, &path)?;
-    write_codes_from_collection_with_id(&mut wtr, &collections.companies, &path)?;
+    let mut wtr = csv::Writer::from_writer(output.create_file(file)?);
+    write_codes_from_collection_with_id(&mut wtr, &collections.stop_areas, file)?;
+    write_codes_from_collection_with_id(&mut wtr, &collections.stop_points, file)?;
+    write_codes_from_collection_with_id(&mut wtr, &collections.networks, file)?;
+    write_codes_from_collection_with_id(&mut wtr, &collections.lines, file)?;
+    write_codes_from_collection_with_id(&mut wtr, &collections.routes, file)?;
+    write_codes_from_collection_with_id(&mut wtr, &collections.vehicle_journeys, file)?;
+    write_codes_from_collection_with_id(&mut wtr, &collections.companies, file)?;
 
     wtr.flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
 
     Ok(())
 }
@@ -855,7 +840,7 @@ pub fn write_codes(path: &path::Path, collections: &Collections) -> Result<()> {
 fn write_object_properties_from_collection_with_id<W, T>(
     wtr: &mut csv::Writer<W>,
     collection: &CollectionWithId<T>,
-    path: &path::Path,
+    file: &str,
 ) -> Result<()>
 where
     T: Id<T> + Properties + GetObjectType,
@@ -869,14 +854,17 @@ where
                 object_property_name: c.0.clone(),
                 object_property_value: c.1.clone(),
             })
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file))?;
         }
     }
 
     Ok(())
 }
 
-pub fn write_object_properties(path: &path::Path, collections: &Collections) -> Result<()> {
+pub fn write_object_properties<F: FileWriter>(
+    mut output: F,
+    collections: &Collections,
+) -> Result<()> {
     fn collection_has_no_object_properties<T: Properties>(
         collection: &CollectionWithId<T>,
     ) -> bool {
@@ -894,22 +882,15 @@ pub fn write_object_properties(path: &path::Path, collections: &Collections) ->
     let file = "object_properties.txt";
     info!(file_name = %file, "Writing");
 
-    let path = path.join(file);
-
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|| format!("Error reading {:?}", path))?;
-    write_object_properties_from_collection_with_id(&mut wtr, &collections.stop_areas, &path)?;
-    write_object_properties_from_collection_with_id(&mut wtr, &collections.stop_points, &path)?;
-    write_object_properties_from_collection_with_id(&mut wtr, &collections.lines, &path)?;
-    write_object_properties_from_collection_with_id(&mut wtr, &collections.routes, &path)?;
-    write_object_properties_from_collection_with_id(
-        &mut wtr,
-        &collections.vehicle_journeys,
-        &path,
-    )?;
+    let mut wtr = csv::Writer::from_writer(output.create_file(file)?);
+    write_object_properties_from_collection_with_id(&mut wtr, &collections.stop_areas, file)?;
+    write_object_properties_from_collection_with_id(&mut wtr, &collections.stop_points, file)?;
+    write_object_properties_from_collection_with_id(&mut wtr, &collections.lines, file)?;
+    write_object_properties_from_collection_with_id(&mut wtr, &collections.routes, file)?;
+    write_object_properties_from_collection_with_id(&mut wtr, &collections.vehicle_journeys, file)?;
 
     wtr.flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
 
     Ok(())
 }
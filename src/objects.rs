@@ -16,15 +16,16 @@
 
 #![allow(missing_docs)]
 
-use crate::{serde_utils::*, AddPrefix, PrefixConfiguration};
-use chrono::{Days, NaiveDate};
+use crate::{calendars::weekly_dates, serde_utils::*, AddPrefix, PrefixConfiguration};
+use chrono::{DateTime, Datelike, Days, NaiveDate, TimeZone, Weekday};
 use chrono_tz::Tz;
 use derivative::Derivative;
 use geo::{Geometry as GeoGeometry, Point as GeoPoint};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
 use std::ops::{Add, Div, Rem, Sub};
 use std::str::FromStr;
@@ -190,7 +191,7 @@ pub enum DatasetType {
     Production,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ValidityPeriod {
     pub start_date: Date,
     pub end_date: Date,
@@ -211,6 +212,33 @@ impl Default for ValidityPeriod {
     }
 }
 
+/// Metadata of the feed, as in GTFS feed_info.txt
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Eq, PartialEq)]
+pub struct FeedInfo {
+    #[serde(rename = "feed_publisher_name")]
+    pub publisher_name: String,
+    #[serde(rename = "feed_publisher_url")]
+    pub publisher_url: String,
+    #[serde(rename = "feed_lang")]
+    pub lang: String,
+    #[serde(
+        rename = "feed_start_date",
+        default,
+        deserialize_with = "de_option_date_string",
+        serialize_with = "ser_option_naive_date"
+    )]
+    pub start_date: Option<Date>,
+    #[serde(
+        rename = "feed_end_date",
+        default,
+        deserialize_with = "de_option_date_string",
+        serialize_with = "ser_option_naive_date"
+    )]
+    pub end_date: Option<Date>,
+    #[serde(rename = "feed_version")]
+    pub version: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct Dataset {
     #[serde(rename = "dataset_id")]
@@ -860,6 +888,22 @@ impl Time {
     pub fn total_seconds(self) -> u32 {
         self.0
     }
+    /// Resolve the time of a service day into an absolute date time.
+    ///
+    /// As in GTFS, the time is an offset from "noon minus 12h" of the service
+    /// day, which is midnight except on DST days: on a spring-forward day
+    /// `01:00:00` is the wall-clock `00:00`, on a fall-back day it is the first
+    /// occurrence of the wall-clock `02:00`. This keeps the times past
+    /// `24:00:00` and the ones around the DST change unambiguous, each of them
+    /// being resolved to a single instant.
+    /// If noon is itself ambiguous in `timezone` its earliest occurrence is
+    /// used, if it does not exist `None` is returned.
+    pub fn on_service_day(self, date: Date, timezone: Tz) -> Option<DateTime<Tz>> {
+        let noon = timezone
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0)?)
+            .earliest()?;
+        Some(noon - chrono::Duration::hours(12) + chrono::Duration::seconds(i64::from(self.0)))
+    }
 }
 impl Add for Time {
     type Output = Time;
@@ -1399,6 +1443,62 @@ impl AddPrefix for Level {
 }
 impl_id!(Level);
 
+/// Organization credited for the data, for the whole dataset or only for a
+/// network, a line or a vehicle journey.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Default, Clone)]
+pub struct Attribution {
+    #[serde(rename = "attribution_id")]
+    pub id: String,
+    pub organization_name: String,
+    #[serde(
+        default,
+        deserialize_with = "de_from_u8",
+        serialize_with = "ser_from_bool"
+    )]
+    pub is_producer: bool,
+    #[serde(
+        default,
+        deserialize_with = "de_from_u8",
+        serialize_with = "ser_from_bool"
+    )]
+    pub is_operator: bool,
+    #[serde(
+        default,
+        deserialize_with = "de_from_u8",
+        serialize_with = "ser_from_bool"
+    )]
+    pub is_authority: bool,
+    pub network_id: Option<String>,
+    pub line_id: Option<String>,
+    #[serde(rename = "trip_id")]
+    pub vehicle_journey_id: Option<String>,
+    #[serde(rename = "attribution_url")]
+    pub url: Option<String>,
+    #[serde(rename = "attribution_email")]
+    pub email: Option<String>,
+    #[serde(rename = "attribution_phone")]
+    pub phone: Option<String>,
+}
+
+impl AddPrefix for Attribution {
+    fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.referential_prefix(self.id.as_str());
+        self.network_id = self
+            .network_id
+            .as_ref()
+            .map(|id| prefix_conf.referential_prefix(id.as_str()));
+        self.line_id = self
+            .line_id
+            .as_ref()
+            .map(|id| prefix_conf.referential_prefix(id.as_str()));
+        self.vehicle_journey_id = self
+            .vehicle_journey_id
+            .as_ref()
+            .map(|id| prefix_conf.schedule_prefix(id.as_str()));
+    }
+}
+impl_id!(Attribution);
+
 pub type Date = chrono::NaiveDate;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -1409,11 +1509,37 @@ pub enum ExceptionType {
     Remove,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+/// Metadata of a calendar besides its dates: the calendar.txt row it has
+/// been read from and the source of each date
+#[derive(Debug, Default, Clone)]
+pub struct CalendarMetadata {
+    /// Source tag of each date, only filled when provenance tracking is
+    /// enabled. Only kept in sync with the dates by the methods of
+    /// `Calendar` (see [`Calendar::remove_date`] and
+    /// [`Calendar::retain_dates`]).
+    pub provenance: Option<BTreeMap<Date, String>>,
+    /// Window declared in calendar.txt, before applying calendar_dates.txt
+    pub declared_period: Option<ValidityPeriod>,
+    /// Human-readable description, from the non-standard service_desc (or
+    /// service_name) column of calendar.txt
+    pub description: Option<String>,
+    /// Days of the week declared in calendar.txt, with `declared_period`,
+    /// only kept when reading with
+    /// [`crate::calendars::ReadConfiguration::keep_declared_days`]
+    pub declared_days: Option<Vec<Weekday>>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Derivative)]
+#[derivative(PartialEq, Eq)]
 pub struct Calendar {
     pub id: String,
     #[serde(skip)]
     pub dates: BTreeSet<Date>,
+    /// Not part of the equality of calendars, `None` when the calendar is
+    /// only known by its dates
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub metadata: Option<CalendarMetadata>,
 }
 
 impl_id!(Calendar);
@@ -1422,6 +1548,58 @@ impl Calendar {
         Calendar {
             id: calendar_id,
             dates: BTreeSet::new(),
+            metadata: None,
+        }
+    }
+
+    /// Returns the metadata of the calendar, created empty if needed
+    pub fn metadata_mut(&mut self) -> &mut CalendarMetadata {
+        self.metadata.get_or_insert_with(CalendarMetadata::default)
+    }
+
+    /// Window declared in calendar.txt, see [`CalendarMetadata::declared_period`]
+    pub fn declared_period(&self) -> Option<&ValidityPeriod> {
+        self.metadata.as_ref()?.declared_period.as_ref()
+    }
+
+    /// Description from calendar.txt, see [`CalendarMetadata::description`]
+    pub fn description(&self) -> Option<&str> {
+        self.metadata.as_ref()?.description.as_deref()
+    }
+
+    /// Days of the week declared in calendar.txt, see
+    /// [`CalendarMetadata::declared_days`]
+    pub fn declared_days(&self) -> Option<&[Weekday]> {
+        self.metadata.as_ref()?.declared_days.as_deref()
+    }
+
+    fn provenance(&self) -> Option<&BTreeMap<Date, String>> {
+        self.metadata.as_ref()?.provenance.as_ref()
+    }
+
+    fn provenance_mut(&mut self) -> Option<&mut BTreeMap<Date, String>> {
+        self.metadata.as_mut()?.provenance.as_mut()
+    }
+
+    /// Builds a calendar active on the `days` of the week between
+    /// `start_date` and `end_date` (both included), as a row of calendar.txt
+    /// would be read.
+    pub fn from_weekly(
+        calendar_id: String,
+        start_date: Date,
+        end_date: Date,
+        days: &[Weekday],
+    ) -> Calendar {
+        Calendar {
+            dates: weekly_dates(start_date, end_date, days),
+            metadata: Some(CalendarMetadata {
+                declared_period: Some(ValidityPeriod {
+                    start_date,
+                    end_date,
+                }),
+                ..Default::default()
+            }),
+            ..Calendar::new(calendar_id)
         }
     }
 
@@ -1429,6 +1607,319 @@ impl Calendar {
     pub fn overlaps(&self, other: &Self) -> bool {
         !self.dates.is_disjoint(&other.dates)
     }
+
+    /// Returns true if the calendar is active on every given date
+    pub fn active_on_all(&self, dates: &[Date]) -> bool {
+        match (self.dates.iter().next(), self.dates.iter().next_back()) {
+            (Some(first_date), Some(last_date)) => dates
+                .iter()
+                .all(|date| date >= first_date && date <= last_date && self.dates.contains(date)),
+            _ => dates.is_empty(),
+        }
+    }
+
+    /// Returns true if the calendar is active on at least one of the given dates
+    pub fn active_on_any(&self, dates: &[Date]) -> bool {
+        match (self.dates.iter().next(), self.dates.iter().next_back()) {
+            (Some(first_date), Some(last_date)) => dates
+                .iter()
+                .any(|date| date >= first_date && date <= last_date && self.dates.contains(date)),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the calendar is active on every day between its first
+    /// and its last date
+    pub fn is_daily(&self) -> bool {
+        match (self.dates.iter().next(), self.dates.iter().next_back()) {
+            (Some(first_date), Some(last_date)) => {
+                let nb_days = (*last_date - *first_date).num_days() + 1;
+                usize::try_from(nb_days).map_or(false, |nb_days| nb_days == self.dates.len())
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if the calendar is active on every day of the period,
+    /// both ends included (always true for a period ending before it starts)
+    pub fn runs_every_day_in(&self, period: &ValidityPeriod) -> bool {
+        if period.end_date < period.start_date {
+            return true;
+        }
+        let nb_days = (period.end_date - period.start_date).num_days() + 1;
+        let nb_active_days = self
+            .dates
+            .range(period.start_date..=period.end_date)
+            .count();
+        usize::try_from(nb_days).map_or(false, |nb_days| nb_days == nb_active_days)
+    }
+
+    /// Returns the ranges of consecutive active dates of the calendar, as
+    /// (first date, last date) pairs in chronological order
+    pub fn contiguous_ranges(&self) -> Vec<(Date, Date)> {
+        let mut ranges: Vec<(Date, Date)> = Vec::new();
+        for date in &self.dates {
+            match ranges.last_mut() {
+                Some((_, end)) if end.succ_opt() == Some(*date) => *end = *date,
+                _ => ranges.push((*date, *date)),
+            }
+        }
+        ranges
+    }
+
+    /// Returns the first date, last date and number of days of the longest
+    /// range of consecutive active dates, the earliest one on ties.
+    /// Returns `None` for a calendar without any date.
+    pub fn longest_run(&self) -> Option<(Date, Date, u32)> {
+        self.contiguous_ranges()
+            .into_iter()
+            .filter_map(|(start, end)| {
+                u32::try_from((end - start).num_days() + 1)
+                    .ok()
+                    .map(|nb_days| (start, end, nb_days))
+            })
+            .rev()
+            .max_by_key(|(_, _, nb_days)| *nb_days)
+    }
+
+    /// Fills the weeks (Monday to Sunday) having at least one active date
+    /// with all the days of the weekly pattern of the calendar (as computed
+    /// by [`crate::vptranslator::translate`]).
+    ///
+    /// This is an opt-in transform for aggregate modeling: it adds dates, so
+    /// the calendar no longer describes the real service.
+    pub fn snap_to_full_weeks(&mut self) {
+        let operating_days = crate::vptranslator::translate(&self.dates).operating_days;
+        let mondays: BTreeSet<Date> = self
+            .dates
+            .iter()
+            .map(|date| *date - Days::new(u64::from(date.weekday().num_days_from_monday())))
+            .collect();
+        for monday in mondays {
+            for day in &operating_days {
+                self.dates
+                    .insert(monday + Days::new(u64::from(day.num_days_from_monday())));
+            }
+        }
+    }
+
+    /// Returns the Jaccard similarity of the dates of both calendars, the
+    /// number of common dates divided by the number of dates of either.
+    /// Two empty calendars are considered identical.
+    pub fn jaccard_similarity(&self, other: &Self) -> f64 {
+        if self.dates.is_empty() && other.dates.is_empty() {
+            return 1.0;
+        }
+        // Both sets are sorted: walk them together to count the common dates
+        let mut intersection = 0usize;
+        let mut dates = self.dates.iter().peekable();
+        let mut other_dates = other.dates.iter().peekable();
+        while let (Some(date), Some(other_date)) = (dates.peek(), other_dates.peek()) {
+            match date.cmp(other_date) {
+                Ordering::Less => {
+                    dates.next();
+                }
+                Ordering::Greater => {
+                    other_dates.next();
+                }
+                Ordering::Equal => {
+                    intersection += 1;
+                    dates.next();
+                    other_dates.next();
+                }
+            }
+        }
+        let union = self.dates.len() + other.dates.len() - intersection;
+        intersection as f64 / union as f64
+    }
+
+    /// Returns a typical week of the calendar, from Monday to Sunday, each day
+    /// being set if the calendar is active on it. The week is the first one
+    /// having the most frequent weekly pattern, ties being broken in favor of
+    /// the pattern with the most active days.
+    pub fn representative_week(&self) -> Option<[Option<Date>; 7]> {
+        // For each week, identified by its Monday, the mask of its active days
+        let mut weeks: BTreeMap<Date, u8> = BTreeMap::new();
+        for date in &self.dates {
+            let day = date.weekday().num_days_from_monday();
+            let monday = *date - Days::new(u64::from(day));
+            *weeks.entry(monday).or_default() |= 1 << day;
+        }
+        let mut pattern_counts: BTreeMap<u8, usize> = BTreeMap::new();
+        for pattern in weeks.values() {
+            *pattern_counts.entry(*pattern).or_default() += 1;
+        }
+        let (best_pattern, _) = pattern_counts
+            .into_iter()
+            .max_by_key(|(pattern, count)| (*count, pattern.count_ones()))?;
+        let (monday, _) = weeks
+            .into_iter()
+            .find(|(_, pattern)| *pattern == best_pattern)?;
+        let mut week = [None; 7];
+        for (day, date) in week.iter_mut().enumerate() {
+            if best_pattern & (1 << day) != 0 {
+                *date = Some(monday + Days::new(day as u64));
+            }
+        }
+        Some(week)
+    }
+
+    /// Returns a human readable grid of the dates, one line per ISO week from
+    /// the first to the last date, active days marked with `x` and the
+    /// others with `.`. Useful for debugging or reporting.
+    ///
+    /// ```
+    /// # use transit_model::objects::{Calendar, Date};
+    /// let mut calendar = Calendar::new("c1".to_string());
+    /// calendar.dates.insert(Date::from_ymd_opt(2020, 1, 1).unwrap());
+    /// calendar.dates.insert(Date::from_ymd_opt(2020, 1, 6).unwrap());
+    /// assert_eq!(
+    ///     "week     Mo Tu We Th Fr Sa Su\n\
+    ///      2020-W01  .  .  x  .  .  .  .\n\
+    ///      2020-W02  x  .  .  .  .  .  .\n",
+    ///     calendar.to_week_grid_string()
+    /// );
+    /// ```
+    pub fn to_week_grid_string(&self) -> String {
+        use std::fmt::Write;
+        let mut grid = String::from("week     Mo Tu We Th Fr Sa Su\n");
+        let (first_date, last_date) =
+            match (self.dates.iter().next(), self.dates.iter().next_back()) {
+                (Some(first_date), Some(last_date)) => (*first_date, *last_date),
+                _ => return grid,
+            };
+        let mut monday =
+            first_date - Days::new(u64::from(first_date.weekday().num_days_from_monday()));
+        while monday <= last_date {
+            let iso_week = monday.iso_week();
+            let _ = write!(grid, "{}-W{:02}", iso_week.year(), iso_week.week());
+            for day in 0..7 {
+                let date = monday + Days::new(day);
+                let cell = if self.dates.contains(&date) { 'x' } else { '.' };
+                let _ = write!(grid, "  {}", cell);
+            }
+            grid.push('\n');
+            monday = monday + Days::new(7);
+        }
+        grid
+    }
+
+    /// Enables provenance tracking, tagging the dates not yet tracked with `source`
+    pub fn track_provenance(&mut self, source: &str) {
+        let dates = &self.dates;
+        let provenance = self
+            .metadata
+            .get_or_insert_with(CalendarMetadata::default)
+            .provenance
+            .get_or_insert_with(BTreeMap::new);
+        for date in dates {
+            provenance
+                .entry(*date)
+                .or_insert_with(|| source.to_string());
+        }
+    }
+
+    /// Inserts a date, recording `source` if provenance is tracked.
+    /// The first source contributing a date is kept.
+    pub fn insert_date_from(&mut self, date: Date, source: &str) -> bool {
+        if let Some(provenance) = self.provenance_mut() {
+            provenance.entry(date).or_insert_with(|| source.to_string());
+        }
+        self.dates.insert(date)
+    }
+
+    /// Removes a date and its provenance
+    pub fn remove_date(&mut self, date: &Date) -> bool {
+        if let Some(provenance) = self.provenance_mut() {
+            provenance.remove(date);
+        }
+        self.dates.remove(date)
+    }
+
+    /// Keeps only the dates for which `f` returns true, and their provenance
+    pub fn retain_dates<F>(&mut self, f: F)
+    where
+        F: FnMut(&Date) -> bool,
+    {
+        self.dates.retain(f);
+        let dates = &self.dates;
+        if let Some(provenance) = self
+            .metadata
+            .as_mut()
+            .and_then(|metadata| metadata.provenance.as_mut())
+        {
+            provenance.retain(|date, _| dates.contains(date));
+        }
+    }
+
+    /// Returns the source which contributed the date, if tracked
+    pub fn date_source(&self, date: &Date) -> Option<&str> {
+        self.provenance()
+            .and_then(|provenance| provenance.get(date))
+            .map(String::as_str)
+    }
+
+    /// Returns the dates contributed by `source`
+    pub fn dates_from_source<'a>(&'a self, source: &'a str) -> impl Iterator<Item = &'a Date> {
+        self.provenance()
+            .into_iter()
+            .flat_map(|provenance| provenance.iter())
+            .filter(move |(_, s)| s.as_str() == source)
+            .map(|(date, _)| date)
+    }
+
+    /// Adds the dates of `other`, keeping the provenance of both calendars
+    /// when tracked. Returns the number of added dates.
+    pub fn union_with(&mut self, other: &Self) -> usize {
+        let mut nb_added_dates = 0;
+        for date in &other.dates {
+            if let Some(source) = other.date_source(date) {
+                self.metadata_mut()
+                    .provenance
+                    .get_or_insert_with(BTreeMap::new)
+                    .entry(*date)
+                    .or_insert_with(|| source.to_string());
+            }
+            if self.dates.insert(*date) {
+                nb_added_dates += 1;
+            }
+        }
+        nb_added_dates
+    }
+
+    /// Removes the dates not in `other`. Returns the number of removed dates.
+    pub fn intersect_with(&mut self, other: &Self) -> usize {
+        let removed_dates: Vec<Date> = self.dates.difference(&other.dates).cloned().collect();
+        for date in &removed_dates {
+            self.remove_date(date);
+        }
+        removed_dates.len()
+    }
+
+    /// Removes the dates of `other`. Returns the number of removed dates.
+    pub fn subtract(&mut self, other: &Self) -> usize {
+        let removed_dates: Vec<Date> = self.dates.intersection(&other.dates).cloned().collect();
+        for date in &removed_dates {
+            self.remove_date(date);
+        }
+        removed_dates.len()
+    }
+
+    /// Returns the dates on which both calendars are active, see
+    /// [`Calendar::intersect_with`] to modify the calendar instead
+    pub fn intersect(&self, other: &Self) -> BTreeSet<Date> {
+        self.dates.intersection(&other.dates).copied().collect()
+    }
+
+    /// Returns the dates on which at least one of the calendars is active
+    pub fn union(&self, other: &Self) -> BTreeSet<Date> {
+        self.dates.union(&other.dates).copied().collect()
+    }
+
+    /// Returns the dates on which the calendar is active but not `other`
+    pub fn difference(&self, other: &Self) -> BTreeSet<Date> {
+        self.dates.difference(&other.dates).copied().collect()
+    }
 }
 
 impl AddPrefix for Calendar {
@@ -1445,6 +1936,22 @@ impl WithId for Calendar {
         }
     }
 }
+
+/// Time window of a service, used by the fare rules (GTFS fares v2)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Timeframe {
+    pub timeframe_group_id: String,
+    pub start_time: Option<Time>,
+    pub end_time: Option<Time>,
+    pub service_id: String,
+}
+
+impl AddPrefix for Timeframe {
+    fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.timeframe_group_id = prefix_conf.referential_prefix(self.timeframe_group_id.as_str());
+        self.service_id = prefix_conf.schedule_prefix(self.service_id.as_str());
+    }
+}
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum CompanyRole {
@@ -1661,6 +2168,16 @@ pub struct Transfer {
     pub equipment_id: Option<String>,
 }
 
+impl Transfer {
+    /// Transfer time marking a transfer as not possible (GTFS `transfer_type=3`)
+    pub const NOT_POSSIBLE_TRANSFER_TIME: u32 = 86400;
+
+    /// Returns false if the transfer is marked as not possible
+    pub fn is_possible(&self) -> bool {
+        self.min_transfer_time != Some(Self::NOT_POSSIBLE_TRANSFER_TIME)
+    }
+}
+
 impl AddPrefix for Transfer {
     fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
         self.from_stop_id = prefix_conf.referential_prefix(self.from_stop_id.as_str());
@@ -2166,6 +2683,30 @@ impl AddPrefix for Occupancy {
     }
 }
 
+/// Translation of a field of an object in a language, as in GTFS
+/// translations.txt
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Translation {
+    pub object_type: ObjectType,
+    pub object_id: String,
+    /// Name of the translated field of the object, like `name`
+    pub field_name: String,
+    /// IETF BCP 47 language code
+    pub language: String,
+    pub translation: String,
+}
+
+impl AddPrefix for Translation {
+    fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.object_id = match self.object_type {
+            ObjectType::VehicleJourney | ObjectType::StopTime => {
+                prefix_conf.schedule_prefix(self.object_id.as_str())
+            }
+            _ => prefix_conf.referential_prefix(self.object_id.as_str()),
+        };
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2442,4 +2983,233 @@ mod tests {
             vehicle_journey.sort_and_check_stop_times().unwrap();
         }
     }
+
+    mod calendar {
+        use super::*;
+
+        fn calendar(days: &[u32]) -> Calendar {
+            let mut calendar = Calendar::new("c".to_string());
+            for day in days {
+                calendar
+                    .dates
+                    .insert(Date::from_ymd_opt(2020, 1, *day).unwrap());
+            }
+            calendar
+        }
+
+        #[test]
+        fn active_on_all_or_any() {
+            let date = |day| Date::from_ymd_opt(2020, 1, day).unwrap();
+            let calendar = calendar(&[1, 2, 5]);
+            assert!(calendar.active_on_all(&[date(1), date(5)]));
+            assert!(!calendar.active_on_all(&[date(1), date(3)]));
+            assert!(calendar.active_on_all(&[]));
+            assert!(calendar.active_on_any(&[date(3), date(5)]));
+            assert!(!calendar.active_on_any(&[date(3), date(10)]));
+            assert!(!calendar.active_on_any(&[]));
+        }
+
+        #[test]
+        fn representative_week() {
+            let date = |day| Some(Date::from_ymd_opt(2020, 1, day).unwrap());
+            assert_eq!(None, calendar(&[]).representative_week());
+            // Mondays and Wednesdays, except the first week
+            let week = calendar(&[1, 6, 8, 13, 15, 20]).representative_week();
+            assert_eq!(Some([date(6), None, date(8), None, None, None, None]), week);
+        }
+
+        #[test]
+        fn is_daily() {
+            assert!(!calendar(&[]).is_daily());
+            assert!(calendar(&[3]).is_daily());
+            assert!(calendar(&[3, 4, 5, 6]).is_daily());
+            assert!(!calendar(&[3, 4, 6]).is_daily());
+        }
+
+        #[test]
+        fn jaccard_similarity() {
+            assert_relative_eq!(calendar(&[]).jaccard_similarity(&calendar(&[])), 1.0);
+            assert_relative_eq!(calendar(&[]).jaccard_similarity(&calendar(&[1])), 0.0);
+            assert_relative_eq!(calendar(&[1, 2]).jaccard_similarity(&calendar(&[3])), 0.0);
+            assert_relative_eq!(
+                calendar(&[1, 2, 3]).jaccard_similarity(&calendar(&[2, 3, 4])),
+                0.5
+            );
+            assert_relative_eq!(
+                calendar(&[1, 2]).jaccard_similarity(&calendar(&[1, 2])),
+                1.0
+            );
+        }
+
+        #[test]
+        fn set_operations_count_modified_dates() {
+            let mut union = calendar(&[1, 2]);
+            assert_eq!(1, union.union_with(&calendar(&[2, 3])));
+            assert_eq!(calendar(&[1, 2, 3]).dates, union.dates);
+            assert_eq!(0, union.union_with(&calendar(&[1])));
+
+            let mut intersection = calendar(&[1, 2, 3]);
+            assert_eq!(2, intersection.intersect_with(&calendar(&[2, 4])));
+            assert_eq!(calendar(&[2]).dates, intersection.dates);
+
+            let mut difference = calendar(&[1, 2, 3]);
+            assert_eq!(2, difference.subtract(&calendar(&[2, 3, 4])));
+            assert_eq!(calendar(&[1]).dates, difference.dates);
+            assert_eq!(0, difference.subtract(&calendar(&[5])));
+        }
+
+        #[test]
+        fn retain_dates_with_provenance() {
+            let date = |day| Date::from_ymd_opt(2020, 1, day).unwrap();
+            let mut tracked = calendar(&[1, 2, 3]);
+            tracked.track_provenance("feed_a");
+            tracked.retain_dates(|date| date.day() != 2);
+            assert_eq!(calendar(&[1, 3]).dates, tracked.dates);
+            assert_eq!(None, tracked.date_source(&date(2)));
+            assert_eq!(
+                vec![&date(1), &date(3)],
+                tracked.dates_from_source("feed_a").collect::<Vec<_>>()
+            );
+            // The provenance is not part of the equality
+            assert_eq!(calendar(&[1, 3]), tracked);
+        }
+
+        #[test]
+        fn set_algebra() {
+            let line_a = calendar(&[1, 2, 3, 4]);
+            let line_b = calendar(&[3, 4, 5]);
+            assert_eq!(calendar(&[3, 4]).dates, line_a.intersect(&line_b));
+            assert_eq!(calendar(&[1, 2, 3, 4, 5]).dates, line_a.union(&line_b));
+            assert_eq!(calendar(&[1, 2]).dates, line_a.difference(&line_b));
+            assert_eq!(calendar(&[5]).dates, line_b.difference(&line_a));
+            assert_eq!(calendar(&[1, 2, 3, 4]).dates, line_a.dates);
+        }
+
+        #[test]
+        fn runs_every_day_in() {
+            let period = |start_day, end_day| ValidityPeriod {
+                start_date: Date::from_ymd_opt(2020, 1, start_day).unwrap(),
+                end_date: Date::from_ymd_opt(2020, 1, end_day).unwrap(),
+            };
+            let calendar = calendar(&[1, 2, 3, 5, 6]);
+            assert!(calendar.runs_every_day_in(&period(1, 3)));
+            assert!(calendar.runs_every_day_in(&period(5, 5)));
+            assert!(!calendar.runs_every_day_in(&period(2, 5)));
+            assert!(!calendar.runs_every_day_in(&period(5, 7)));
+            assert!(calendar.runs_every_day_in(&period(3, 2)));
+        }
+
+        #[test]
+        fn snap_to_full_weeks() {
+            // Mondays and Wednesdays from 2020-01-06, missing 2020-01-15
+            let mut snapped = calendar(&[6, 8, 13, 20, 22, 27, 29]);
+            snapped.snap_to_full_weeks();
+            assert_eq!(
+                calendar(&[6, 8, 13, 15, 20, 22, 27, 29]).dates,
+                snapped.dates
+            );
+        }
+
+        #[test]
+        fn longest_run() {
+            let date = |day| Date::from_ymd_opt(2020, 1, day).unwrap();
+            assert_eq!(None, calendar(&[]).longest_run());
+            assert_eq!(
+                vec![(date(1), date(2)), (date(4), date(6)), (date(8), date(8))],
+                calendar(&[1, 2, 4, 5, 6, 8]).contiguous_ranges()
+            );
+            assert_eq!(
+                Some((date(4), date(6), 3)),
+                calendar(&[1, 2, 4, 5, 6, 8]).longest_run()
+            );
+            // The earliest run wins on ties
+            assert_eq!(
+                Some((date(1), date(2), 2)),
+                calendar(&[1, 2, 4, 5]).longest_run()
+            );
+            // Runs across months
+            let mut calendar = calendar(&[30, 31]);
+            calendar
+                .dates
+                .insert(Date::from_ymd_opt(2020, 2, 1).unwrap());
+            assert_eq!(
+                Some((date(30), Date::from_ymd_opt(2020, 2, 1).unwrap(), 3)),
+                calendar.longest_run()
+            );
+        }
+    }
+
+    mod on_service_day {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn resolve(time: &str, date: &str) -> String {
+            let time: Time = time.parse().unwrap();
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            time.on_service_day(date, chrono_tz::Europe::Paris)
+                .unwrap()
+                .to_rfc3339()
+        }
+
+        #[test]
+        fn after_midnight() {
+            assert_eq!(
+                "2020-03-27T08:00:00+01:00",
+                resolve("08:00:00", "2020-03-27")
+            );
+            assert_eq!(
+                "2020-03-28T01:30:00+01:00",
+                resolve("25:30:00", "2020-03-27")
+            );
+            // the next day is the spring-forward day
+            assert_eq!(
+                "2020-03-29T01:30:00+01:00",
+                resolve("25:30:00", "2020-03-28")
+            );
+            assert_eq!(
+                "2020-03-29T04:30:00+02:00",
+                resolve("27:30:00", "2020-03-28")
+            );
+        }
+
+        #[test]
+        fn spring_forward_day() {
+            // times are offsets from 23:00 of the previous day
+            assert_eq!(
+                "2020-03-29T00:00:00+01:00",
+                resolve("01:00:00", "2020-03-29")
+            );
+            // the nonexistent wall-clock 02:30 is never produced
+            assert_eq!(
+                "2020-03-29T01:30:00+01:00",
+                resolve("02:30:00", "2020-03-29")
+            );
+            assert_eq!(
+                "2020-03-29T08:00:00+02:00",
+                resolve("08:00:00", "2020-03-29")
+            );
+        }
+
+        #[test]
+        fn fall_back_day() {
+            // times are offsets from 01:00 of the day
+            assert_eq!(
+                "2020-10-25T01:00:00+02:00",
+                resolve("00:00:00", "2020-10-25")
+            );
+            // the ambiguous wall-clock 02:30 is reached twice
+            assert_eq!(
+                "2020-10-25T02:30:00+02:00",
+                resolve("01:30:00", "2020-10-25")
+            );
+            assert_eq!(
+                "2020-10-25T02:30:00+01:00",
+                resolve("02:30:00", "2020-10-25")
+            );
+            assert_eq!(
+                "2020-10-25T08:00:00+01:00",
+                resolve("08:00:00", "2020-10-25")
+            );
+        }
+    }
 }
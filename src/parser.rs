@@ -16,9 +16,91 @@
 use crate::{file_handler::FileHandler, Result};
 use anyhow::{anyhow, bail, Context};
 use skip_error::SkipError;
+use std::io::{BufRead, BufReader, Read};
 use tracing::info;
 use typed_index_collection::{CollectionWithId, Id};
 
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+// Delimiter of the header line: a comma, unless there is none but some
+// semicolons or tabulations
+fn detect_delimiter(header: &[u8]) -> u8 {
+    if header.contains(&b',') {
+        return b',';
+    }
+    let count = |delimiter: u8| header.iter().filter(|byte| **byte == delimiter).count();
+    match (count(b';'), count(b'\t')) {
+        (0, 0) => b',',
+        (semicolons, tabulations) if semicolons >= tabulations => b';',
+        _ => b'\t',
+    }
+}
+
+// Deserialize all the rows like `deserialize_objects`, skipping a leading
+// UTF-8 BOM and detecting the delimiter from the header line when not given
+fn deserialize_sniffed_objects<R, O>(
+    reader: R,
+    path: &std::path::Path,
+    delimiter: Option<u8>,
+) -> Result<Vec<O>>
+where
+    R: Read,
+    O: for<'de> serde::Deserialize<'de>,
+{
+    let mut reader = BufReader::new(reader);
+    let mut header = vec![];
+    reader
+        .read_until(b'\n', &mut header)
+        .with_context(|| format!("Error reading {:?}", path))?;
+    if header.starts_with(UTF8_BOM) {
+        header.drain(..UTF8_BOM.len());
+    }
+    let delimiter = delimiter.unwrap_or_else(|| detect_delimiter(&header));
+    deserialize_objects(header.as_slice().chain(reader), path, delimiter)
+}
+
+// Deserialize all the rows, the error on a malformed row giving its line
+// number and the value of its first column (usually its identifier)
+fn deserialize_objects<R, O>(reader: R, path: &std::path::Path, delimiter: u8) -> Result<Vec<O>>
+where
+    R: Read,
+    O: for<'de> serde::Deserialize<'de>,
+{
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let headers = rdr
+        .byte_headers()
+        .with_context(|| format!("Error reading {:?}", path))?
+        .clone();
+    let mut objects = vec![];
+    for record in rdr.byte_records() {
+        let record = record.with_context(|| format!("Error reading {:?}", path))?;
+        match record.deserialize(Some(&headers)) {
+            Ok(object) => objects.push(object),
+            Err(error) => {
+                let line = record.position().map_or(0, csv::Position::line);
+                let key = headers
+                    .get(0)
+                    .zip(record.get(0))
+                    .map(|(header, value)| {
+                        format!(
+                            " ({} {:?})",
+                            String::from_utf8_lossy(header),
+                            String::from_utf8_lossy(value)
+                        )
+                    })
+                    .unwrap_or_default();
+                return Err(anyhow::Error::new(error)
+                    .context(format!("Error reading {:?} at line {}{}", path, line, key)));
+            }
+        }
+    }
+    Ok(objects)
+}
+
 /// Read a vector of objects from a zip in a file_handler
 pub fn read_objects<H, O>(
     file_handler: &mut H,
@@ -43,15 +125,68 @@ where
         }
         (Some(reader), _) => {
             info!(file_name = %basename, "Reading");
-            let mut rdr = csv::ReaderBuilder::new()
-                .flexible(true)
-                .trim(csv::Trim::All)
-                .from_reader(reader);
-            Ok(rdr
-                .deserialize()
-                .collect::<Result<_, _>>()
-                .with_context(|| format!("Error reading {:?}", path))?)
+            deserialize_objects(reader, &path, b',')
+        }
+    }
+}
+
+/// Options of [`read_objects_with_options`], which also skips a leading UTF-8
+/// BOM
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReadOptions {
+    /// Encoding of the file, transcoded to UTF-8 before parsing it. UTF-8 when
+    /// not set.
+    pub encoding: Option<&'static encoding_rs::Encoding>,
+    /// Delimiter of the fields, detected from the header when not set
+    pub delimiter: Option<u8>,
+}
+
+/// Read a vector of objects from a zip in a file_handler, with the given
+/// options
+pub fn read_objects_with_options<H, O>(
+    file_handler: &mut H,
+    file_name: &str,
+    required_file: bool,
+    options: &ReadOptions,
+) -> Result<Vec<O>>
+where
+    for<'a> &'a mut H: FileHandler,
+    O: for<'de> serde::Deserialize<'de>,
+{
+    let (reader, path) = file_handler.get_file_if_exists(file_name)?;
+    let file_name = path.file_name();
+    let basename = file_name.map_or(path.to_string_lossy(), |b| b.to_string_lossy());
+
+    match (reader, required_file) {
+        (None, false) => {
+            info!(file_name = %basename, "Skipping");
+            Ok(vec![])
+        }
+        (None, true) => {
+            bail!("file {:?} not found", path)
         }
+        (Some(mut reader), _) => match options.encoding {
+            Some(encoding) => {
+                info!(file_name = %basename, encoding = encoding.name(), "Reading");
+                let mut bytes = vec![];
+                reader
+                    .read_to_end(&mut bytes)
+                    .with_context(|| format!("Error reading {:?}", path))?;
+                let (content, _, had_errors) = encoding.decode(&bytes);
+                if had_errors {
+                    bail!(
+                        "Error reading {:?}: invalid {} content",
+                        path,
+                        encoding.name()
+                    );
+                }
+                deserialize_sniffed_objects(content.as_bytes(), &path, options.delimiter)
+            }
+            None => {
+                info!(file_name = %basename, "Reading");
+                deserialize_sniffed_objects(reader, &path, options.delimiter)
+            }
+        },
     }
 }
 
@@ -40,6 +40,39 @@ where
     }
 }
 
+/// deserialize u8 as bool, also accepting integer valued floats like `1.0`
+/// returns an error if non boolean value
+pub fn de_from_u8_or_float<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::{
+        de::{Error, Unexpected::Other},
+        Deserialize,
+    };
+    let s = <String as Deserialize<'de>>::deserialize(deserializer)?;
+    parse_u8_or_float(s.trim()).ok_or_else(|| {
+        D::Error::invalid_value(Other(&format!("'{}' non boolean value", s)), &"boolean")
+    })
+}
+
+// `0`/`1`, or an integer valued float like `1.0`, as bool
+fn parse_u8_or_float(value: &str) -> Option<bool> {
+    let flag = match value.parse::<u8>() {
+        Ok(i) => Some(i),
+        Err(_) => value
+            .parse::<f64>()
+            .ok()
+            .filter(|f| *f == 0.0 || *f == 1.0)
+            .map(|f| f as u8),
+    };
+    match flag {
+        Some(0) => Some(false),
+        Some(1) => Some(true),
+        _ => None,
+    }
+}
+
 /// deserialize optional u8 as Option<bool>
 /// returns an error if non boolean value
 pub fn de_opt_bool_from_str<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
@@ -62,6 +95,53 @@ where
     }
 }
 
+/// deserialize a boolean-ish value as bool: `true`/`false`, `yes`/`no`
+/// (case-insensitive) or any value accepted by [`de_from_u8_or_float`], an
+/// empty value giving `default`
+/// returns an error if non boolean value
+pub fn de_from_bool_flexible_with_default<'de, D>(
+    deserializer: D,
+    default: bool,
+) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::{
+        de::{Error, Unexpected::Other},
+        Deserialize,
+    };
+    let s = <String as Deserialize<'de>>::deserialize(deserializer)?;
+    let value = s.trim();
+    match value.to_lowercase().as_str() {
+        "" => Ok(default),
+        "true" | "yes" => Ok(true),
+        "false" | "no" => Ok(false),
+        _ => parse_u8_or_float(value).ok_or_else(|| {
+            D::Error::invalid_value(Other(&format!("'{}' non boolean value", s)), &"boolean")
+        }),
+    }
+}
+
+/// deserialize a boolean-ish value as bool (see
+/// [`de_from_bool_flexible_with_default`]), an empty value being false
+/// returns an error if non boolean value
+pub fn de_from_bool_flexible<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    de_from_bool_flexible_with_default(deserializer, false)
+}
+
+/// deserialize a boolean-ish value as bool (see
+/// [`de_from_bool_flexible_with_default`]), an empty value being true
+/// returns an error if non boolean value
+pub fn de_from_bool_flexible_with_true_default<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    de_from_bool_flexible_with_default(deserializer, true)
+}
+
 /// deserialize u8 as bool
 /// returns true if non boolean value
 pub fn de_from_u8_with_true_default<'de, D>(deserializer: D) -> Result<bool, D::Error>
@@ -118,6 +198,31 @@ where
     serializer.serialize_str(&s)
 }
 
+/// deserialize an optional date from String, empty meaning no date
+pub fn de_option_date_string<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::IntoDeserializer;
+    use serde::Deserialize;
+    Option::<String>::deserialize(deserializer)?
+        .filter(|s| !s.is_empty())
+        .map(|s| de_from_date_string(s.into_deserializer()))
+        .transpose()
+}
+
+/// serialize an optional naive date to String, empty if no date
+// The signature of the function must pass by reference for 'serde' to be able to use the function
+pub fn ser_option_naive_date<S>(date: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match date {
+        Some(date) => ser_from_naive_date(date, serializer),
+        None => serializer.serialize_str(""),
+    }
+}
+
 /// deserialize type T or returns its default value
 pub fn de_with_empty_default<'de, T, D>(de: D) -> Result<T, D::Error>
 where
@@ -328,6 +433,81 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    mod serde_u8_or_float {
+        use super::*;
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize)]
+        struct WithFlag {
+            #[serde(deserialize_with = "de_from_u8_or_float")]
+            flag: bool,
+        }
+
+        fn de(value: &str) -> Result<bool, serde_json::Error> {
+            let json = format!(r#"{{"flag": "{}"}}"#, value);
+            serde_json::from_str::<WithFlag>(&json).map(|object| object.flag)
+        }
+
+        #[test]
+        fn accepted_values() {
+            assert!(de("1").unwrap());
+            assert!(!de("0").unwrap());
+            assert!(de("1.0").unwrap());
+            assert!(!de("0.0").unwrap());
+            assert!(de(" 1.00 ").unwrap());
+        }
+
+        #[test]
+        fn rejected_values() {
+            assert!(de("0.5").is_err());
+            assert!(de("2").is_err());
+            assert!(de("2.0").is_err());
+            assert!(de("-1.0").is_err());
+            assert!(de("").is_err());
+            assert!(de("true").is_err());
+        }
+    }
+
+    mod serde_bool_flexible {
+        use super::*;
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize)]
+        struct WithFlags {
+            #[serde(deserialize_with = "de_from_bool_flexible")]
+            flag: bool,
+            #[serde(deserialize_with = "de_from_bool_flexible_with_true_default")]
+            flag_with_true_default: bool,
+        }
+
+        fn de(value: &str) -> Result<(bool, bool), serde_json::Error> {
+            let json = format!(
+                r#"{{"flag": "{0}", "flag_with_true_default": "{0}"}}"#,
+                value
+            );
+            serde_json::from_str::<WithFlags>(&json)
+                .map(|object| (object.flag, object.flag_with_true_default))
+        }
+
+        #[test]
+        fn accepted_values() {
+            for value in ["1", "1.0", "true", "TRUE", "Yes", " yes "] {
+                assert_eq!((true, true), de(value).unwrap());
+            }
+            for value in ["0", "0.0", "false", "False", "NO"] {
+                assert_eq!((false, false), de(value).unwrap());
+            }
+            assert_eq!((false, true), de("").unwrap());
+        }
+
+        #[test]
+        fn rejected_values() {
+            assert!(de("2").is_err());
+            assert!(de("0.5").is_err());
+            assert!(de("y").is_err());
+        }
+    }
+
     mod serde_option_string {
         use super::*;
         use pretty_assertions::assert_eq;
@@ -17,7 +17,7 @@ use anyhow::Context;
 use skip_error::skip_error_and_warn;
 use std::{
     fs,
-    io::{Read, Write},
+    io::{self, Read, Seek, Write},
     path,
 };
 use tracing::{debug, info};
@@ -96,52 +96,102 @@ where
     Ok(collection)
 }
 
-pub fn write_collection_with_id<T>(
-    path: &path::Path,
+/// Destination of the written files: a directory or the entries of a ZIP
+/// archive.
+pub(crate) trait FileWriter {
+    /// Create the file, which must be completely written before creating
+    /// the next one
+    fn create_file(&mut self, file: &str) -> crate::Result<Box<dyn Write + '_>>;
+}
+
+impl FileWriter for &path::Path {
+    fn create_file(&mut self, file: &str) -> crate::Result<Box<dyn Write + '_>> {
+        let path = self.join(file);
+        let file = fs::File::create(&path).with_context(|| format!("Error reading {:?}", path))?;
+        Ok(Box::new(file))
+    }
+}
+
+impl<F: FileWriter + ?Sized> FileWriter for &mut F {
+    fn create_file(&mut self, file: &str) -> crate::Result<Box<dyn Write + '_>> {
+        (**self).create_file(file)
+    }
+}
+
+/// Write the files as the entries of a ZIP archive, streamed to the
+/// underlying writer.
+pub(crate) struct ZipFileWriter<W: Write + Seek> {
+    zip: zip::ZipWriter<W>,
+    options: zip::write::SimpleFileOptions,
+}
+
+impl<W: Write + Seek> ZipFileWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        ZipFileWriter {
+            zip: zip::ZipWriter::new(writer),
+            options: zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated),
+        }
+    }
+
+    /// Finish the last entry and the archive
+    pub(crate) fn finish(self) -> crate::Result<W> {
+        Ok(self.zip.finish()?)
+    }
+}
+
+impl<W: Write + Seek> FileWriter for ZipFileWriter<W> {
+    fn create_file(&mut self, file: &str) -> crate::Result<Box<dyn Write + '_>> {
+        debug!("adding {:?} ...", file);
+        self.zip.start_file(file, self.options)?;
+        Ok(Box::new(&mut self.zip))
+    }
+}
+
+pub(crate) fn write_collection_with_id<T, F>(
+    mut output: F,
     file: &str,
     collection: &CollectionWithId<T>,
 ) -> crate::Result<()>
 where
     T: Id<T> + serde::Serialize,
+    F: FileWriter,
 {
     if collection.is_empty() {
         return Ok(());
     }
     info!(file_name = %file, "Writing");
-    let path = path.join(file);
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|| format!("Error reading {:?}", path))?;
+    let mut wtr = csv::Writer::from_writer(output.create_file(file)?);
     for obj in collection.values() {
         wtr.serialize(obj)
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file))?;
     }
     wtr.flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
 
     Ok(())
 }
 
-pub fn write_collection<T>(
-    path: &path::Path,
+pub(crate) fn write_collection<T, F>(
+    mut output: F,
     file: &str,
     collection: &Collection<T>,
 ) -> crate::Result<()>
 where
     T: serde::Serialize,
+    F: FileWriter,
 {
     if collection.is_empty() {
         return Ok(());
     }
     info!(file_name = %file, "Writing");
-    let path = path.join(file);
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|| format!("Error reading {:?}", path))?;
+    let mut wtr = csv::Writer::from_writer(output.create_file(file)?);
     for obj in collection.values() {
         wtr.serialize(obj)
-            .with_context(|| format!("Error reading {:?}", path))?;
+            .with_context(|| format!("Error reading {:?}", file))?;
     }
     wtr.flush()
-        .with_context(|| format!("Error reading {:?}", path))?;
+        .with_context(|| format!("Error reading {:?}", file))?;
 
     Ok(())
 }
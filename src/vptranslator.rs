@@ -43,12 +43,16 @@ fn get_prev_monday(date: Date) -> Date {
     date - Days::new(u64::from(date.weekday().num_days_from_monday()))
 }
 
+fn get_week_bit(date: Date) -> u8 {
+    1 << (7 - date.weekday().number_from_monday())
+}
+
 fn compute_validity_pattern(start_date: Date, end_date: Date, dates: &BTreeSet<Date>) -> Vec<u8> {
     let length = (end_date.signed_duration_since(start_date).num_weeks() + 1) as usize;
     let mut res = vec![0; length];
     for date in dates {
         let w = date.signed_duration_since(start_date).num_weeks() as usize;
-        res[w] |= 1 << (7 - date.weekday().number_from_monday());
+        res[w] |= get_week_bit(*date);
     }
     res
 }
@@ -109,21 +113,141 @@ fn clean_extra_dates(start_date: Date, end_date: Date, dates: &mut Vec<Exception
     dates.retain(|d| d.date >= start_date && d.date <= end_date);
 }
 
+/// Minimum number of exceptions a weekly pattern restricted to a window has to
+/// save, a window saving a single exception only moving it to calendar.txt
+const MIN_WINDOW_GAIN: usize = 2;
+
+///Finds, among the candidate weekly patterns, the pattern and the window of
+///days where it saves the most exceptions compared to adding every date:
+///each active date of the pattern in the window saves an exception, each
+///inactive one costs a removal.
+///
+///Returns the pattern, the window and the number of saved exceptions.
+fn get_best_window(
+    start_date: Date,
+    end_date: Date,
+    dates: &BTreeSet<Date>,
+    patterns: &BTreeSet<u8>,
+) -> Option<(u8, ValidityPeriod, usize)> {
+    let nb_days = end_date.signed_duration_since(start_date).num_days() as u64 + 1;
+    let mut best: Option<(u8, ValidityPeriod, usize)> = None;
+    for &pattern in patterns {
+        // Maximum subarray of the gains of the days
+        let mut gain: i64 = 0;
+        let mut window_start = start_date;
+        for offset in 0..nb_days {
+            let date = start_date + Days::new(offset);
+            if gain <= 0 {
+                gain = 0;
+                window_start = date;
+            }
+            if pattern & get_week_bit(date) != 0 {
+                gain += if dates.contains(&date) { 1 } else { -1 };
+            }
+            if gain > 0
+                && best
+                    .as_ref()
+                    .map_or(true, |(_, _, best_gain)| gain as usize > *best_gain)
+            {
+                let window = ValidityPeriod {
+                    start_date: window_start,
+                    end_date: date,
+                };
+                best = Some((pattern, window, gain as usize));
+            }
+        }
+    }
+    best
+}
+
+fn translate_in_window(
+    dates: &BTreeSet<Date>,
+    pattern: u8,
+    window: ValidityPeriod,
+) -> BlockPattern {
+    let mut exceptions = Vec::new();
+    if let (Some(start_date), Some(end_date)) = (dates.iter().next(), dates.iter().next_back()) {
+        let nb_days = end_date.signed_duration_since(*start_date).num_days() as u64 + 1;
+        for offset in 0..nb_days {
+            let date = *start_date + Days::new(offset);
+            let expected = window.start_date <= date
+                && date <= window.end_date
+                && pattern & get_week_bit(date) != 0;
+            let exception_type = match (dates.contains(&date), expected) {
+                (true, false) => ExceptionType::Add,
+                (false, true) => ExceptionType::Remove,
+                _ => continue,
+            };
+            exceptions.push(ExceptionDate {
+                date,
+                exception_type,
+            });
+        }
+    }
+    BlockPattern {
+        operating_days: get_operating_days(pattern),
+        validity_period: Some(window),
+        exceptions,
+    }
+}
+
 ///Allows you to present a list of dates in a readable way.
+///
+///The weekly pattern is applied on the whole span of the dates, unless
+///restricting it to a narrower window gives strictly fewer exceptions (for
+///example when the service stops for a few months before a last isolated
+///date). The validity period is then that window, the dates outside of it
+///being added as exceptions.
 pub fn translate(dates: &BTreeSet<Date>) -> BlockPattern {
     let start_date = match dates.iter().next() {
         Some(d) => *d,
         None => return BlockPattern::default(),
     };
     let end_date: Date = *dates.iter().next_back().unwrap();
-
-    let mut monday_ref = get_prev_monday(start_date);
+    let monday_ref = get_prev_monday(start_date);
     let validity_pattern = compute_validity_pattern(monday_ref, end_date, dates);
     let best_week = get_min_week_pattern(&validity_pattern);
+    let block_pattern = translate_whole_span(
+        start_date,
+        end_date,
+        monday_ref,
+        &validity_pattern,
+        best_week,
+    );
+    if block_pattern.exceptions.is_empty() {
+        return block_pattern;
+    }
+    // Only the best week pattern of the whole span and the patterns of the
+    // weeks of the dates are tried, not every combination of days
+    let mut patterns: BTreeSet<u8> = validity_pattern
+        .iter()
+        .copied()
+        .filter(|week| *week != 0)
+        .collect();
+    if best_week != 0 {
+        patterns.insert(best_week);
+    }
+    match get_best_window(start_date, end_date, dates, &patterns) {
+        Some((pattern, window, gain))
+            if gain >= MIN_WINDOW_GAIN && dates.len() - gain < block_pattern.exceptions.len() =>
+        {
+            translate_in_window(dates, pattern, window)
+        }
+        _ => block_pattern,
+    }
+}
+
+fn translate_whole_span(
+    start_date: Date,
+    end_date: Date,
+    mut monday_ref: Date,
+    validity_pattern: &[u8],
+    best_week: u8,
+) -> BlockPattern {
     let operating_days = get_operating_days(best_week);
     let mut exceptions_list = Vec::new();
 
-    for week in validity_pattern {
+    for &week in validity_pattern {
         if week != best_week {
             let exception: u8 = (!best_week) & week;
             fill_exceptions(
@@ -154,6 +278,136 @@ pub fn translate(dates: &BTreeSet<Date>) -> BlockPattern {
     }
 }
 
+/// Maximum number of weeks between two active weeks detected by
+/// [`translate_with_period_detection`]
+pub const MAX_WEEK_PERIOD: usize = 4;
+
+///Presents a list of dates as a weekly pattern only active one week out of
+///`period`, and exception dates.
+#[derive(Default, Debug)]
+pub struct PeriodicBlockPattern {
+    ///Number of weeks between two active weeks, 1 for a weekly pattern
+    pub period: usize,
+    ///Monday of the first week using the pattern, the next ones being every
+    ///`period` weeks
+    pub first_active_week: Option<Date>,
+    ///Operating days of the active weeks, validity period and exceptions
+    pub block_pattern: BlockPattern,
+}
+
+fn periodic_exceptions(
+    monday_ref: Date,
+    weeks: &[u8],
+    period: usize,
+    phase: usize,
+    week_pattern: u8,
+    validity_period: &ValidityPeriod,
+) -> Vec<ExceptionDate> {
+    let mut exceptions = Vec::new();
+    for (i, week) in weeks.iter().enumerate() {
+        let expected = if i % period == phase { week_pattern } else { 0 };
+        let monday = monday_ref + Days::new(7 * i as u64);
+        fill_exceptions(
+            monday,
+            !expected & week,
+            ExceptionType::Add,
+            &mut exceptions,
+        );
+        fill_exceptions(
+            monday,
+            (week ^ expected) & expected,
+            ExceptionType::Remove,
+            &mut exceptions,
+        );
+    }
+    clean_extra_dates(
+        validity_period.start_date,
+        validity_period.end_date,
+        &mut exceptions,
+    );
+    exceptions
+}
+
+///Same as [`translate`], also detecting the weekly patterns only active one
+///week out of 2 up to [`MAX_WEEK_PERIOD`] (for example every other Monday).
+///
+///A pattern with a period greater than 1 is only returned when it has
+///strictly fewer exceptions than the weekly pattern of [`translate`], which is
+///returned otherwise with a period of 1.
+pub fn translate_with_period_detection(dates: &BTreeSet<Date>) -> PeriodicBlockPattern {
+    let weekly = translate(dates);
+    // The weekly pattern may only be applied on a part of the dates
+    let validity_period = match (dates.iter().next(), dates.iter().next_back()) {
+        (Some(start_date), Some(end_date)) => ValidityPeriod {
+            start_date: *start_date,
+            end_date: *end_date,
+        },
+        _ => {
+            return PeriodicBlockPattern {
+                period: 1,
+                first_active_week: None,
+                block_pattern: weekly,
+            }
+        }
+    };
+    let monday_ref = get_prev_monday(validity_period.start_date);
+    let weeks = compute_validity_pattern(monday_ref, validity_period.end_date, dates);
+    let mut best: Option<(usize, usize, u8, Vec<ExceptionDate>)> = None;
+    let mut best_nb_exceptions = weekly.exceptions.len();
+    for period in 2..=MAX_WEEK_PERIOD {
+        for phase in 0..period.min(weeks.len()) {
+            // Each day of the pattern is chosen independently: it is only
+            // operating if active on most of the active weeks
+            let mut week_pattern = 0u8;
+            for day in 0..7u64 {
+                let bit = 1 << (6 - day);
+                let (nb_active, nb_days) = weeks
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| i % period == phase)
+                    .filter(|(i, _)| {
+                        let date = monday_ref + Days::new(7 * *i as u64 + day);
+                        date >= validity_period.start_date && date <= validity_period.end_date
+                    })
+                    .fold((0, 0), |(nb_active, nb_days), (_, week)| {
+                        (nb_active + usize::from(week & bit != 0), nb_days + 1)
+                    });
+                if 2 * nb_active > nb_days {
+                    week_pattern |= bit;
+                }
+            }
+            let exceptions = periodic_exceptions(
+                monday_ref,
+                &weeks,
+                period,
+                phase,
+                week_pattern,
+                &validity_period,
+            );
+            if exceptions.len() < best_nb_exceptions {
+                best_nb_exceptions = exceptions.len();
+                best = Some((period, phase, week_pattern, exceptions));
+            }
+        }
+    }
+    match best {
+        Some((period, phase, week_pattern, exceptions)) => PeriodicBlockPattern {
+            period,
+            first_active_week: Some(monday_ref + Days::new(7 * phase as u64)),
+            block_pattern: BlockPattern {
+                operating_days: get_operating_days(week_pattern),
+                validity_period: Some(validity_period),
+                exceptions,
+            },
+        },
+        None => PeriodicBlockPattern {
+            period: 1,
+            first_active_week: Some(monday_ref),
+            block_pattern: weekly,
+        },
+    }
+}
+
 //       July 2012
 // Mo Tu We Th Fr Sa Su
 //                    1
@@ -651,4 +905,84 @@ mod tests {
             res.validity_period.unwrap()
         )
     }
+
+    #[test]
+    fn every_other_week() {
+        let mut dates = BTreeSet::new();
+        // Mondays, every other week
+        for day in [2, 16, 30] {
+            dates.insert(Date::from_ymd_opt(2012, 7, day).unwrap());
+        }
+        dates.insert(Date::from_ymd_opt(2012, 8, 13).unwrap());
+        assert_eq!(3, translate(&dates).exceptions.len());
+
+        let res = translate_with_period_detection(&dates);
+        assert_eq!(2, res.period);
+        assert_eq!(Date::from_ymd_opt(2012, 7, 2), res.first_active_week);
+        assert_eq!(vec![Weekday::Mon], res.block_pattern.operating_days);
+        assert!(res.block_pattern.exceptions.is_empty());
+    }
+
+    #[test]
+    fn period_detection_keeps_weekly_patterns() {
+        let mut dates = BTreeSet::new();
+        // Mondays, 2012-07-16 excepted, and Wednesday 2012-07-25
+        for day in [2, 9, 23, 25, 30] {
+            dates.insert(Date::from_ymd_opt(2012, 7, day).unwrap());
+        }
+        let weekly = translate(&dates);
+
+        let res = translate_with_period_detection(&dates);
+        assert_eq!(1, res.period);
+        assert_eq!(weekly.operating_days, res.block_pattern.operating_days);
+        assert_eq!(weekly.exceptions, res.block_pattern.exceptions);
+        assert_eq!(weekly.validity_period, res.block_pattern.validity_period);
+
+        let res = translate_with_period_detection(&BTreeSet::new());
+        assert_eq!(1, res.period);
+        assert_eq!(None, res.first_active_week);
+    }
+
+    #[test]
+    fn weekly_pattern_restricted_to_a_window() {
+        let mut dates = BTreeSet::new();
+        // 10 Mondays from 2012-07-02 to 2012-09-03, and a last Monday 10
+        // weeks later
+        for week in 0..10 {
+            dates.insert(Date::from_ymd_opt(2012, 7, 2).unwrap() + Days::new(7 * week));
+        }
+        dates.insert(Date::from_ymd_opt(2012, 11, 12).unwrap());
+
+        let res = translate(&dates);
+        assert_eq!(vec![Weekday::Mon], res.operating_days);
+        assert_eq!(
+            ValidityPeriod {
+                start_date: Date::from_ymd_opt(2012, 7, 2).unwrap(),
+                end_date: Date::from_ymd_opt(2012, 9, 3).unwrap(),
+            },
+            res.validity_period.clone().unwrap()
+        );
+        assert_eq!(
+            vec![ExceptionDate {
+                date: Date::from_ymd_opt(2012, 11, 12).unwrap(),
+                exception_type: ExceptionType::Add,
+            }],
+            res.exceptions
+        );
+
+        let validity_period = res.validity_period.unwrap();
+        let mut translated_dates: BTreeSet<Date> = validity_period
+            .start_date
+            .iter_days()
+            .take_while(|date| *date <= validity_period.end_date)
+            .filter(|date| res.operating_days.contains(&date.weekday()))
+            .collect();
+        for exception in res.exceptions {
+            match exception.exception_type {
+                ExceptionType::Add => translated_dates.insert(exception.date),
+                ExceptionType::Remove => translated_dates.remove(&exception.date),
+            };
+        }
+        assert_eq!(dates, translated_dates);
+    }
 }
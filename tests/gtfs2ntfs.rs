@@ -38,6 +38,8 @@ fn test_gtfs() {
             on_demand_transport: false,
             on_demand_transport_comment: None,
             read_as_line: false,
+            stop_times_order: gtfs::StopTimesOrder::Warn,
+            calendar_configuration: Default::default(),
         };
         let model = transit_model::gtfs::Reader::new(configuration)
             .parse(input_dir)
@@ -66,6 +68,8 @@ fn test_gtfs_with_description() {
             on_demand_transport: false,
             on_demand_transport_comment: None,
             read_as_line: false,
+            stop_times_order: gtfs::StopTimesOrder::Warn,
+            calendar_configuration: Default::default(),
         };
         let model = transit_model::gtfs::Reader::new(configuration)
             .parse(input_dir)
@@ -175,6 +179,8 @@ fn test_minimal_gtfs_with_odt_comment() {
                 "Service à réservation {agency_name} {agency_phone}".to_string(),
             ),
             read_as_line: false,
+            stop_times_order: gtfs::StopTimesOrder::Warn,
+            calendar_configuration: Default::default(),
         };
         let model = transit_model::gtfs::Reader::new(configuration)
             .parse(input_dir)
@@ -204,6 +210,8 @@ fn test_minimal_gtfs_frequencies_with_odt_comment() {
                 "Service à réservation {agency_name} {agency_phone}".to_string(),
             ),
             read_as_line: false,
+            stop_times_order: gtfs::StopTimesOrder::Warn,
+            calendar_configuration: Default::default(),
         };
 
         let model = transit_model::gtfs::Reader::new(configuration)
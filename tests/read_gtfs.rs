@@ -69,3 +69,30 @@ fn directory_not_a_gtfs() {
     // reading a directory that does not contain the gtfs files will lead to an error
     let _ = transit_model::gtfs::read("tests/fixtures/netex_france").unwrap();
 }
+
+#[test]
+fn gtfs_with_service_id_normalizer_reading() {
+    let calendar_configuration = transit_model::calendars::ReadConfiguration {
+        service_id_normalizer: Some(std::sync::Arc::new(|id: &str| {
+            id.trim_start_matches("service:").to_string()
+        })),
+        ..Default::default()
+    };
+    let c = transit_model::gtfs::Configuration {
+        calendar_configuration,
+        ..Default::default()
+    };
+    let model = transit_model::gtfs::Reader::new(c)
+        .parse("tests/fixtures/gtfs")
+        .unwrap();
+    assert!(model.calendars.contains_id("1"));
+    // the service ids of trips.txt are normalized as the ones of calendar_dates.txt
+    assert_eq!(
+        model.vehicle_journeys.get("trip:3").unwrap().service_id,
+        "1"
+    );
+    assert_eq!(
+        model.vehicle_journeys.get("trip:5").unwrap().service_id,
+        "2"
+    );
+}
@@ -125,6 +125,93 @@ fn directory_not_a_ntfs() {
     let _ = transit_model::ntfs::read("tests/fixtures/netex_france").unwrap();
 }
 
+#[test]
+fn read_ntfs_collections() {
+    let collections = transit_model::ntfs::read_ntfs("tests/fixtures/minimal_ntfs/").unwrap();
+    assert_eq!(6, collections.vehicle_journeys.len());
+    assert!(!collections.calendars.is_empty());
+}
+
+#[test]
+#[should_panic(
+    expected = "file \"tests/fixtures/ntfs/stops.txt\" is not a directory, cannot read a ntfs from it"
+)]
+fn read_ntfs_not_a_directory() {
+    let _ = transit_model::ntfs::read_ntfs("tests/fixtures/ntfs/stops.txt").unwrap();
+}
+
+#[test]
+fn read_ntfs_with_unresolved_reference() {
+    test_in_tmp_dir(|path| {
+        for entry in std::fs::read_dir("tests/fixtures/minimal_ntfs").unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), path.join(entry.file_name())).unwrap();
+        }
+        create_file_with_content(
+            path,
+            "routes.txt",
+            "route_id,route_name,line_id\nM1F,Nation - Charles de Gaulle,unknown",
+        );
+        let error = transit_model::ntfs::read_ntfs(path).unwrap_err();
+        assert!(error.to_string().contains("unresolved references"));
+    });
+}
+
+#[test]
+fn read_and_write_ntfs_with_declared_days() {
+    // The weekly pattern starts on a Saturday, which the translation of the
+    // dates would move to the first Monday
+    let calendar_content =
+        "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+         Week,1,1,1,1,1,0,0,20180106,20181231\n";
+    test_in_tmp_dir(|path| {
+        let input_dir = path.join("input");
+        let output_dir = path.join("output");
+        std::fs::create_dir(&input_dir).unwrap();
+        for entry in std::fs::read_dir("tests/fixtures/minimal_ntfs").unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), input_dir.join(entry.file_name())).unwrap();
+        }
+        create_file_with_content(&input_dir, "calendar.txt", calendar_content);
+        let config = transit_model::calendars::ReadConfiguration {
+            keep_declared_days: true,
+            ..Default::default()
+        };
+        let collections =
+            transit_model::ntfs::read_ntfs_with_configuration(&input_dir, &config).unwrap();
+        let model = Model::new(collections).unwrap();
+        transit_model::ntfs::write(&model, &output_dir, get_test_datetime()).unwrap();
+        assert_eq!(
+            calendar_content,
+            std::fs::read_to_string(output_dir.join("calendar.txt")).unwrap()
+        );
+    });
+}
+
+#[test]
+fn validate_calendar_bounds_with_ntfs_feed_infos() {
+    let feed_infos_content = "feed_info_param,feed_info_value\n\
+                              ntfs_version,0.10.0\n\
+                              feed_start_date,20180101\n\
+                              feed_end_date,20181228\n";
+    test_in_tmp_dir(|path| {
+        for entry in std::fs::read_dir("tests/fixtures/minimal_ntfs").unwrap() {
+            let entry = entry.unwrap();
+            std::fs::copy(entry.path(), path.join(entry.file_name())).unwrap();
+        }
+        create_file_with_content(path, "feed_infos.txt", feed_infos_content);
+        let collections = transit_model::ntfs::read_ntfs(path).unwrap();
+        // The last Monday is after the feed_end_date, though in the dataset
+        assert_eq!(
+            vec![(
+                "Week".to_string(),
+                Date::from_ymd_opt(2018, 12, 31).unwrap()
+            )],
+            collections.validate_calendar_bounds()
+        );
+    });
+}
+
 #[test]
 fn ntfs_stop_zones() {
     let ntm = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/").unwrap();
@@ -338,6 +425,43 @@ fn optional_empty_collections_not_created() {
     });
 }
 
+#[test]
+fn zip_writer_same_as_directory() {
+    use std::io::Read;
+    let ntm = transit_model::ntfs::read("tests/fixtures/ntfs/").unwrap();
+    test_in_tmp_dir(|path| {
+        transit_model::ntfs::write(&ntm, path, get_test_datetime()).unwrap();
+        let cursor = transit_model::ntfs::write_to_zip_writer(
+            &ntm,
+            std::io::Cursor::new(Vec::new()),
+            get_test_datetime(),
+        )
+        .unwrap();
+        let mut archive = zip::ZipArchive::new(cursor).unwrap();
+
+        let mut file_names: Vec<String> = ::std::fs::read_dir(path)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        file_names.sort();
+        let mut entry_names: Vec<String> = archive.file_names().map(String::from).collect();
+        entry_names.sort();
+        assert_eq!(file_names, entry_names);
+        for file_name in file_names {
+            let mut content = String::new();
+            archive
+                .by_name(&file_name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            assert_eq!(
+                ::std::fs::read_to_string(path.join(&file_name)).unwrap(),
+                content
+            );
+        }
+    });
+}
+
 #[test]
 fn preserve_frequencies() {
     let ntm = transit_model::ntfs::read("tests/fixtures/ntfs/").unwrap();